@@ -40,6 +40,7 @@ fn main() {
         .read(true)
         .write(true)
         .create(true)
+        .truncate(true)
         .open("ppm/chapter-02.ppm");
 
     fs::write("ppm/chapter-02.ppm", ppm).unwrap();
@@ -0,0 +1,324 @@
+use crate::group::Group;
+use crate::mesh::TriangleMesh;
+use crate::shape::Shape;
+use crate::tuple::{Point, Vector};
+use std::io::{Error, ErrorKind, Read};
+
+/// Highest number of divisions per side when tessellating a patch.
+const MAX_DIVISIONS: usize = 64;
+
+/// Bicubic Bézier patch. control_points[i][j] is the control point of row i, along v,
+/// and column j, along u.
+#[derive(Debug, Clone, Copy)]
+pub struct BezierPatch {
+    pub control_points: [[Point; 4]; 4],
+}
+
+/// The cubic Bernstein polynomials at t.
+fn bernstein(t: f64) -> [f64; 4] {
+    let s = 1.0 - t;
+    [s * s * s, 3.0 * t * s * s, 3.0 * t * t * s, t * t * t]
+}
+
+/// The derivatives of the cubic Bernstein polynomials at t.
+fn bernstein_derivative(t: f64) -> [f64; 4] {
+    let s = 1.0 - t;
+    [
+        -3.0 * s * s,
+        3.0 * s * s - 6.0 * t * s,
+        6.0 * t * s - 3.0 * t * t,
+        3.0 * t * t,
+    ]
+}
+
+impl BezierPatch {
+    /// Return a new BezierPatch.
+    pub fn new(control_points: [[Point; 4]; 4]) -> Self {
+        BezierPatch { control_points }
+    }
+
+    /// Sum of the control points, as offsets from the origin, weighted by bu along u and bv along v.
+    fn weighted_sum(&self, bu: [f64; 4], bv: [f64; 4]) -> Vector {
+        let origin = Point::new(0, 0, 0);
+        let mut sum = Vector::new(0, 0, 0);
+        for (i, row) in self.control_points.iter().enumerate() {
+            for (j, &p) in row.iter().enumerate() {
+                sum = sum + (p - origin) * (bv[i] * bu[j]);
+            }
+        }
+        sum
+    }
+
+    /// Returns the point of the surface at parameters u, v in [0, 1].
+    pub fn point_at(&self, u: f64, v: f64) -> Point {
+        Point::new(0, 0, 0) + self.weighted_sum(bernstein(u), bernstein(v))
+    }
+
+    /// Returns the surface normal at parameters u, v: the cross product of the partial
+    /// derivatives along u and v.
+    pub fn normal_at(&self, u: f64, v: f64) -> Vector {
+        let du = self.weighted_sum(bernstein_derivative(u), bernstein(v));
+        let dv = self.weighted_sum(bernstein(u), bernstein_derivative(v));
+        let normal = du.cross(&dv);
+        if normal.magnitude() > 1e-12 {
+            return normal.normalize();
+        }
+        // A degenerate edge, like the tip of the Utah teapot lid, collapses one partial
+        // derivative; use the normal a little closer to the middle of the patch instead.
+        let nudge = |t: f64| t + (0.5 - t) * 1e-3;
+        let du = self.weighted_sum(bernstein_derivative(nudge(u)), bernstein(nudge(v)));
+        let dv = self.weighted_sum(bernstein(nudge(u)), bernstein_derivative(nudge(v)));
+        du.cross(&dv).normalize()
+    }
+
+    /// Tessellate the patch into a (divisions + 1)^2 grid of vertices with exact normals,
+    /// and the u, v of the patch as texture coordinates.
+    pub fn tessellate_uniform(&self, divisions: usize) -> TriangleMesh {
+        let n = divisions.max(1);
+        let mut vertices = Vec::with_capacity((n + 1) * (n + 1));
+        let mut normals = Vec::with_capacity((n + 1) * (n + 1));
+        let mut uvs = Vec::with_capacity((n + 1) * (n + 1));
+        for j in 0..=n {
+            for i in 0..=n {
+                let (u, v) = (i as f64 / n as f64, j as f64 / n as f64);
+                vertices.push(self.point_at(u, v));
+                normals.push(self.normal_at(u, v));
+                uvs.push((u, v));
+            }
+        }
+
+        let mut faces = Vec::with_capacity(2 * n * n);
+        for j in 0..n {
+            for i in 0..n {
+                let a = j * (n + 1) + i;
+                let (b, c, d) = (a + 1, a + n + 2, a + n + 1);
+                faces.push([a, b, c]);
+                faces.push([a, c, d]);
+            }
+        }
+
+        let mut mesh = TriangleMesh::new(vertices, faces);
+        mesh.normals = Some(normals);
+        mesh.uvs = Some(uvs);
+        mesh
+    }
+
+    /// Tessellate the patch finely enough for the triangles to stay within roughly
+    /// tolerance of the surface. Flat patches get few triangles, curved ones many.
+    pub fn tessellate(&self, tolerance: f64) -> TriangleMesh {
+        // The distance between a cubic curve and its chords over n segments is bounded by
+        // its largest second difference times 3 / (4 n^2).
+        let cp = &self.control_points;
+        let mut curvature = 0.0f64;
+        for (i, row) in cp.iter().enumerate() {
+            for j in 1..3 {
+                let along_u = (row[j - 1] - row[j]) - (row[j] - row[j + 1]);
+                let along_v = (cp[j - 1][i] - cp[j][i]) - (cp[j][i] - cp[j + 1][i]);
+                curvature = curvature.max(along_u.magnitude()).max(along_v.magnitude());
+            }
+        }
+        let divisions = (0.75 * curvature / tolerance).sqrt().ceil() as usize;
+        self.tessellate_uniform(divisions.clamp(1, MAX_DIVISIONS))
+    }
+}
+
+/// Tessellate the patches into a Group holding one bounded Group of smooth triangles
+/// per patch.
+pub fn patches_to_group(patches: &[BezierPatch], tolerance: f64) -> Group {
+    let children = patches
+        .iter()
+        .map(|patch| Box::new(patch.tessellate(tolerance).to_group()) as Box<dyn Shape>)
+        .collect();
+    Group::new(children)
+}
+
+/// Parse patches in the `.bpt` format used for the Utah teapot and similar models: the
+/// number of patches, then for each patch its degrees along u and v followed by its
+/// control points, one `x y z` per line. Only bicubic patches (degrees 3 3) are supported.
+pub fn parse_bpt(mut reader: impl Read) -> std::io::Result<Vec<BezierPatch>> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    let mut tokens = text.split_ascii_whitespace();
+    let count = tokens
+        .next()
+        .and_then(|token| token.parse::<usize>().ok())
+        .ok_or_else(|| invalid_data("invalid patch count in BPT file"))?;
+    let mut numbers = tokens.map(|token| {
+        token
+            .parse::<f64>()
+            .map_err(|_| invalid_data(&format!("invalid number '{}' in BPT file", token)))
+    });
+    let mut next = || {
+        numbers
+            .next()
+            .unwrap_or_else(|| Err(invalid_data("unexpected end of BPT file")))
+    };
+
+    let mut patches = Vec::new();
+    for _ in 0..count {
+        let (degree_u, degree_v) = (next()?, next()?);
+        if degree_u != 3.0 || degree_v != 3.0 {
+            return Err(invalid_data("only bicubic BPT patches are supported"));
+        }
+        let mut control_points = [[Point::new(0, 0, 0); 4]; 4];
+        for row in control_points.iter_mut() {
+            for point in row.iter_mut() {
+                *point = Point::new(next()?, next()?, next()?);
+            }
+        }
+        patches.push(BezierPatch::new(control_points));
+    }
+    if numbers.next().is_some() {
+        return Err(invalid_data("more patches in BPT file than its count"));
+    }
+
+    Ok(patches)
+}
+
+fn invalid_data(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray::Ray;
+
+    /// Flat patch covering x and z from 0 to 3 at y = 0.
+    fn flat_patch() -> BezierPatch {
+        let mut control_points = [[Point::new(0, 0, 0); 4]; 4];
+        for (i, row) in control_points.iter_mut().enumerate() {
+            for (j, point) in row.iter_mut().enumerate() {
+                *point = Point::new(j as f64, 0, i as f64);
+            }
+        }
+        BezierPatch::new(control_points)
+    }
+
+    /// Patch bulging up to y = 0.75 in its middle.
+    fn dome_patch() -> BezierPatch {
+        let mut patch = flat_patch();
+        for i in 1..3 {
+            for j in 1..3 {
+                patch.control_points[i][j] = Point::new(j as f64, 4.0 / 3.0, i as f64);
+            }
+        }
+        patch
+    }
+
+    /// A patch interpolates its corner control points.
+    #[test]
+    fn patch_corners() {
+        let p = dome_patch();
+        assert_eq!(p.point_at(0.0, 0.0), Point::new(0, 0, 0));
+        assert_eq!(p.point_at(1.0, 0.0), Point::new(3, 0, 0));
+        assert_eq!(p.point_at(0.0, 1.0), Point::new(0, 0, 3));
+        assert_eq!(p.point_at(1.0, 1.0), Point::new(3, 0, 3));
+        assert_eq!(p.point_at(0.5, 0.5), Point::new(1.5, 0.75, 1.5));
+    }
+
+    /// The normal of a patch follows the cross product of its u and v derivatives.
+    #[test]
+    fn patch_normal() {
+        let p = flat_patch();
+        assert_eq!(p.normal_at(0.3, 0.6), Vector::new(0, -1, 0));
+        assert_eq!(dome_patch().normal_at(0.5, 0.5), Vector::new(0, -1, 0));
+    }
+
+    /// The normal at a collapsed edge of a patch is still defined.
+    #[test]
+    fn patch_degenerate_normal() {
+        let mut p = dome_patch();
+        // Collapse the first row into a single point, like the tip of a teapot lid.
+        p.control_points[0] = [Point::new(1.5, 0, 0); 4];
+        let n = p.normal_at(0.5, 0.0);
+        assert!(n.x().is_finite() && n.y().is_finite() && n.z().is_finite());
+        assert!(n.y() < 0.0);
+    }
+
+    /// Tessellating a patch uniformly.
+    #[test]
+    fn tessellate_uniform_patch() {
+        let mesh = dome_patch().tessellate_uniform(4);
+        assert_eq!(mesh.vertices.len(), 25);
+        assert_eq!(mesh.faces.len(), 32);
+        assert_eq!(mesh.vertices[12], Point::new(1.5, 0.75, 1.5));
+        assert_eq!(mesh.normals.as_ref().unwrap()[12], Vector::new(0, -1, 0));
+        assert_eq!(mesh.uvs.as_ref().unwrap()[7], (0.5, 0.25));
+        // Faces are wound the same way as the patch normal.
+        assert!(mesh.face_normal(0).y() < 0.0);
+    }
+
+    /// Adaptive tessellation spends triangles on curved patches only.
+    #[test]
+    fn tessellate_adaptive_patch() {
+        assert_eq!(flat_patch().tessellate(0.01).faces.len(), 2);
+        let coarse = dome_patch().tessellate(0.1).faces.len();
+        let fine = dome_patch().tessellate(0.001).faces.len();
+        assert!(coarse > 2 && fine > coarse);
+    }
+
+    /// A ray hits the tessellated surface close to the exact patch.
+    #[test]
+    fn ray_hits_tessellated_patch() {
+        let group = dome_patch().tessellate(1e-3).to_group();
+        let r = Ray::new(Point::new(1.4, 5, 1.3), Vector::new(0, -1, 0));
+        let xs = group.local_intersect(&r);
+        assert_eq!(xs.len(), 1);
+        // On this patch x = 3u, z = 3v and y = 12 u (1 - u) v (1 - v).
+        let (u, v) = (1.4 / 3.0, 1.3 / 3.0);
+        let y = 12.0 * u * (1.0 - u) * v * (1.0 - v);
+        assert!((xs[0].t - (5.0 - y)).abs() < 1e-3);
+    }
+
+    /// A group of patches is intersected patch by patch.
+    #[test]
+    fn ray_hits_patch_group() {
+        let mut shifted = flat_patch();
+        for row in shifted.control_points.iter_mut() {
+            for p in row.iter_mut() {
+                *p = *p + Vector::new(10, 0, 0);
+            }
+        }
+        let group = patches_to_group(&[dome_patch(), shifted], 1e-2);
+        assert_eq!(group.children.len(), 2);
+        let r = Ray::new(Point::new(11.5, 5, 0.7), Vector::new(0, -1, 0));
+        let xs = group.local_intersect(&r);
+        assert_eq!(xs.len(), 1);
+        assert!((xs[0].t - 5.0).abs() < 1e-9);
+    }
+
+    /// Parsing a BPT file.
+    #[test]
+    fn parse_bpt_file() {
+        let mut bpt = String::from("2\n");
+        for offset in [0, 10] {
+            bpt.push_str("3 3\n");
+            for i in 0..4 {
+                for j in 0..4 {
+                    bpt.push_str(&format!("{} {} {}\n", j + offset, 0, i));
+                }
+            }
+        }
+        let patches = parse_bpt(bpt.as_bytes()).unwrap();
+        assert_eq!(patches.len(), 2);
+        assert_eq!(patches[0].control_points[1][2], Point::new(2, 0, 1));
+        assert_eq!(patches[1].control_points[3][0], Point::new(10, 0, 3));
+    }
+
+    /// Malformed BPT files are rejected.
+    #[test]
+    fn parse_invalid_bpt() {
+        assert!(parse_bpt("1\n3 3\n0 0 0\n".as_bytes()).is_err());
+        assert!(parse_bpt("1\n2 2\n".as_bytes()).is_err());
+        assert!(parse_bpt("x".as_bytes()).is_err());
+        for count in ["1e30", "2.5", "-1"] {
+            assert!(parse_bpt(format!("{count}\n3 3\n").as_bytes()).is_err());
+        }
+        let mut extra = String::from("1\n3 3\n");
+        extra.push_str(&"0 0 0\n".repeat(17));
+        assert!(parse_bpt(extra.as_bytes()).is_err());
+        extra.truncate(extra.len() - "0 0 0\n".len());
+        assert!(parse_bpt(extra.as_bytes()).is_ok());
+    }
+}
@@ -0,0 +1,217 @@
+use crate::polynomial::{multiply, real_roots};
+use crate::ray::Ray;
+use crate::shape::{Intersection, Shape};
+use crate::tuple::{Point, Vector};
+
+/// One center of a Blob. Its field is strength * (1 - d^2 / radius^2)^3 at a distance d
+/// from the center, falling smoothly to zero at radius.
+#[derive(Debug, Clone, Copy)]
+pub struct Metaball {
+    pub center: Point,
+    pub radius: f64,
+    pub strength: f64,
+}
+
+impl Metaball {
+    /// Return a new Metaball.
+    pub fn new(center: Point, radius: f64, strength: f64) -> Self {
+        Metaball {
+            center,
+            radius,
+            strength,
+        }
+    }
+
+    /// Returns the field of the metaball at a point.
+    pub fn field_at(&self, point: Point) -> f64 {
+        let q = (point - self.center).dot(&(point - self.center)) / (self.radius * self.radius);
+        if q >= 1.0 {
+            0.0
+        } else {
+            self.strength * (1.0 - q).powi(3)
+        }
+    }
+}
+
+/// Implicit surface where the summed field of its metaballs equals threshold.
+/// The threshold must be positive.
+#[derive(Debug, Clone)]
+pub struct Blob {
+    pub balls: Vec<Metaball>,
+    pub threshold: f64,
+}
+
+impl Blob {
+    /// Return a new Blob.
+    pub fn new(balls: Vec<Metaball>, threshold: f64) -> Self {
+        Blob { balls, threshold }
+    }
+
+    /// Returns the summed field of all metaballs at a point.
+    pub fn field_at(&self, point: Point) -> f64 {
+        self.balls.iter().map(|ball| ball.field_at(point)).sum()
+    }
+}
+
+impl Shape for Blob {
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let length = ray.direction.magnitude();
+        let d = ray.direction / length;
+
+        // Along the ray, the field of a metaball is a polynomial of degree 6 in the distance s
+        // between the two points where the ray crosses its sphere of influence, and zero
+        // elsewhere. Between consecutive crossings the summed field is a single polynomial.
+        let mut spans = vec![];
+        for ball in &self.balls {
+            let oc = ray.origin - ball.center;
+            let r2 = ball.radius * ball.radius;
+            let b = oc.dot(&d);
+            let discriminant = b * b - (oc.dot(&oc) - r2);
+            if discriminant <= 0.0 {
+                continue;
+            }
+            let (near, far) = (-b - discriminant.sqrt(), -b + discriminant.sqrt());
+
+            // 1 - |oc + s * d|^2 / r^2
+            let falloff = [1.0 - oc.dot(&oc) / r2, -2.0 * b / r2, -1.0 / r2];
+            let cube = multiply(&falloff, &multiply(&falloff, &falloff));
+            let field: Vec<f64> = cube.iter().map(|c| c * ball.strength).collect();
+            spans.push((near, far, field));
+        }
+
+        let mut events: Vec<f64> = spans.iter().flat_map(|(n, f, _)| [*n, *f]).collect();
+        events.sort_by(|a, b| a.total_cmp(b));
+
+        let mut xs = vec![];
+        let mut root_at_previous_end = false;
+        for window in events.windows(2) {
+            let (start, end) = (window[0], window[1]);
+            if end <= start {
+                continue;
+            }
+            let middle = 0.5 * (start + end);
+            let mut field = vec![-self.threshold];
+            for (near, far, ball_field) in &spans {
+                if *near < middle && middle < *far {
+                    field.resize(field.len().max(ball_field.len()), 0.0);
+                    for (f, b) in field.iter_mut().zip(ball_field) {
+                        *f += b;
+                    }
+                }
+            }
+            let roots = real_roots(&field, start, end);
+            for &s in &roots {
+                // A root on the boundary of two spans is found by both of them.
+                if root_at_previous_end && s - start < 1e-9 {
+                    root_at_previous_end = false;
+                    continue;
+                }
+                xs.push(Intersection::new(s / length, self));
+            }
+            root_at_previous_end = roots.last().is_some_and(|&s| end - s < 1e-9);
+        }
+
+        xs
+    }
+
+    fn local_normal_at(&self, point: Point, _hit: &Intersection) -> Vector {
+        // The field decreases outwards, so the normal is the negated gradient.
+        let mut normal = Vector::new(0, 0, 0);
+        for ball in &self.balls {
+            let offset = point - ball.center;
+            let r2 = ball.radius * ball.radius;
+            let q = offset.dot(&offset) / r2;
+            if q < 1.0 {
+                normal = normal + offset * (6.0 * ball.strength * (1.0 - q).powi(2) / r2);
+            }
+        }
+        normal.normalize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approximate_equation::ApproximateEq;
+
+    /// Distance from the center at which a single ball of radius 2 and strength 1
+    /// reaches a threshold of 0.5.
+    fn surface_radius() -> f64 {
+        (4.0 * (1.0 - 0.5f64.cbrt())).sqrt()
+    }
+
+    /// The field of a metaball vanishes at its radius.
+    #[test]
+    fn metaball_field() {
+        let ball = Metaball::new(Point::new(1, 0, 0), 2.0, 3.0);
+        assert_eq!(ball.field_at(Point::new(1, 0, 0)), 3.0);
+        assert!(ball
+            .field_at(Point::new(2, 0, 0))
+            .approx_eq(&(3.0 * 0.75f64.powi(3))));
+        assert_eq!(ball.field_at(Point::new(3, 0, 0)), 0.0);
+        assert_eq!(ball.field_at(Point::new(5, 0, 0)), 0.0);
+    }
+
+    /// A ray intersects a blob made of a single metaball.
+    #[test]
+    fn ray_intersects_single_ball() {
+        let blob = Blob::new(vec![Metaball::new(Point::new(0, 0, 0), 2.0, 1.0)], 0.5);
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 2));
+        let xs = blob.local_intersect(&r);
+        assert_eq!(xs.len(), 2);
+        assert!(xs[0].t.approx_eq(&((5.0 - surface_radius()) / 2.0)));
+        assert!(xs[1].t.approx_eq(&((5.0 + surface_radius()) / 2.0)));
+    }
+
+    /// Two metaballs too far apart to touch on their own merge into one surface.
+    #[test]
+    fn metaballs_merge() {
+        let balls = vec![
+            Metaball::new(Point::new(-1.2, 0, 0), 2.0, 1.0),
+            Metaball::new(Point::new(1.2, 0, 0), 2.0, 1.0),
+        ];
+        assert!(surface_radius() < 1.2);
+        let r = Ray::new(Point::new(0, -5, 0), Vector::new(0, 1, 0));
+
+        let blob = Blob::new(balls.clone(), 0.5);
+        let xs = blob.local_intersect(&r);
+        assert_eq!(xs.len(), 2);
+        assert!(xs[0].t < 5.0 && xs[1].t > 5.0);
+
+        let blob = Blob::new(balls, 0.6);
+        assert!(blob.local_intersect(&r).is_empty());
+    }
+
+    /// A ray through two separate metaballs hits each of them twice.
+    #[test]
+    fn ray_through_separate_balls() {
+        let blob = Blob::new(
+            vec![
+                Metaball::new(Point::new(-3, 0, 0), 2.0, 1.0),
+                Metaball::new(Point::new(3, 0, 0), 2.0, 1.0),
+            ],
+            0.5,
+        );
+        let r = Ray::new(Point::new(-10, 0, 0), Vector::new(1, 0, 0));
+        let ts: Vec<f64> = blob.local_intersect(&r).iter().map(|x| x.t).collect();
+        let expected = [
+            7.0 - surface_radius(),
+            7.0 + surface_radius(),
+            13.0 - surface_radius(),
+            13.0 + surface_radius(),
+        ];
+        assert_eq!(ts.len(), 4);
+        for (t, e) in ts.iter().zip(expected.iter()) {
+            assert!(t.approx_eq(e));
+        }
+    }
+
+    /// The normal on a blob points away from its metaballs.
+    #[test]
+    fn normal_blob() {
+        let blob = Blob::new(vec![Metaball::new(Point::new(1, 2, 3), 2.0, 1.0)], 0.5);
+        let hit = Intersection::new(0.0, &blob);
+        let point = Point::new(1, 2, 3) + Vector::new(surface_radius(), 0, 0);
+        assert_eq!(blob.local_normal_at(point, &hit), Vector::new(1, 0, 0));
+    }
+}
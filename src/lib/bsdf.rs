@@ -0,0 +1,35 @@
+use crate::color::Color;
+use crate::random::RandomSource;
+use crate::tuple::Vector;
+
+/// Direction chosen by Bsdf::sample.
+#[derive(Debug, Clone, Copy)]
+pub struct BsdfSample {
+    /// Unit vector from the surface toward where the light comes from.
+    pub direction: Vector,
+    /// The BSDF times the cosine of direction with the normal, divided by pdf: the
+    /// factor by which the light arriving along direction is scaled.
+    pub weight: Color,
+    /// Probability density of the direction per unit solid angle. Not meaningful for
+    /// specular samples.
+    pub pdf: f64,
+    /// True for perfectly sharp reflections, which lights cannot be sampled toward.
+    pub specular: bool,
+}
+
+/// How a surface scatters light, for the path tracer.
+///
+/// wo is the unit vector from the surface toward the viewer, wi toward where the light
+/// comes from, and normal is the unit surface normal on the side of wo.
+pub trait Bsdf {
+    /// Returns the BSDF times the cosine of wi with the normal. Specular lobes, which
+    /// only reflect in a single direction, are not included.
+    fn evaluate(&self, wo: Vector, wi: Vector, normal: Vector) -> Color;
+
+    /// Returns the probability density with which sample would choose wi.
+    fn pdf(&self, wo: Vector, wi: Vector, normal: Vector) -> f64;
+
+    /// Returns a direction to continue the path in, chosen roughly in proportion to the
+    /// light it reflects, or None when the path is absorbed.
+    fn sample(&self, wo: Vector, normal: Vector, rng: &mut dyn RandomSource) -> Option<BsdfSample>;
+}
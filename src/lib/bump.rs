@@ -0,0 +1,240 @@
+use crate::tuple::{Point, Vector};
+use crate::uv::{UvMapping, UvPattern};
+use std::fmt;
+use std::fmt::Debug;
+use std::rc::Rc;
+
+/// Distance between the points at which heights and texture coordinates are compared
+/// to find their slope, in object space.
+const EPSILON: f64 = 1e-4;
+
+/// Height of a bumpy surface above an object space point, in object space units.
+pub type Height = Rc<dyn Fn(Point) -> f64>;
+
+/// The object space surface around a point being shaded.
+#[derive(Debug, Clone, Copy)]
+pub struct SurfaceFrame {
+    pub point: Point,
+    /// Unit normal of the geometry.
+    pub normal: Vector,
+    /// Texture coordinates of the shape, for shapes which carry them.
+    pub uv: Option<(f64, f64)>,
+    /// Tangent of the shape pointing toward increasing u, for shapes which carry one,
+    /// with the handedness of the texture coordinates: 1 where v increases along
+    /// normal × tangent and -1 where the texture is mirrored.
+    pub tangent: Option<(Vector, f64)>,
+}
+
+/// Fine detail of a surface, shown by tilting the normal used for shading rather than by
+/// moving the geometry.
+pub trait Bump: Debug {
+    /// Returns the object space unit normal to shade surface with.
+    fn normal_at(&self, surface: &SurfaceFrame) -> Vector;
+}
+
+/// Bumps raised along the normal by a height function, such as noise.
+#[derive(Clone)]
+pub struct BumpMap {
+    pub height: Height,
+    /// Scales the height, and so the slopes of the bumps.
+    pub strength: f64,
+}
+
+impl BumpMap {
+    /// Return a new BumpMap raising the surface by height.
+    pub fn new(height: Height) -> Self {
+        BumpMap {
+            height,
+            strength: 1.0,
+        }
+    }
+}
+
+impl Debug for BumpMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BumpMap")
+            .field("strength", &self.strength)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Bump for BumpMap {
+    fn normal_at(&self, surface: &SurfaceFrame) -> Vector {
+        let p = surface.point;
+        let slope = |offset: Vector| {
+            ((self.height)(p + offset) - (self.height)(p - offset)) / (2.0 * EPSILON)
+        };
+        let gradient = Vector::new(
+            slope(Vector::new(EPSILON, 0, 0)),
+            slope(Vector::new(0, EPSILON, 0)),
+            slope(Vector::new(0, 0, EPSILON)),
+        );
+        // Only the slope along the surface tilts the normal, away from the way up.
+        let n = surface.normal;
+        let along_surface = gradient - n * gradient.dot(&n);
+        (n - along_surface * self.strength).normalize()
+    }
+}
+
+/// Normals read from an image in tangent space, as baked from detailed models: red,
+/// green and blue from 0 to 1 give the normal along u, along v and out of the surface
+/// from -1 to 1.
+///
+/// Texture coordinates and tangents come from the shape when it has them, as meshes
+/// with texture coordinates and tangents do, and from mapping otherwise.
+#[derive(Debug, Clone)]
+pub struct NormalMap {
+    pub texture: Rc<dyn UvPattern>,
+    pub mapping: UvMapping,
+    /// Scales the tilt of the normals: 0 leaves the surface flat.
+    pub strength: f64,
+}
+
+impl NormalMap {
+    /// Return a new NormalMap.
+    pub fn new(texture: Rc<dyn UvPattern>, mapping: UvMapping) -> Self {
+        NormalMap {
+            texture,
+            mapping,
+            strength: 1.0,
+        }
+    }
+
+    /// Returns the direction in which u of mapping increases along the surface.
+    fn mapped_tangent(&self, point: Point, normal: Vector) -> Vector {
+        let slope = |offset: Vector| {
+            let change = self.mapping.map(point + offset).0 - self.mapping.map(point - offset).0;
+            // Across the seam, where u wraps around, the change is off by a whole turn.
+            (change - change.round()) / (2.0 * EPSILON)
+        };
+        let gradient = Vector::new(
+            slope(Vector::new(EPSILON, 0, 0)),
+            slope(Vector::new(0, EPSILON, 0)),
+            slope(Vector::new(0, 0, EPSILON)),
+        );
+        gradient - normal * gradient.dot(&normal)
+    }
+}
+
+impl Bump for NormalMap {
+    fn normal_at(&self, surface: &SurfaceFrame) -> Vector {
+        let (n, p) = (surface.normal, surface.point);
+        let (u, v) = surface.uv.unwrap_or_else(|| self.mapping.map(p));
+        let (tangent, sign) = surface
+            .tangent
+            .unwrap_or_else(|| (self.mapped_tangent(p, n), 1.0));
+        let tangent = tangent - n * tangent.dot(&n);
+        let (tangent, bitangent) = if tangent.magnitude() > 1e-12 {
+            let tangent = tangent.normalize();
+            (tangent, n.cross(&tangent) * sign)
+        } else {
+            // At the poles of a mapping u has no direction.
+            n.orthonormal_basis()
+        };
+
+        let color = self.texture.uv_color_at(u, v);
+        let x = (color.red() * 2.0 - 1.0) * self.strength;
+        let y = (color.green() * 2.0 - 1.0) * self.strength;
+        let z = color.blue() * 2.0 - 1.0;
+        (tangent * x + bitangent * y + n * z).normalize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+
+    #[derive(Debug)]
+    struct Solid(Color);
+
+    impl UvPattern for Solid {
+        fn uv_color_at(&self, _u: f64, _v: f64) -> Color {
+            self.0
+        }
+    }
+
+    fn frame(point: Point, normal: Vector) -> SurfaceFrame {
+        SurfaceFrame {
+            point,
+            normal,
+            uv: None,
+            tangent: None,
+        }
+    }
+
+    /// A slope tilts the normal away from the way the surface rises, and a constant
+    /// height leaves it alone.
+    #[test]
+    fn bump_map_slope() {
+        let up = frame(Point::new(0.3, 0, 0.2), Vector::new(0, 1, 0));
+        let flat = BumpMap::new(Rc::new(|_| 0.5));
+        assert_eq!(flat.normal_at(&up), Vector::new(0, 1, 0));
+
+        let ramp = BumpMap::new(Rc::new(|p: Point| p.x()));
+        assert_eq!(ramp.normal_at(&up), Vector::new(-1, 1, 0).normalize());
+        let flattened = BumpMap {
+            strength: 0.0,
+            ..ramp.clone()
+        };
+        assert_eq!(flattened.normal_at(&up), Vector::new(0, 1, 0));
+        // The height changes along the normal do not tilt it.
+        let side = frame(Point::new(0, 0, 0), Vector::new(1, 0, 0));
+        assert_eq!(ramp.normal_at(&side), Vector::new(1, 0, 0));
+    }
+
+    /// A flat normal map color keeps the normal, and others tilt it along the tangent
+    /// and bitangent.
+    #[test]
+    fn normal_map_tangent_space() {
+        let map = |color| NormalMap::new(Rc::new(Solid(color)), UvMapping::Planar);
+        let surface = SurfaceFrame {
+            uv: Some((0.5, 0.5)),
+            tangent: Some((Vector::new(0, 0, 2), 1.0)),
+            ..frame(Point::new(0, 0, 0), Vector::new(1, 0, 0))
+        };
+        let flat = map(Color::new(0.5, 0.5, 1));
+        assert_eq!(flat.normal_at(&surface), Vector::new(1, 0, 0));
+        let along_u = map(Color::new(1, 0.5, 0.5));
+        assert_eq!(along_u.normal_at(&surface), Vector::new(0, 0, 1));
+        // The bitangent completes a right handed frame, unless the texture is mirrored.
+        let along_v = map(Color::new(0.5, 1, 0.5));
+        assert_eq!(along_v.normal_at(&surface), Vector::new(0, -1, 0));
+        let mirrored = SurfaceFrame {
+            tangent: Some((Vector::new(0, 0, 2), -1.0)),
+            ..surface
+        };
+        assert_eq!(along_v.normal_at(&mirrored), Vector::new(0, 1, 0));
+        assert_eq!(along_u.normal_at(&mirrored), Vector::new(0, 0, 1));
+
+        let weak = NormalMap {
+            strength: 0.0,
+            ..map(Color::new(1, 0.5, 1))
+        };
+        assert_eq!(weak.normal_at(&surface), Vector::new(1, 0, 0));
+    }
+
+    /// Without tangents from the shape, u of the mapping gives the tangent, even across
+    /// its seam.
+    #[test]
+    fn normal_map_mapped_tangent() {
+        let map = NormalMap::new(
+            Rc::new(Solid(Color::new(1, 0.5, 0.5))),
+            UvMapping::Spherical,
+        );
+        // u starts and ends at -z, going around y toward +x.
+        let seam = Point::new(0, 0, -1);
+        assert_eq!(
+            map.normal_at(&frame(seam, Vector::new(0, 0, -1))),
+            Vector::new(1, 0, 0)
+        );
+        let side = Point::new(1, 0, 0);
+        assert_eq!(
+            map.normal_at(&frame(side, Vector::new(1, 0, 0))),
+            Vector::new(0, 0, 1)
+        );
+        // At the poles any tangent will do.
+        let pole = map.normal_at(&frame(Point::new(0, 1, 0), Vector::new(0, 1, 0)));
+        assert!(pole.y().abs() < 1e-9);
+    }
+}
@@ -19,6 +19,14 @@ impl Canvas {
         }
     }
 
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
     /// Returns an iterator which copies all of its elements.
     /// Same as:
     ///
@@ -30,7 +38,7 @@ impl Canvas {
 
     /// Set color to specific pixel in the canvas.
     pub fn write_pixel(&mut self, x: i32, y: i32, color: Color) {
-        self.rows_mut().skip(y as usize).next().unwrap()[x as usize] = color;
+        self.rows_mut().nth(y as usize).unwrap()[x as usize] = color;
     }
 
     pub fn set_all_pixel(&mut self, c: Color) {
@@ -61,8 +69,7 @@ impl Canvas {
     pub fn pixel_at(&self, x: i32, y: i32) -> Color {
         self.data
             .chunks_exact(self.width as usize)
-            .skip(y as usize)
-            .next()
+            .nth(y as usize)
             .unwrap()[x as usize]
     }
 
@@ -74,6 +81,66 @@ impl Canvas {
         Ok(())
     }
 
+    /// Read a canvas from a PPM (P3, P6) or PGM (P2, P5) image.
+    /// Gray levels of a PGM image are read into all three channels.
+    pub fn from_ppm(mut reader: impl std::io::Read) -> std::io::Result<Self> {
+        let mut data = Vec::<u8>::new();
+        reader.read_to_end(&mut data)?;
+        let mut tokens = PpmTokens {
+            data: &data,
+            position: 0,
+        };
+
+        let (channels, binary) = match tokens.next_token()? {
+            b"P2" => (1, false),
+            b"P3" => (3, false),
+            b"P5" => (1, true),
+            b"P6" => (3, true),
+            _ => return Err(invalid_ppm("unsupported magic number")),
+        };
+        let width = tokens.next_number()?;
+        let height = tokens.next_number()?;
+        let max = tokens.next_number()?;
+        if max == 0 || max > 65535 {
+            return Err(invalid_ppm("invalid maximum color value"));
+        }
+        if binary {
+            // A single whitespace character separates the header from binary data.
+            tokens.position += 1;
+        }
+        // Every sample takes at least a byte, or two when binary and wide.
+        let sample_size = if binary && max > 255 { 2 } else { 1 };
+        let remaining = data.len().saturating_sub(tokens.position);
+        let (width, height) = image_size(width as usize, height as usize)
+            .filter(|&(width, height)| {
+                (width as usize * height as usize)
+                    .checked_mul(channels * sample_size)
+                    .is_some_and(|size| size <= remaining)
+            })
+            .ok_or_else(|| invalid_ppm("invalid image size"))?;
+
+        let mut canvas = Canvas::new(width, height);
+        let scale = 1.0 / max as f64;
+        for pixel in canvas.data.iter_mut() {
+            let mut sample = [0.0; 3];
+            for value in sample.iter_mut().take(channels) {
+                *value = if binary {
+                    tokens.next_binary(max > 255)?
+                } else {
+                    tokens.next_number()?
+                } as f64
+                    * scale;
+            }
+            *pixel = if channels == 1 {
+                Color::new(sample[0], sample[0], sample[0])
+            } else {
+                Color::new(sample[0], sample[1], sample[2])
+            };
+        }
+
+        Ok(canvas)
+    }
+
     /// The parameter struct MaxWidthWriter is replaced by impl std::io::Write
     /// since Vec<u8> can be replaced by T: std::io::Write for the member of the struct definition.
     // fn write_ppm_header(&self, line_guard: &mut MaxWidthWriter) -> std::io::Result<()> {
@@ -92,12 +159,70 @@ impl Canvas {
                 write!(line_guard, " {}", g)?;
                 write!(line_guard, " {}", b)?;
             }
-            write!(line_guard, "\n")?;
+            writeln!(line_guard)?;
         }
         Ok(())
     }
 }
 
+/// Splits the text of a PPM file into whitespace separated tokens, skipping comments.
+struct PpmTokens<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> PpmTokens<'a> {
+    fn next_token(&mut self) -> std::io::Result<&'a [u8]> {
+        loop {
+            match self.data.get(self.position) {
+                Some(b'#') => {
+                    while !matches!(self.data.get(self.position), Some(b'\n') | None) {
+                        self.position += 1;
+                    }
+                }
+                Some(c) if c.is_ascii_whitespace() => self.position += 1,
+                Some(_) => break,
+                None => return Err(invalid_ppm("unexpected end of file")),
+            }
+        }
+        let start = self.position;
+        while matches!(self.data.get(self.position), Some(c) if !c.is_ascii_whitespace()) {
+            self.position += 1;
+        }
+        Ok(&self.data[start..self.position])
+    }
+
+    fn next_number(&mut self) -> std::io::Result<u32> {
+        std::str::from_utf8(self.next_token()?)
+            .ok()
+            .and_then(|token| token.parse().ok())
+            .ok_or_else(|| invalid_ppm("invalid number"))
+    }
+
+    /// Read one binary sample, stored on two big endian bytes when wide.
+    fn next_binary(&mut self, wide: bool) -> std::io::Result<u32> {
+        let size = if wide { 2 } else { 1 };
+        let bytes = self
+            .data
+            .get(self.position..self.position + size)
+            .ok_or_else(|| invalid_ppm("unexpected end of file"))?;
+        self.position += size;
+        Ok(bytes.iter().fold(0, |acc, &b| acc << 8 | b as u32))
+    }
+}
+
+/// Returns the size of a Canvas for an image width by height pixels read from a file,
+/// or None if either is 0 or the image has too many pixels for a Canvas.
+pub(crate) fn image_size(width: usize, height: usize) -> Option<(u32, u32)> {
+    let (width, height) = (u32::try_from(width).ok()?, u32::try_from(height).ok()?);
+    let pixels = width.checked_mul(height)?;
+    (pixels > 0).then_some((width, height))
+}
+
+fn invalid_ppm(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
 // struct MaxWidthWriter<'a> {
 //     writer_vec: &'a mut Vec<u8>,
 //     line_buffer: Vec<u8>,
@@ -139,7 +264,7 @@ where
 
     pub fn flush_partial(&mut self, x: usize) -> std::io::Result<()> {
         self.line_buffer[x] = b'\n';
-        self.writer_vec.write(&self.line_buffer[..=x])?;
+        self.writer_vec.write_all(&self.line_buffer[..=x])?;
         // println!(
         //     "Before: {:?}",
         //     String::from_utf8(Vec::from(self.line_buffer.clone())).unwrap()
@@ -208,7 +333,7 @@ where
     }
 
     fn flush(&mut self) -> std::io::Result<()> {
-        self.writer_vec.write(&self.line_buffer)?;
+        self.writer_vec.write_all(&self.line_buffer)?;
         self.line_buffer.clear();
         self.writer_vec.flush()
     }
@@ -296,6 +421,153 @@ P3
         );
     }
 
+    /// Reading a file with the wrong magic number.
+    #[test]
+    fn ppm_wrong_magic_number() {
+        let ppm = "\
+P32
+1 1
+255
+0 0 0";
+        assert!(Canvas::from_ppm(ppm.as_bytes()).is_err());
+    }
+
+    /// Reading a PPM returns a canvas of the right size.
+    #[test]
+    fn ppm_canvas_size() {
+        let ppm = "\
+P3
+10 2
+255
+0 0 0  0 0 0  0 0 0  0 0 0  0 0 0
+0 0 0  0 0 0  0 0 0  0 0 0  0 0 0
+0 0 0  0 0 0  0 0 0  0 0 0  0 0 0
+0 0 0  0 0 0  0 0 0  0 0 0  0 0 0
+";
+        let c = Canvas::from_ppm(ppm.as_bytes()).unwrap();
+        assert_eq!(c.width(), 10);
+        assert_eq!(c.height(), 2);
+    }
+
+    /// Reading pixel data from a PPM file.
+    #[test]
+    fn ppm_pixel_data_read() {
+        let ppm = "\
+P3
+4 3
+255
+255 127 0  0 127 255  127 255 0  255 255 255
+0 0 0  255 0 0  0 255 0  0 0 255
+255 255 0  0 255 255  255 0 255  127 127 127
+";
+        let c = Canvas::from_ppm(ppm.as_bytes()).unwrap();
+        let cases = [
+            (0, 0, Color::new(1, 0.49804, 0)),
+            (1, 0, Color::new(0, 0.49804, 1)),
+            (2, 0, Color::new(0.49804, 1, 0)),
+            (3, 0, Color::new(1, 1, 1)),
+            (0, 1, Color::new(0, 0, 0)),
+            (1, 1, Color::new(1, 0, 0)),
+            (2, 1, Color::new(0, 1, 0)),
+            (3, 1, Color::new(0, 0, 1)),
+            (0, 2, Color::new(1, 1, 0)),
+            (1, 2, Color::new(0, 1, 1)),
+            (2, 2, Color::new(1, 0, 1)),
+            (3, 2, Color::new(0.49804, 0.49804, 0.49804)),
+        ];
+        for (x, y, color) in cases {
+            assert_eq!(c.pixel_at(x, y), color);
+        }
+    }
+
+    /// PPM parsing ignores comment lines.
+    #[test]
+    fn ppm_comment_lines() {
+        let ppm = "\
+P3
+# this is a comment
+2 1
+# this, too
+255
+# another comment
+255 255 255
+# oh, no, comments in the pixel data!
+255 0 255
+";
+        let c = Canvas::from_ppm(ppm.as_bytes()).unwrap();
+        assert_eq!(c.pixel_at(0, 0), Color::new(1, 1, 1));
+        assert_eq!(c.pixel_at(1, 0), Color::new(1, 0, 1));
+    }
+
+    /// PPM parsing allows an RGB triple to span lines.
+    #[test]
+    fn ppm_triple_span_lines() {
+        let ppm = "\
+P3
+1 1
+255
+51
+153
+
+204
+";
+        let c = Canvas::from_ppm(ppm.as_bytes()).unwrap();
+        assert_eq!(c.pixel_at(0, 0), Color::new(0.2, 0.6, 0.8));
+    }
+
+    /// PPM parsing respects the scale setting.
+    #[test]
+    fn ppm_scale_setting() {
+        let ppm = "\
+P3
+2 2
+100
+100 100 100  50 50 50
+75 50 25  0 0 0
+";
+        let c = Canvas::from_ppm(ppm.as_bytes()).unwrap();
+        assert_eq!(c.pixel_at(0, 1), Color::new(0.75, 0.5, 0.25));
+    }
+
+    /// Reading binary PPM and grayscale PGM files.
+    #[test]
+    fn ppm_binary_and_gray() {
+        let mut p6 = b"P6\n2 1\n255\n".to_vec();
+        p6.extend_from_slice(&[255, 0, 51, 0, 255, 0]);
+        let c = Canvas::from_ppm(p6.as_slice()).unwrap();
+        assert_eq!(c.pixel_at(0, 0), Color::new(1, 0, 0.2));
+        assert_eq!(c.pixel_at(1, 0), Color::new(0, 1, 0));
+
+        let c = Canvas::from_ppm("P2\n2 1\n4\n1 4\n".as_bytes()).unwrap();
+        assert_eq!(c.pixel_at(0, 0), Color::new(0.25, 0.25, 0.25));
+        assert_eq!(c.pixel_at(1, 0), Color::new(1, 1, 1));
+
+        let mut p5 = b"P5 1 2 65535\n".to_vec();
+        p5.extend_from_slice(&[0x80, 0x00, 0xff, 0xff]);
+        let c = Canvas::from_ppm(p5.as_slice()).unwrap();
+        assert_eq!(c.pixel_at(0, 0), Color::new(0.50001, 0.50001, 0.50001));
+        assert_eq!(c.pixel_at(0, 1), Color::new(1, 1, 1));
+
+        let truncated = b"P5 2 2 255\n\x01\x02".to_vec();
+        assert!(Canvas::from_ppm(truncated.as_slice()).is_err());
+    }
+
+    /// Reading a file of no pixels, or of more pixels than it holds.
+    #[test]
+    fn ppm_invalid_size() {
+        for header in [
+            "P6 0 2 255\n",
+            "P6 2 0 255\n",
+            "P6 70000 70000 255\n",
+            "P2 4000 4000 255\n",
+        ] {
+            let mut ppm = header.as_bytes().to_vec();
+            ppm.extend_from_slice(&[0; 64]);
+            let err = Canvas::from_ppm(ppm.as_slice()).err().unwrap();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        }
+    }
+
     /// PPM files end with a newline character.
     #[test]
     fn ppm_newline_character() {
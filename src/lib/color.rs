@@ -67,6 +67,11 @@ impl Color {
         )
     }
 
+    /// Perceived brightness of the color (Rec. 709 weights).
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.red() + 0.7152 * self.green() + 0.0722 * self.blue()
+    }
+
     pub fn clamp(self, floor: f64, ceiling: f64) -> Self {
         Color::new(
             self.red().max(floor).min(ceiling),
@@ -0,0 +1,269 @@
+use crate::ray::Ray;
+use crate::shape::{sort_intersections, Intersection, Shape};
+use crate::tuple::{Point, Vector};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CsgOperation {
+    Union,
+    Intersection,
+    Difference,
+}
+
+/// Constructive solid geometry: two shapes combined by a set operation.
+pub struct Csg {
+    pub operation: CsgOperation,
+    pub left: Box<dyn Shape>,
+    pub right: Box<dyn Shape>,
+}
+
+impl Csg {
+    /// Return a new Csg.
+    pub fn new(operation: CsgOperation, left: Box<dyn Shape>, right: Box<dyn Shape>) -> Self {
+        Csg {
+            operation,
+            left,
+            right,
+        }
+    }
+
+    /// Keep only the intersections which lie on the surface of the combined shape.
+    /// xs must be sorted by increasing t.
+    pub fn filter_intersections<'a>(&self, xs: &[Intersection<'a>]) -> Vec<Intersection<'a>> {
+        // Both rays start outside of either child.
+        let mut inside_left = false;
+        let mut inside_right = false;
+        let mut result = vec![];
+
+        for x in xs {
+            let left_hit = self.left.includes(x.object);
+
+            if intersection_allowed(self.operation, left_hit, inside_left, inside_right) {
+                result.push(*x);
+            }
+
+            if left_hit {
+                inside_left = !inside_left;
+            } else {
+                inside_right = !inside_right;
+            }
+        }
+
+        result
+    }
+}
+
+/// Returns true if a hit on one child is a hit on the combined shape.
+/// left_hit tells which child was hit, inside_left and inside_right whether
+/// the hit happened inside of the left and right child.
+pub fn intersection_allowed(
+    operation: CsgOperation,
+    left_hit: bool,
+    inside_left: bool,
+    inside_right: bool,
+) -> bool {
+    match operation {
+        CsgOperation::Union => (left_hit && !inside_right) || (!left_hit && !inside_left),
+        CsgOperation::Intersection => (left_hit && inside_right) || (!left_hit && inside_left),
+        CsgOperation::Difference => (left_hit && !inside_right) || (!left_hit && inside_left),
+    }
+}
+
+impl Shape for Csg {
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let mut xs = self.left.local_intersect(ray);
+        xs.extend(self.right.local_intersect(ray));
+        sort_intersections(&mut xs);
+
+        self.filter_intersections(&xs)
+    }
+
+    /// Intersections of a Csg refer to the child they hit, which computes the normal.
+    /// The Csg has no surface of its own, so a hit on the Csg itself has a zero normal.
+    fn local_normal_at(&self, point: Point, hit: &Intersection) -> Vector {
+        if self.left.includes(hit.object) {
+            self.left.local_normal_at(point, hit)
+        } else if self.right.includes(hit.object) {
+            self.right.local_normal_at(point, hit)
+        } else if !std::ptr::addr_eq(hit.object, self) {
+            hit.normal_at(point)
+        } else {
+            Vector::new(0, 0, 0)
+        }
+    }
+
+    fn includes(&self, other: &dyn Shape) -> bool {
+        self.left.includes(other) || self.right.includes(other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cube::Cube;
+    use crate::sdf::{sphere, Sdf};
+    use crate::sphere::Sphere;
+
+    /// Evaluating the rule for a CSG operation.
+    #[test]
+    fn evaluate_csg_rule() {
+        use CsgOperation::*;
+        let cases = [
+            (Union, true, true, true, false),
+            (Union, true, true, false, true),
+            (Union, true, false, true, false),
+            (Union, true, false, false, true),
+            (Union, false, true, true, false),
+            (Union, false, true, false, false),
+            (Union, false, false, true, true),
+            (Union, false, false, false, true),
+            (Intersection, true, true, true, true),
+            (Intersection, true, true, false, false),
+            (Intersection, true, false, true, true),
+            (Intersection, true, false, false, false),
+            (Intersection, false, true, true, true),
+            (Intersection, false, true, false, true),
+            (Intersection, false, false, true, false),
+            (Intersection, false, false, false, false),
+            (Difference, true, true, true, false),
+            (Difference, true, true, false, true),
+            (Difference, true, false, true, false),
+            (Difference, true, false, false, true),
+            (Difference, false, true, true, true),
+            (Difference, false, true, false, true),
+            (Difference, false, false, true, false),
+            (Difference, false, false, false, false),
+        ];
+        for (op, lhit, inl, inr, result) in cases {
+            assert_eq!(intersection_allowed(op, lhit, inl, inr), result);
+        }
+    }
+
+    /// Filtering a list of intersections.
+    #[test]
+    fn filter_intersections() {
+        let cases = [
+            (CsgOperation::Union, 0, 3),
+            (CsgOperation::Intersection, 1, 2),
+            (CsgOperation::Difference, 0, 1),
+        ];
+        for (operation, x0, x1) in cases {
+            let c = Csg::new(
+                operation,
+                Box::new(Sphere::default()),
+                Box::new(Cube::default()),
+            );
+            let (s1, s2) = (c.left.as_ref(), c.right.as_ref());
+            let xs = [
+                Intersection::new(1.0, s1),
+                Intersection::new(2.0, s2),
+                Intersection::new(3.0, s1),
+                Intersection::new(4.0, s2),
+            ];
+            let result = c.filter_intersections(&xs);
+            assert_eq!(result.len(), 2);
+            assert_eq!(result[0].t, xs[x0].t);
+            assert_eq!(result[1].t, xs[x1].t);
+        }
+    }
+
+    /// A ray misses a CSG object.
+    #[test]
+    fn ray_misses_csg() {
+        let c = Csg::new(
+            CsgOperation::Union,
+            Box::new(Sphere::default()),
+            Box::new(Cube::default()),
+        );
+        let r = Ray::new(Point::new(0, 2, -5), Vector::new(0, 0, 1));
+        assert!(c.local_intersect(&r).is_empty());
+    }
+
+    /// A ray hits a CSG object.
+    #[test]
+    fn ray_hits_csg() {
+        let c = Csg::new(
+            CsgOperation::Union,
+            Box::new(Sphere::default()),
+            Box::new(Sphere::new(Point::new(0, 0, 0.5), 1.0)),
+        );
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        let xs = c.local_intersect(&r);
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].t, 4.0);
+        assert!(c.left.includes(xs[0].object));
+        assert_eq!(xs[1].t, 6.5);
+        assert!(c.right.includes(xs[1].object));
+    }
+
+    /// A ray starting inside a distance field operand keeps track of being inside it.
+    #[test]
+    fn ray_inside_sdf_csg() {
+        let c = Csg::new(
+            CsgOperation::Difference,
+            Box::new(Sdf::new(sphere(2.0))),
+            Box::new(Cube::default()),
+        );
+        let r = Ray::new(Point::new(0, 0, 0), Vector::new(0, 0, 1));
+        let ts: Vec<f64> = c.local_intersect(&r).iter().map(|x| x.t).collect();
+        assert_eq!(ts.len(), 4);
+        assert!((ts[0] + 2.0).abs() < 1e-3 && (ts[3] - 2.0).abs() < 1e-3);
+        assert_eq!(&ts[1..3], &[-1.0, 1.0]);
+    }
+
+    /// The normal of a CSG object is the normal of the child it was hit on.
+    #[test]
+    fn normal_csg() {
+        let c = Csg::new(
+            CsgOperation::Union,
+            Box::new(Sphere::default()),
+            Box::new(Sphere::new(Point::new(0, 0, 0.5), 1.0)),
+        );
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        let xs = c.local_intersect(&r);
+        let (near, far) = (r.position(xs[0].t), r.position(xs[1].t));
+        assert_eq!(c.local_normal_at(near, &xs[0]), Vector::new(0, 0, -1));
+        assert_eq!(c.local_normal_at(far, &xs[1]), Vector::new(0, 0, 1));
+        let itself = Intersection::new(1.0, &c);
+        assert_eq!(c.local_normal_at(far, &itself), Vector::new(0, 0, 0));
+    }
+
+    /// Carving a hole through a wall and hitting the far side of the hole.
+    #[test]
+    fn ray_through_hole() {
+        let wall = Cube::new(Point::new(-5, -5, 0), Point::new(5, 5, 1));
+        let hole = Sphere::new(Point::new(0, 0, 0.5), 2.0);
+        let c = Csg::new(CsgOperation::Difference, Box::new(wall), Box::new(hole));
+
+        let through = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        assert!(c.local_intersect(&through).is_empty());
+
+        let solid = Ray::new(Point::new(3, 0, -5), Vector::new(0, 0, 1));
+        let xs = c.local_intersect(&solid);
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].t, 5.0);
+        assert_eq!(xs[1].t, 6.0);
+    }
+
+    /// Nested CSG objects include the shapes of their children.
+    #[test]
+    fn nested_csg_includes() {
+        let inner = Csg::new(
+            CsgOperation::Intersection,
+            Box::new(Cube::default()),
+            Box::new(Sphere::new(Point::new(0, 0, 0), 1.3)),
+        );
+        let outer = Csg::new(
+            CsgOperation::Difference,
+            Box::new(inner),
+            Box::new(Sphere::new(Point::new(0, 0, -1), 0.3)),
+        );
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        let xs = outer.local_intersect(&r);
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].t, 4.3);
+        assert!(outer.right.includes(xs[0].object));
+        assert_eq!(xs[1].t, 6.0);
+        assert!(outer.left.includes(xs[1].object));
+        assert!(!outer.right.includes(xs[1].object));
+    }
+}
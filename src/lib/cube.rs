@@ -0,0 +1,159 @@
+use crate::ray::Ray;
+use crate::shape::{Intersection, Shape};
+use crate::tuple::{Point, Vector};
+
+/// Axis aligned box spanning from min to max.
+#[derive(Debug, Clone, Copy)]
+pub struct Cube {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl Cube {
+    /// Return a new Cube.
+    pub fn new(min: Point, max: Point) -> Self {
+        Cube { min, max }
+    }
+}
+
+/// The cube from (-1, -1, -1) to (1, 1, 1).
+impl Default for Cube {
+    fn default() -> Self {
+        Cube::new(Point::new(-1, -1, -1), Point::new(1, 1, 1))
+    }
+}
+
+/// Returns the distances at which a ray crosses the two planes of one axis.
+fn check_axis(origin: f64, direction: f64, min: f64, max: f64) -> (f64, f64) {
+    // Dividing by zero yields infinities of the right sign, which is what the slab test needs.
+    let tmin = (min - origin) / direction;
+    let tmax = (max - origin) / direction;
+
+    if tmin > tmax {
+        (tmax, tmin)
+    } else {
+        (tmin, tmax)
+    }
+}
+
+impl Shape for Cube {
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let (xtmin, xtmax) = check_axis(
+            ray.origin.x(),
+            ray.direction.x(),
+            self.min.x(),
+            self.max.x(),
+        );
+        let (ytmin, ytmax) = check_axis(
+            ray.origin.y(),
+            ray.direction.y(),
+            self.min.y(),
+            self.max.y(),
+        );
+        let (ztmin, ztmax) = check_axis(
+            ray.origin.z(),
+            ray.direction.z(),
+            self.min.z(),
+            self.max.z(),
+        );
+
+        let tmin = xtmin.max(ytmin).max(ztmin);
+        let tmax = xtmax.min(ytmax).min(ztmax);
+
+        if tmin > tmax {
+            return vec![];
+        }
+
+        vec![Intersection::new(tmin, self), Intersection::new(tmax, self)]
+    }
+
+    fn local_normal_at(&self, point: Point, _hit: &Intersection) -> Vector {
+        // The normal points out of whichever face the point is closest to.
+        let faces = [
+            (point.x() - self.min.x(), Vector::new(-1, 0, 0)),
+            (self.max.x() - point.x(), Vector::new(1, 0, 0)),
+            (point.y() - self.min.y(), Vector::new(0, -1, 0)),
+            (self.max.y() - point.y(), Vector::new(0, 1, 0)),
+            (point.z() - self.min.z(), Vector::new(0, 0, -1)),
+            (self.max.z() - point.z(), Vector::new(0, 0, 1)),
+        ];
+
+        faces
+            .iter()
+            .min_by(|a, b| a.0.abs().total_cmp(&b.0.abs()))
+            .unwrap()
+            .1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A ray intersects a cube.
+    #[test]
+    fn ray_intersects_cube() {
+        let c = Cube::default();
+        let cases = [
+            (Point::new(5, 0.5, 0), Vector::new(-1, 0, 0), 4.0, 6.0),
+            (Point::new(-5, 0.5, 0), Vector::new(1, 0, 0), 4.0, 6.0),
+            (Point::new(0.5, 5, 0), Vector::new(0, -1, 0), 4.0, 6.0),
+            (Point::new(0.5, -5, 0), Vector::new(0, 1, 0), 4.0, 6.0),
+            (Point::new(0.5, 0, 5), Vector::new(0, 0, -1), 4.0, 6.0),
+            (Point::new(0.5, 0, -5), Vector::new(0, 0, 1), 4.0, 6.0),
+            (Point::new(0, 0.5, 0), Vector::new(0, 0, 1), -1.0, 1.0),
+        ];
+        for (origin, direction, t1, t2) in cases {
+            let xs = c.local_intersect(&Ray::new(origin, direction));
+            assert_eq!(xs.len(), 2);
+            assert_eq!(xs[0].t, t1);
+            assert_eq!(xs[1].t, t2);
+        }
+    }
+
+    /// A ray misses a cube.
+    #[test]
+    fn ray_misses_cube() {
+        let c = Cube::default();
+        let cases = [
+            (Point::new(-2, 0, 0), Vector::new(0.2673, 0.5345, 0.8018)),
+            (Point::new(0, -2, 0), Vector::new(0.8018, 0.2673, 0.5345)),
+            (Point::new(0, 0, -2), Vector::new(0.5345, 0.8018, 0.2673)),
+            (Point::new(2, 0, 2), Vector::new(0, 0, -1)),
+            (Point::new(0, 2, 2), Vector::new(0, -1, 0)),
+            (Point::new(2, 2, 0), Vector::new(-1, 0, 0)),
+        ];
+        for (origin, direction) in cases {
+            assert!(c.local_intersect(&Ray::new(origin, direction)).is_empty());
+        }
+    }
+
+    /// Intersecting a box which is not centered at the origin.
+    #[test]
+    fn ray_intersects_box() {
+        let c = Cube::new(Point::new(1, 1, 1), Point::new(2, 3, 4));
+        let xs = c.local_intersect(&Ray::new(Point::new(1.5, 2, -5), Vector::new(0, 0, 1)));
+        assert_eq!(xs[0].t, 6.0);
+        assert_eq!(xs[1].t, 9.0);
+    }
+
+    /// The normal on the surface of a cube.
+    #[test]
+    fn normal_cube() {
+        let c = Cube::default();
+        let cases = [
+            (Point::new(1, 0.5, -0.8), Vector::new(1, 0, 0)),
+            (Point::new(-1, -0.2, 0.9), Vector::new(-1, 0, 0)),
+            (Point::new(-0.4, 1, -0.1), Vector::new(0, 1, 0)),
+            (Point::new(0.3, -1, -0.7), Vector::new(0, -1, 0)),
+            (Point::new(-0.6, 0.3, 1), Vector::new(0, 0, 1)),
+            (Point::new(0.4, 0.4, -1), Vector::new(0, 0, -1)),
+        ];
+        for (point, normal) in cases {
+            assert_eq!(
+                c.local_normal_at(point, &Intersection::new(0.0, &c)),
+                normal
+            );
+        }
+    }
+}
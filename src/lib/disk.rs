@@ -0,0 +1,151 @@
+use crate::ray::Ray;
+use crate::shape::{Intersection, Shape};
+use crate::tuple::{Point, Vector};
+use std::f64::consts::PI;
+
+/// Flat disk facing along normal, with a hole of inner_radius in its middle (an annulus)
+/// when inner_radius is not zero.
+///
+/// Hits carry u, the angle around the center divided by 2π, measured from the tangent
+/// given by Vector::orthonormal_basis, and v, going from 0 on the outer edge to 1 on
+/// the inner edge.
+#[derive(Debug, Clone, Copy)]
+pub struct Disk {
+    pub center: Point,
+    pub normal: Vector,
+    pub radius: f64,
+    pub inner_radius: f64,
+}
+
+impl Disk {
+    /// Return a new Disk.
+    pub fn new(center: Point, normal: Vector, radius: f64) -> Self {
+        Disk::annulus(center, normal, radius, 0.0)
+    }
+
+    /// Return a new Disk with a hole of inner_radius in its middle.
+    pub fn annulus(center: Point, normal: Vector, radius: f64, inner_radius: f64) -> Self {
+        Disk {
+            center,
+            normal: normal.normalize(),
+            radius,
+            inner_radius,
+        }
+    }
+
+    /// Returns the point of the disk at surface coordinates u, v.
+    pub fn point_at(&self, u: f64, v: f64) -> Point {
+        let (tangent, bitangent) = self.normal.orthonormal_basis();
+        let r = self.radius - v * (self.radius - self.inner_radius);
+        let phi = 2.0 * PI * u;
+        self.center + tangent * (r * phi.cos()) + bitangent * (r * phi.sin())
+    }
+
+    /// Returns the area of the disk.
+    pub fn area(&self) -> f64 {
+        PI * (self.radius * self.radius - self.inner_radius * self.inner_radius)
+    }
+}
+
+/// The unit disk at the origin facing up.
+impl Default for Disk {
+    fn default() -> Self {
+        Disk::new(Point::new(0, 0, 0), Vector::new(0, 1, 0), 1.0)
+    }
+}
+
+impl Shape for Disk {
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let denominator = ray.direction.dot(&self.normal);
+        if denominator.abs() < 1e-12 {
+            return vec![];
+        }
+
+        let t = (self.center - ray.origin).dot(&self.normal) / denominator;
+        let offset = ray.position(t) - self.center;
+        let r = offset.magnitude();
+        if r > self.radius || r < self.inner_radius {
+            return vec![];
+        }
+
+        let (tangent, bitangent) = self.normal.orthonormal_basis();
+        let phi = offset.dot(&bitangent).atan2(offset.dot(&tangent));
+        let u = phi.rem_euclid(2.0 * PI) / (2.0 * PI);
+        let v = (self.radius - r) / (self.radius - self.inner_radius);
+        vec![Intersection::with_uv(t, self, u, v)]
+    }
+
+    fn local_normal_at(&self, _point: Point, _hit: &Intersection) -> Vector {
+        self.normal
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approximate_equation::ApproximateEq;
+
+    /// A ray hits a disk inside its radius.
+    #[test]
+    fn ray_hits_disk() {
+        let d = Disk::new(Point::new(0, 1, 0), Vector::new(0, 2, 0), 2.0);
+        let r = Ray::new(Point::new(1, 5, 1), Vector::new(0, -1, 0));
+        let xs = d.local_intersect(&r);
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].t, 4.0);
+        assert!(xs[0].v.approx_eq(&(1.0 - 2f64.sqrt() / 2.0)));
+    }
+
+    /// A ray misses a disk outside of its radius or parallel to it.
+    #[test]
+    fn ray_misses_disk() {
+        let d = Disk::default();
+        let r = Ray::new(Point::new(1, 5, 1), Vector::new(0, -1, 0));
+        assert!(d.local_intersect(&r).is_empty());
+        let r = Ray::new(Point::new(-5, 0, 0), Vector::new(1, 0, 0));
+        assert!(d.local_intersect(&r).is_empty());
+    }
+
+    /// A ray through the hole of an annulus misses it.
+    #[test]
+    fn ray_through_annulus_hole() {
+        let d = Disk::annulus(Point::new(0, 0, 0), Vector::new(0, 0, 1), 2.0, 1.0);
+        let r = Ray::new(Point::new(0.5, 0, -5), Vector::new(0, 0, 1));
+        assert!(d.local_intersect(&r).is_empty());
+        let r = Ray::new(Point::new(1.5, 0, -5), Vector::new(0, 0, 1));
+        let xs = d.local_intersect(&r);
+        assert_eq!(xs.len(), 1);
+        assert!(xs[0].v.approx_eq(&0.5));
+    }
+
+    /// The u/v coordinates of a hit map back to the hit point.
+    #[test]
+    fn disk_uv_round_trip() {
+        let d = Disk::annulus(Point::new(1, 2, 3), Vector::new(1, 1, 0), 3.0, 0.5);
+        let (tangent, bitangent) = d.normal.orthonormal_basis();
+        let p = d.center + tangent * -1.2 + bitangent * -0.7;
+        let r = Ray::new(p + d.normal * 4.0, -d.normal);
+        let xs = d.local_intersect(&r);
+        assert_eq!(xs.len(), 1);
+        assert!(xs[0].u > 0.5);
+        assert_eq!(d.point_at(xs[0].u, xs[0].v), p);
+    }
+
+    /// The normal of a disk is the same everywhere.
+    #[test]
+    fn normal_disk() {
+        let d = Disk::default();
+        let hit = Intersection::new(0.0, &d);
+        assert_eq!(
+            d.local_normal_at(Point::new(0.5, 0, 0), &hit),
+            Vector::new(0, 1, 0)
+        );
+    }
+
+    /// The area of an annulus excludes its hole.
+    #[test]
+    fn annulus_area() {
+        let d = Disk::annulus(Point::new(0, 0, 0), Vector::new(0, 1, 0), 2.0, 1.0);
+        assert!(d.area().approx_eq(&(3.0 * PI)));
+    }
+}
@@ -0,0 +1,270 @@
+use crate::canvas::Canvas;
+use crate::color::Color;
+use crate::light::{Light, LightSample};
+use crate::random::RandomSource;
+use crate::tuple::{Point, Vector};
+use std::f64::consts::PI;
+
+/// Equirectangular image surrounding the scene, seen by rays escaping it and lighting
+/// it from every direction.
+///
+/// The top row of the image is straight up (+y), the bottom row straight down, and
+/// the middle of the image looks toward -z, with +x a quarter of the width to its right.
+/// Directions are importance sampled in proportion to the brightness of the image.
+#[derive(Debug, Clone)]
+pub struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+    /// Cumulative distribution of the rows, height + 1 entries from 0 to 1.
+    row_cdf: Vec<f64>,
+    /// Cumulative distribution of the pixels within each row, width + 1 entries per row.
+    column_cdfs: Vec<f64>,
+    /// Sum of the sampling weights of all pixels.
+    total_weight: f64,
+    /// Multiplies the colors of the image.
+    pub strength: f64,
+    /// Number of directions sampled each time the map lights a point.
+    pub samples: usize,
+}
+
+impl EnvironmentMap {
+    /// Return a new EnvironmentMap from an equirectangular image, or None if the image
+    /// has no pixels.
+    pub fn new(image: &Canvas) -> Option<Self> {
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        if width == 0 || height == 0 {
+            return None;
+        }
+        let pixels: Vec<Color> = image.flat().collect();
+
+        // Pixels are weighted by the solid angle they cover, smaller near the poles.
+        let mut row_cdf = vec![0.0; height + 1];
+        let mut column_cdfs = vec![0.0; (width + 1) * height];
+        for y in 0..height {
+            let solid_angle = pixel_solid_angle(y, width, height);
+            let cdf = &mut column_cdfs[y * (width + 1)..(y + 1) * (width + 1)];
+            for x in 0..width {
+                cdf[x + 1] = cdf[x] + pixels[y * width + x].luminance().max(0.0) * solid_angle;
+            }
+            row_cdf[y + 1] = row_cdf[y] + cdf[width];
+            let row_total = cdf[width];
+            if row_total > 0.0 {
+                cdf.iter_mut().for_each(|c| *c /= row_total);
+            }
+        }
+        let total_weight = row_cdf[height];
+        if total_weight > 0.0 {
+            row_cdf.iter_mut().for_each(|c| *c /= total_weight);
+        }
+
+        Some(EnvironmentMap {
+            width,
+            height,
+            pixels,
+            row_cdf,
+            column_cdfs,
+            total_weight,
+            strength: 1.0,
+            samples: 16,
+        })
+    }
+
+    /// Returns the unit direction at image coordinates u, v in [0, 1].
+    pub fn direction(u: f64, v: f64) -> Vector {
+        let (sin_theta, cos_theta) = (PI * v).sin_cos();
+        let (sin_phi, cos_phi) = (2.0 * PI * u).sin_cos();
+        Vector::new(sin_theta * sin_phi, cos_theta, -sin_theta * cos_phi)
+    }
+
+    /// Returns the image coordinates u, v in [0, 1] seen along a direction.
+    pub fn uv(direction: Vector) -> (f64, f64) {
+        let d = direction.normalize();
+        let u = d.x().atan2(-d.z()).rem_euclid(2.0 * PI) / (2.0 * PI);
+        let v = d.y().clamp(-1.0, 1.0).acos() / PI;
+        (u, v)
+    }
+
+    fn pixel_index(&self, u: f64, v: f64) -> usize {
+        let x = ((u * self.width as f64) as usize).min(self.width - 1);
+        let y = ((v * self.height as f64) as usize).min(self.height - 1);
+        y * self.width + x
+    }
+
+    /// Returns the light coming from a direction.
+    pub fn radiance(&self, direction: Vector) -> Color {
+        let (u, v) = EnvironmentMap::uv(direction);
+        self.pixels[self.pixel_index(u, v)] * self.strength
+    }
+
+    /// Returns the probability density, per unit solid angle, of sampling a direction.
+    pub fn pdf(&self, direction: Vector) -> f64 {
+        if self.total_weight <= 0.0 {
+            return 0.0;
+        }
+        let (u, v) = EnvironmentMap::uv(direction);
+        let index = self.pixel_index(u, v);
+        let (x, y) = (index % self.width, index / self.width);
+        let row = &self.column_cdfs[y * (self.width + 1)..];
+        let pixel_probability = (self.row_cdf[y + 1] - self.row_cdf[y]) * (row[x + 1] - row[x]);
+        // Directions are spread uniformly over the solid angle of the pixel.
+        pixel_probability / pixel_solid_angle(y, self.width, self.height)
+    }
+
+    /// Returns a direction chosen in proportion to the brightness of the image and the
+    /// probability density of choosing it, or None for a black image.
+    pub fn sample(&self, rng: &mut dyn RandomSource) -> Option<(Vector, f64)> {
+        if self.total_weight <= 0.0 {
+            return None;
+        }
+        let (y, dv) = sample_cdf(&self.row_cdf, rng.next_f64());
+        let row = &self.column_cdfs[y * (self.width + 1)..(y + 1) * (self.width + 1)];
+        let (x, du) = sample_cdf(row, rng.next_f64());
+        let u = (x as f64 + du) / self.width as f64;
+        // Uniform in cos θ within the row, so uniform over the solid angle of the pixel.
+        let (top, bottom) = row_cosines(y, self.height);
+        let v = (top - dv * (top - bottom)).clamp(-1.0, 1.0).acos() / PI;
+        let direction = EnvironmentMap::direction(u, v);
+        let pdf = self.pdf(direction);
+        (pdf > 0.0).then_some((direction, pdf))
+    }
+}
+
+/// Returns cos θ at the top and bottom edges of row y.
+fn row_cosines(y: usize, height: usize) -> (f64, f64) {
+    let theta = |row: usize| PI * row as f64 / height as f64;
+    (theta(y).cos(), theta(y + 1).cos())
+}
+
+/// Returns the solid angle covered by a pixel of row y.
+fn pixel_solid_angle(y: usize, width: usize, height: usize) -> f64 {
+    let (top, bottom) = row_cosines(y, height);
+    2.0 * PI / width as f64 * (top - bottom)
+}
+
+/// Returns the interval of a cumulative distribution which r falls in, and where r
+/// falls within that interval, from 0 to 1.
+fn sample_cdf(cdf: &[f64], r: f64) -> (usize, f64) {
+    let last = cdf.len() - 2;
+    // The first interval whose upper end is above r, skipping empty intervals.
+    let index = cdf[1..].partition_point(|&c| c <= r).min(last);
+    let width = cdf[index + 1] - cdf[index];
+    let offset = if width > 0.0 {
+        ((r - cdf[index]) / width).clamp(0.0, 1.0)
+    } else {
+        0.5
+    };
+    (index, offset)
+}
+
+impl Light for EnvironmentMap {
+    /// The map already lights points from every direction, so it adds no ambient light.
+    fn intensity(&self) -> Color {
+        Color::new(0, 0, 0)
+    }
+
+    /// Each sample carries radiance / (π pdf), so that averaging the Phong diffuse term
+    /// over the samples estimates the light reflected by a Lambertian surface.
+    fn samples(&self, _point: Point, rng: &mut dyn RandomSource) -> Vec<LightSample> {
+        (0..self.samples)
+            .map(|_| match self.sample(rng) {
+                Some((direction, pdf)) => LightSample {
+                    direction,
+                    distance: f64::INFINITY,
+                    intensity: self.radiance(direction) * (1.0 / (PI * pdf)),
+                },
+                None => LightSample {
+                    direction: Vector::new(0, 1, 0),
+                    distance: f64::INFINITY,
+                    intensity: Color::new(0, 0, 0),
+                },
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::light::{lighting, ShadingPoint};
+    use crate::material::Material;
+    use crate::random::XorShift;
+
+    fn uniform_map(color: Color) -> EnvironmentMap {
+        let mut canvas = Canvas::new(16, 8);
+        canvas.set_all_pixel(color);
+        EnvironmentMap::new(&canvas).unwrap()
+    }
+
+    /// Directions and image coordinates map to each other.
+    #[test]
+    fn direction_uv_round_trip() {
+        assert_eq!(EnvironmentMap::direction(0.5, 0.5), Vector::new(0, 0, 1));
+        assert_eq!(EnvironmentMap::direction(0.0, 0.5), Vector::new(0, 0, -1));
+        assert_eq!(EnvironmentMap::direction(0.25, 0.5), Vector::new(1, 0, 0));
+        assert_eq!(EnvironmentMap::direction(0.3, 0.0), Vector::new(0, 1, 0));
+        let (u, v) = EnvironmentMap::uv(EnvironmentMap::direction(0.7, 0.2));
+        assert!((u - 0.7).abs() < 1e-9 && (v - 0.2).abs() < 1e-9);
+    }
+
+    /// A uniform map is sampled uniformly over the sphere.
+    #[test]
+    fn uniform_map_pdf() {
+        let map = uniform_map(Color::new(0.5, 0.5, 0.5));
+        for direction in [
+            Vector::new(0, 0, -1),
+            Vector::new(1, 0.2, 0.3),
+            Vector::new(0.1, -0.9, 0),
+        ] {
+            assert!((map.pdf(direction) * 4.0 * PI - 1.0).abs() < 1e-9);
+        }
+        assert_eq!(
+            map.radiance(Vector::new(0, 1, 0)),
+            Color::new(0.5, 0.5, 0.5)
+        );
+    }
+
+    /// Samples of a map come from its bright pixels.
+    #[test]
+    fn sample_bright_pixel() {
+        let mut canvas = Canvas::new(8, 4);
+        canvas.write_pixel(6, 1, Color::new(10, 10, 10));
+        let map = EnvironmentMap::new(&canvas).unwrap();
+        let mut rng = XorShift::new(7);
+        for _ in 0..100 {
+            let (direction, pdf) = map.sample(&mut rng).unwrap();
+            let (u, v) = EnvironmentMap::uv(direction);
+            assert_eq!(((u * 8.0) as usize, (v * 4.0) as usize), (6, 1));
+            assert!((pdf - map.pdf(direction)).abs() < 1e-9);
+        }
+        assert_eq!(map.pdf(EnvironmentMap::direction(0.1, 0.6)), 0.0);
+        assert!(EnvironmentMap::new(&Canvas::new(4, 2))
+            .unwrap()
+            .sample(&mut rng)
+            .is_none());
+        assert!(EnvironmentMap::new(&Canvas::new(0, 2)).is_none());
+    }
+
+    /// A uniform white environment lights a white diffuse surface to its own brightness.
+    #[test]
+    fn lighting_uniform_environment() {
+        let mut map = uniform_map(Color::new(1, 1, 1));
+        map.samples = 4096;
+        let material = Material {
+            ambient: 0.0,
+            diffuse: 1.0,
+            specular: 0.0,
+            ..Material::default()
+        };
+        let shading = ShadingPoint {
+            point: Point::new(0, 0, 0),
+            eyev: Vector::new(0, 1, 0),
+            normalv: Vector::new(0, 1, 0),
+        };
+        let mut rng = XorShift::new(1);
+        let c = lighting(&material, &map, &shading, &mut rng, &mut |_| false);
+        assert!((c.red() - 1.0).abs() < 0.05, "{c:?}");
+        let dark = lighting(&material, &map, &shading, &mut rng, &mut |_| true);
+        assert_eq!(dark, Color::new(0, 0, 0));
+    }
+}
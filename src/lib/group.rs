@@ -0,0 +1,154 @@
+use crate::cube::Cube;
+use crate::ray::Ray;
+use crate::shape::{Intersection, Shape};
+use crate::tuple::{Point, Vector};
+
+/// Collection of shapes intersected together. When given bounds enclosing all of its
+/// children, rays missing the bounds skip the children altogether.
+#[derive(Default)]
+pub struct Group {
+    pub children: Vec<Box<dyn Shape>>,
+    pub bounds: Option<Cube>,
+}
+
+impl Group {
+    /// Return a new Group.
+    pub fn new(children: Vec<Box<dyn Shape>>) -> Self {
+        Group {
+            children,
+            bounds: None,
+        }
+    }
+
+    /// Return a new Group whose children all lie within bounds.
+    pub fn with_bounds(children: Vec<Box<dyn Shape>>, bounds: Cube) -> Self {
+        Group {
+            children,
+            bounds: Some(bounds),
+        }
+    }
+}
+
+/// Returns the smallest box containing all points, or None when there are no points.
+pub fn bounding_box(points: impl IntoIterator<Item = Point>) -> Option<Cube> {
+    points.into_iter().fold(None, |bounds, p| {
+        Some(match bounds {
+            None => Cube::new(p, p),
+            Some(Cube { min, max }) => Cube::new(
+                Point::new(min.x().min(p.x()), min.y().min(p.y()), min.z().min(p.z())),
+                Point::new(max.x().max(p.x()), max.y().max(p.y()), max.z().max(p.z())),
+            ),
+        })
+    })
+}
+
+impl Shape for Group {
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        if let Some(bounds) = &self.bounds {
+            if bounds.local_intersect(ray).is_empty() {
+                return vec![];
+            }
+        }
+
+        self.children
+            .iter()
+            .flat_map(|child| child.local_intersect(ray))
+            .collect()
+    }
+
+    /// Intersections of a Group refer to the child they hit, which computes the normal.
+    /// The Group has no surface of its own, so a hit on the Group itself has a zero
+    /// normal.
+    fn local_normal_at(&self, point: Point, hit: &Intersection) -> Vector {
+        match self
+            .children
+            .iter()
+            .find(|child| child.includes(hit.object))
+        {
+            Some(child) => child.local_normal_at(point, hit),
+            None if !std::ptr::addr_eq(hit.object, self) => hit.normal_at(point),
+            None => Vector::new(0, 0, 0),
+        }
+    }
+
+    fn includes(&self, other: &dyn Shape) -> bool {
+        self.children.iter().any(|child| child.includes(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sphere::Sphere;
+
+    /// Intersecting a ray with an empty group.
+    #[test]
+    fn intersect_empty_group() {
+        let g = Group::default();
+        let r = Ray::new(Point::new(0, 0, 0), Vector::new(0, 0, 1));
+        assert!(g.local_intersect(&r).is_empty());
+    }
+
+    /// Intersecting a ray with a nonempty group.
+    #[test]
+    fn intersect_nonempty_group() {
+        let g = Group::new(vec![
+            Box::new(Sphere::default()),
+            Box::new(Sphere::new(Point::new(0, 0, -3), 1.0)),
+            Box::new(Sphere::new(Point::new(5, 0, 0), 1.0)),
+        ]);
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        let mut ts: Vec<f64> = g.local_intersect(&r).iter().map(|x| x.t).collect();
+        ts.sort_by(|a, b| a.total_cmp(b));
+        assert_eq!(ts, vec![1.0, 3.0, 4.0, 6.0]);
+        let xs = g.local_intersect(&r);
+        assert!(g.children[0].includes(xs[0].object));
+        assert!(g.includes(xs[2].object));
+        assert!(!g.children[2].includes(xs[2].object));
+    }
+
+    /// A ray missing the bounds of a group skips its children.
+    #[test]
+    fn intersect_bounded_group() {
+        let g = Group::with_bounds(
+            vec![Box::new(Sphere::default())],
+            Cube::new(Point::new(5, 5, 5), Point::new(6, 6, 6)),
+        );
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        assert!(g.local_intersect(&r).is_empty());
+    }
+
+    /// The bounding box of a set of points.
+    #[test]
+    fn bounding_box_points() {
+        assert!(bounding_box(vec![]).is_none());
+        let b = bounding_box(vec![
+            Point::new(-5, 2, 0),
+            Point::new(7, 0, -3),
+            Point::new(0, 1, 4),
+        ])
+        .unwrap();
+        assert_eq!(b.min, Point::new(-5, 0, -3));
+        assert_eq!(b.max, Point::new(7, 2, 4));
+    }
+
+    /// The normal of a group is the normal of the child it was hit on.
+    #[test]
+    fn normal_group() {
+        let g = Group::new(vec![
+            Box::new(Sphere::default()),
+            Box::new(Group::new(vec![Box::new(Sphere::new(
+                Point::new(5, 0, 0),
+                1.0,
+            ))])),
+        ]);
+        let r = Ray::new(Point::new(5, 0, -5), Vector::new(0, 0, 1));
+        let xs = g.local_intersect(&r);
+        let p = r.position(xs[0].t);
+        assert_eq!(g.local_normal_at(p, &xs[0]), Vector::new(0, 0, -1));
+        assert_eq!(g.local_normal_at(p, &xs[0]), xs[0].normal_at(p));
+        let empty = Group::default();
+        let itself = Intersection::new(1.0, &empty);
+        assert_eq!(empty.local_normal_at(p, &itself), Vector::new(0, 0, 0));
+    }
+}
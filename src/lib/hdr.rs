@@ -0,0 +1,195 @@
+use crate::canvas::{image_size, Canvas};
+use crate::color::Color;
+use std::io::{Error, ErrorKind, Read};
+
+/// Parse a Radiance RGBE (.hdr) image into a Canvas of linear, unclamped colors.
+///
+/// Scanlines may be stored flat or with the run length encoding written by most
+/// tools. Only the usual -Y height +X width orientation is supported.
+pub fn parse_hdr(mut reader: impl Read) -> std::io::Result<Canvas> {
+    let mut data = Vec::<u8>::new();
+    reader.read_to_end(&mut data)?;
+    let mut position = 0;
+
+    let mut next_line = || -> std::io::Result<&[u8]> {
+        let rest = &data[position..];
+        let end = rest
+            .iter()
+            .position(|&b| b == b'\n')
+            .ok_or_else(|| invalid_data("unexpected end of HDR header"))?;
+        position += end + 1;
+        Ok(&rest[..end])
+    };
+
+    let magic = next_line()?;
+    if !magic.starts_with(b"#?") {
+        return Err(invalid_data("not a Radiance HDR file"));
+    }
+    loop {
+        let line = next_line()?;
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with(b"FORMAT=") && line != b"FORMAT=32-bit_rle_rgbe" {
+            return Err(invalid_data("unsupported HDR pixel format"));
+        }
+    }
+    let resolution = String::from_utf8_lossy(next_line()?).into_owned();
+    let (width, height) = match resolution.split_ascii_whitespace().collect::<Vec<_>>()[..] {
+        ["-Y", height, "+X", width] => (width.parse().ok(), height.parse().ok()),
+        _ => (None, None),
+    };
+    let (width, height): (usize, usize) = width
+        .zip(height)
+        .ok_or_else(|| invalid_data("unsupported HDR resolution line"))?;
+    let mut pixels = &data[position..];
+    let fits = min_scanline_size(width)
+        .checked_mul(height)
+        .is_some_and(|size| size <= pixels.len());
+    let (canvas_width, canvas_height) = image_size(width, height)
+        .filter(|_| fits)
+        .ok_or_else(|| invalid_data("invalid HDR image size"))?;
+
+    let mut canvas = Canvas::new(canvas_width, canvas_height);
+    let mut scanline = vec![[0u8; 4]; width];
+    for y in 0..height {
+        read_scanline(&mut pixels, &mut scanline)?;
+        for (x, rgbe) in scanline.iter().enumerate() {
+            canvas.write_pixel(x as i32, y as i32, rgbe_to_color(*rgbe));
+        }
+    }
+
+    Ok(canvas)
+}
+
+/// Returns the fewest bytes a scanline width pixels wide can be stored in.
+fn min_scanline_size(width: usize) -> usize {
+    if (8..32768).contains(&width) {
+        // Each of the four channels in runs of up to 127 repeated bytes, stored on two.
+        4 + 8 * width.div_ceil(127)
+    } else {
+        4 * width
+    }
+}
+
+/// Read one scanline from the front of data, advancing past it.
+fn read_scanline(data: &mut &[u8], scanline: &mut [[u8; 4]]) -> std::io::Result<()> {
+    let width = scanline.len();
+    let run_length_encoded = (8..32768).contains(&width)
+        && matches!(data, [2, 2, hi, lo, ..] if (*hi as usize) << 8 | *lo as usize == width);
+    if !run_length_encoded {
+        for pixel in scanline.iter_mut() {
+            for channel in pixel.iter_mut() {
+                *channel = next_byte(data)?;
+            }
+        }
+        return Ok(());
+    }
+
+    *data = &data[4..];
+    // Each channel is stored separately, as runs of a repeated byte or of literal bytes.
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = next_byte(data)? as usize;
+            let (length, repeated) = if count > 128 {
+                (count - 128, Some(next_byte(data)?))
+            } else {
+                (count, None)
+            };
+            if length == 0 || x + length > width {
+                return Err(invalid_data("invalid HDR run length"));
+            }
+            for pixel in scanline[x..x + length].iter_mut() {
+                pixel[channel] = match repeated {
+                    Some(value) => value,
+                    None => next_byte(data)?,
+                };
+            }
+            x += length;
+        }
+    }
+    Ok(())
+}
+
+fn next_byte(data: &mut &[u8]) -> std::io::Result<u8> {
+    let (&byte, rest) = data
+        .split_first()
+        .ok_or_else(|| invalid_data("unexpected end of HDR data"))?;
+    *data = rest;
+    Ok(byte)
+}
+
+/// Decode a pixel with a shared exponent.
+fn rgbe_to_color([r, g, b, e]: [u8; 4]) -> Color {
+    if e == 0 {
+        return Color::new(0, 0, 0);
+    }
+    let scale = 2f64.powi(e as i32 - (128 + 8));
+    Color::new(r as f64 * scale, g as f64 * scale, b as f64 * scale)
+}
+
+fn invalid_data(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(width: usize, height: usize) -> Vec<u8> {
+        format!("#?RADIANCE\n# made by hand\nFORMAT=32-bit_rle_rgbe\n\n-Y {height} +X {width}\n")
+            .into_bytes()
+    }
+
+    /// Reading flat RGBE pixels.
+    #[test]
+    fn parse_flat_hdr() {
+        let mut data = header(2, 1);
+        data.extend_from_slice(&[128, 64, 0, 129, 0, 0, 0, 0]);
+        let c = parse_hdr(data.as_slice()).unwrap();
+        assert_eq!(c.width(), 2);
+        assert_eq!(c.height(), 1);
+        assert_eq!(c.pixel_at(0, 0), Color::new(1, 0.5, 0));
+        assert_eq!(c.pixel_at(1, 0), Color::new(0, 0, 0));
+    }
+
+    /// Reading run length encoded scanlines.
+    #[test]
+    fn parse_rle_hdr() {
+        let mut data = header(8, 2);
+        for _ in 0..2 {
+            data.extend_from_slice(&[2, 2, 0, 8]);
+            // Red: a run of eight 128.
+            data.extend_from_slice(&[128 + 8, 128]);
+            // Green: four literal values, then a run of four 0.
+            data.extend_from_slice(&[4, 0, 64, 128, 255, 128 + 4, 0]);
+            // Blue: a run of eight 0.
+            data.extend_from_slice(&[128 + 8, 0]);
+            // Exponent: a run of eight 132, scaling by 2^-4.
+            data.extend_from_slice(&[128 + 8, 132]);
+        }
+        let c = parse_hdr(data.as_slice()).unwrap();
+        assert_eq!(c.pixel_at(0, 1), Color::new(8, 0, 0));
+        assert_eq!(c.pixel_at(2, 0), Color::new(8, 8, 0));
+        assert_eq!(c.pixel_at(7, 1), Color::new(8, 0, 0));
+    }
+
+    /// Malformed HDR files are rejected.
+    #[test]
+    fn parse_invalid_hdr() {
+        assert!(parse_hdr("P3\n1 1\n255\n".as_bytes()).is_err());
+        let mut truncated = header(2, 2);
+        truncated.extend_from_slice(&[128, 64, 0, 129]);
+        let err = parse_hdr(truncated.as_slice()).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let flipped = b"#?RADIANCE\n\n+Y 1 +X 1\n\0\0\0\0".to_vec();
+        assert!(parse_hdr(flipped.as_slice()).is_err());
+        for (width, height) in [(0, 1), (1, 0), (70000, 70000), (1 << 33, 1)] {
+            let mut data = header(width, height);
+            data.extend_from_slice(&[0; 64]);
+            let err = parse_hdr(data.as_slice()).err().unwrap();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+        }
+    }
+}
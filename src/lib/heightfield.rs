@@ -0,0 +1,242 @@
+use crate::canvas::Canvas;
+use crate::cube::Cube;
+use crate::ray::Ray;
+use crate::shape::{Intersection, Shape};
+use crate::triangle::intersect_triangle;
+use crate::tuple::{Point, Vector};
+
+/// Terrain given by a grid of heights. The sample at column x and row z sits at
+/// point (x, height, z), so the field spans 0..width - 1 along x and 0..depth - 1
+/// along z. Each grid cell is split into two triangles along its diagonal, which are
+/// intersected on the fly while walking the cells crossed by the ray.
+#[derive(Debug, Clone)]
+pub struct Heightfield {
+    width: usize,
+    depth: usize,
+    heights: Vec<f64>,
+    bounds: Cube,
+}
+
+impl Heightfield {
+    /// Return a new Heightfield from width * depth heights stored row by row, or None
+    /// unless there are at least 2x2 of them.
+    pub fn new(width: usize, depth: usize, heights: Vec<f64>) -> Option<Self> {
+        if width < 2 || depth < 2 || width.checked_mul(depth) != Some(heights.len()) {
+            return None;
+        }
+
+        let min = heights.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = heights.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        Some(Heightfield {
+            width,
+            depth,
+            heights,
+            bounds: Cube::new(
+                Point::new(0, min, 0),
+                Point::new((width - 1) as f64, max, (depth - 1) as f64),
+            ),
+        })
+    }
+
+    /// Return a new Heightfield from the brightness of a grayscale image, scaled so
+    /// that white is max_height high, or None unless the image is at least 2x2 pixels.
+    pub fn from_canvas(canvas: &Canvas, max_height: f64) -> Option<Self> {
+        let heights = canvas
+            .flat()
+            .map(|c| (c.red() + c.green() + c.blue()) / 3.0 * max_height)
+            .collect();
+        Heightfield::new(canvas.width() as usize, canvas.height() as usize, heights)
+    }
+
+    /// Returns the height of the sample at column x and row z.
+    pub fn height_at(&self, x: usize, z: usize) -> f64 {
+        self.heights[z * self.width + x]
+    }
+
+    fn corner(&self, x: usize, z: usize) -> Point {
+        Point::new(x as f64, self.height_at(x, z), z as f64)
+    }
+
+    /// Returns the two triangles of a cell, wound so that their normals point up.
+    fn cell_triangles(&self, x: usize, z: usize) -> [(Point, Point, Point); 2] {
+        let p00 = self.corner(x, z);
+        let p10 = self.corner(x + 1, z);
+        let p01 = self.corner(x, z + 1);
+        let p11 = self.corner(x + 1, z + 1);
+        [(p00, p11, p10), (p00, p01, p11)]
+    }
+}
+
+impl Shape for Heightfield {
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let span = self.bounds.local_intersect(ray);
+        if span.is_empty() {
+            return vec![];
+        }
+        let (t_start, t_end) = (span[0].t, span[1].t);
+
+        // Walk the grid cells under the ray (2D DDA in the xz plane).
+        let start = ray.position(t_start);
+        let last_x = self.width as isize - 2;
+        let last_z = self.depth as isize - 2;
+        let mut x = (start.x().floor() as isize).clamp(0, last_x);
+        let mut z = (start.z().floor() as isize).clamp(0, last_z);
+
+        let axis = |origin: f64, direction: f64, cell: isize| {
+            if direction > 0.0 {
+                (1, (cell as f64 + 1.0 - origin) / direction, 1.0 / direction)
+            } else if direction < 0.0 {
+                (-1, (cell as f64 - origin) / direction, -1.0 / direction)
+            } else {
+                (0, f64::INFINITY, f64::INFINITY)
+            }
+        };
+        let (step_x, mut next_x, delta_x) = axis(ray.origin.x(), ray.direction.x(), x);
+        let (step_z, mut next_z, delta_z) = axis(ray.origin.z(), ray.direction.z(), z);
+
+        let mut xs: Vec<Intersection> = vec![];
+        while (0..=last_x).contains(&x) && (0..=last_z).contains(&z) {
+            let mut cell_hits: Vec<f64> = self
+                .cell_triangles(x as usize, z as usize)
+                .iter()
+                .filter_map(|&(p1, p2, p3)| intersect_triangle(ray, p1, p2, p3))
+                .map(|(t, _, _)| t)
+                .collect();
+            cell_hits.sort_by(|a, b| a.total_cmp(b));
+
+            for t in cell_hits {
+                // Hits on an edge shared by two triangles are found twice.
+                if xs.last().is_some_and(|x| (x.t - t).abs() < 1e-9) {
+                    continue;
+                }
+                let point = ray.position(t);
+                xs.push(Intersection::with_uv(
+                    t,
+                    self,
+                    point.x() / (self.width - 1) as f64,
+                    point.z() / (self.depth - 1) as f64,
+                ));
+            }
+
+            if next_x.min(next_z) > t_end {
+                break;
+            }
+            if next_x < next_z {
+                x += step_x;
+                next_x += delta_x;
+            } else {
+                z += step_z;
+                next_z += delta_z;
+            }
+        }
+
+        xs
+    }
+
+    fn local_normal_at(&self, point: Point, _hit: &Intersection) -> Vector {
+        let x = (point.x().floor().max(0.0) as usize).min(self.width - 2);
+        let z = (point.z().floor().max(0.0) as usize).min(self.depth - 2);
+        let [below, above] = self.cell_triangles(x, z);
+        // The diagonal runs from (x, z) to (x + 1, z + 1).
+        let (p1, p2, p3) = if point.x() - x as f64 >= point.z() - z as f64 {
+            below
+        } else {
+            above
+        };
+        (p2 - p1).cross(&(p3 - p1)).normalize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approximate_equation::ApproximateEq;
+    use crate::color::Color;
+
+    fn ts(xs: &[Intersection]) -> Vec<f64> {
+        xs.iter().map(|x| x.t).collect()
+    }
+
+    /// A ray straight down hits a flat heightfield at its height.
+    #[test]
+    fn ray_hits_flat_heightfield() {
+        let h = Heightfield::new(3, 3, vec![0.5; 9]).unwrap();
+        let r = Ray::new(Point::new(1.2, 5, 0.7), Vector::new(0, -1, 0));
+        let xs = h.local_intersect(&r);
+        assert_eq!(ts(&xs), vec![4.5]);
+        assert!(xs[0].u.approx_eq(&0.6));
+        assert!(xs[0].v.approx_eq(&0.35));
+    }
+
+    /// A ray outside of the grid misses the heightfield.
+    #[test]
+    fn ray_misses_heightfield() {
+        let h = Heightfield::new(3, 3, vec![0.5; 9]).unwrap();
+        let r = Ray::new(Point::new(2.5, 5, 1), Vector::new(0, -1, 0));
+        assert!(h.local_intersect(&r).is_empty());
+    }
+
+    /// A horizontal ray crosses both slopes of a hill.
+    #[test]
+    fn ray_through_hill() {
+        #[rustfmt::skip]
+        let h = Heightfield::new(3, 3, vec![
+            0.0, 0.0, 0.0,
+            0.0, 2.0, 0.0,
+            0.0, 0.0, 0.0,
+        ]).unwrap();
+        let r = Ray::new(Point::new(-1, 1, 1), Vector::new(1, 0, 0));
+        let xs = h.local_intersect(&r);
+        assert_eq!(ts(&xs), vec![1.5, 2.5]);
+    }
+
+    /// A ray walking many cells hits the far side of a slope.
+    #[test]
+    fn ray_walks_cells() {
+        // Heights rise by 1 per row along z.
+        let heights = (0..10).flat_map(|z| [z as f64; 10]).collect();
+        let h = Heightfield::new(10, 10, heights).unwrap();
+        let r = Ray::new(Point::new(0.5, 6, 0.5), Vector::new(0, 0, 1));
+        let xs = h.local_intersect(&r);
+        assert_eq!(xs.len(), 1);
+        assert!(xs[0].t.approx_eq(&5.5));
+    }
+
+    /// The normal of a heightfield follows the slope of the cell.
+    #[test]
+    fn normal_heightfield() {
+        let h = Heightfield::new(2, 2, vec![0.0, 1.0, 0.0, 1.0]).unwrap();
+        let hit = Intersection::new(0.0, &h);
+        let k = 2f64.sqrt() / 2.0;
+        assert_eq!(
+            h.local_normal_at(Point::new(0.5, 0.5, 0.2), &hit),
+            Vector::new(-k, k, 0)
+        );
+        assert_eq!(
+            h.local_normal_at(Point::new(0.2, 0.2, 0.5), &hit),
+            Vector::new(-k, k, 0)
+        );
+    }
+
+    /// Building a heightfield from a grayscale image.
+    #[test]
+    fn heightfield_from_canvas() {
+        let canvas = Canvas::from_ppm("P2\n2 2\n4\n0 1\n2 4\n".as_bytes()).unwrap();
+        let h = Heightfield::from_canvas(&canvas, 10.0).unwrap();
+        assert_eq!(h.height_at(0, 0), 0.0);
+        assert_eq!(h.height_at(1, 0), 2.5);
+        assert_eq!(h.height_at(0, 1), 5.0);
+        assert_eq!(h.height_at(1, 1), 10.0);
+
+        let mut canvas = Canvas::new(2, 2);
+        canvas.set_all_pixel(Color::new(0.5, 0.5, 0.5));
+        let r = Ray::new(Point::new(0.5, 2, 0.5), Vector::new(0, -1, 0));
+        let h = Heightfield::from_canvas(&canvas, 2.0).unwrap();
+        let xs = h.local_intersect(&r);
+        assert_eq!(ts(&xs), vec![1.0]);
+
+        // A single row of samples has no cells.
+        assert!(Heightfield::from_canvas(&Canvas::new(1, 5), 2.0).is_none());
+        assert!(Heightfield::new(2, 2, vec![0.0; 3]).is_none());
+    }
+}
@@ -0,0 +1,323 @@
+use crate::canvas::Canvas;
+use crate::color::Color;
+use crate::uv::UvPattern;
+use std::fmt;
+
+/// How colors are looked up between the pixels of an image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    /// The pixel the coordinates fall in, for a crisp, blocky look up close.
+    Nearest,
+    /// A blend of the four pixels around the coordinates.
+    Bilinear,
+}
+
+/// How an image repeats outside of u, v coordinates from 0 to 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wrap {
+    /// Tile the image.
+    Repeat,
+    /// Stretch the pixels of the edges.
+    Clamp,
+    /// Tile the image, flipping every other tile so that the edges meet seamlessly.
+    Mirror,
+}
+
+/// An image used as a 2D texture, such as a Canvas loaded from a PPM or HDR file.
+///
+/// u goes from the left of the image to its right and v from its bottom to its top.
+/// Pixels are squares, with the coordinates of their centers at half integers.
+/// Mipmaps, when built, hold the image halved again and again, down to a single pixel,
+/// so that textures seen from afar can be looked up without shimmering. They are used
+/// by rays standing for beams of some width, such as rays given a cone by a camera.
+pub struct ImageTexture {
+    /// The image, followed by its mipmaps.
+    levels: Vec<Canvas>,
+    pub filter: Filter,
+    pub wrap: Wrap,
+}
+
+impl ImageTexture {
+    /// Return a new ImageTexture with bilinear filtering, repeating, without mipmaps.
+    pub fn new(image: Canvas) -> Self {
+        ImageTexture {
+            levels: vec![image],
+            filter: Filter::Bilinear,
+            wrap: Wrap::Repeat,
+        }
+    }
+
+    /// Return the texture with the filter and wrap mode.
+    pub fn with_sampling(self, filter: Filter, wrap: Wrap) -> Self {
+        ImageTexture {
+            filter,
+            wrap,
+            ..self
+        }
+    }
+
+    /// Return the texture with mipmaps, each level averaging the pixels of the previous
+    /// one two by two.
+    pub fn with_mipmaps(mut self) -> Self {
+        self.levels.truncate(1);
+        loop {
+            let last = &self.levels[self.levels.len() - 1];
+            let (width, height) = (last.width(), last.height());
+            if width <= 1 && height <= 1 {
+                break;
+            }
+            let mut level = Canvas::new((width / 2).max(1), (height / 2).max(1));
+            for y in 0..level.height() {
+                let ys = texel_span(y, height, level.height());
+                for x in 0..level.width() {
+                    let xs = texel_span(x, width, level.width());
+                    let mut sum = Color::new(0, 0, 0);
+                    for &(py, wy) in &ys {
+                        for &(px, wx) in &xs {
+                            sum = sum + last.pixel_at(px, py) * (wx * wy);
+                        }
+                    }
+                    level.write_pixel(x as i32, y as i32, sum);
+                }
+            }
+            self.levels.push(level);
+        }
+        self
+    }
+
+    /// Returns the number of levels: 1 for the image alone, more with mipmaps.
+    pub fn levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Returns the image of a level, 0 being the full size image.
+    pub fn level(&self, level: usize) -> &Canvas {
+        &self.levels[level]
+    }
+
+    /// Returns the color at u, v of the full size image.
+    pub fn sample(&self, u: f64, v: f64) -> Color {
+        self.sample_level(0, u, v)
+    }
+
+    /// Returns the color at u, v over a footprint of about width pixels of the full
+    /// size image, blending the two closest mipmap levels. Without mipmaps it is the
+    /// same as sample.
+    pub fn sample_footprint(&self, u: f64, v: f64, width: f64) -> Color {
+        let last = (self.levels.len() - 1) as f64;
+        let lod = width.max(1.0).log2().clamp(0.0, last);
+        let (lower, t) = (lod.floor(), lod.fract());
+        let color = self.sample_level(lower as usize, u, v);
+        if t == 0.0 {
+            return color;
+        }
+        color * (1.0 - t) + self.sample_level(lower as usize + 1, u, v) * t
+    }
+
+    fn sample_level(&self, level: usize, u: f64, v: f64) -> Color {
+        let image = &self.levels[level];
+        let (width, height) = (image.width() as f64, image.height() as f64);
+        let (x, y) = (u * width, (1.0 - v) * height);
+        match self.filter {
+            Filter::Nearest => self.texel(image, x.floor() as i64, y.floor() as i64),
+            Filter::Bilinear => {
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (tx, ty) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+                let top =
+                    self.texel(image, x0, y0) * (1.0 - tx) + self.texel(image, x0 + 1, y0) * tx;
+                let bottom = self.texel(image, x0, y0 + 1) * (1.0 - tx)
+                    + self.texel(image, x0 + 1, y0 + 1) * tx;
+                top * (1.0 - ty) + bottom * ty
+            }
+        }
+    }
+
+    /// Returns a pixel of image, its coordinates wrapped into the image.
+    fn texel(&self, image: &Canvas, x: i64, y: i64) -> Color {
+        let x = wrap(self.wrap, x, image.width() as i64);
+        let y = wrap(self.wrap, y, image.height() as i64);
+        image.pixel_at(x as i32, y as i32)
+    }
+}
+
+/// Returns the pixels of a row or column of size pixels covered by texel index of the
+/// same row or column shrunk to texels, with the share of each in the texel. Texels of
+/// odd sides cover part of a pixel on their edge, so that every pixel counts.
+fn texel_span(index: u32, size: u32, texels: u32) -> Vec<(i32, f64)> {
+    let scale = size as f64 / texels as f64;
+    let (start, end) = (index as f64 * scale, (index + 1) as f64 * scale);
+    (start.floor() as i32..end.ceil() as i32)
+        .map(|pixel| {
+            let covered = end.min(pixel as f64 + 1.0) - start.max(pixel as f64);
+            (pixel, covered / scale)
+        })
+        .collect()
+}
+
+/// Returns the index of a pixel of a row or column of size pixels.
+fn wrap(mode: Wrap, index: i64, size: i64) -> i64 {
+    match mode {
+        Wrap::Repeat => index.rem_euclid(size),
+        Wrap::Clamp => index.clamp(0, size - 1),
+        Wrap::Mirror => {
+            let index = index.rem_euclid(2 * size);
+            if index < size {
+                index
+            } else {
+                2 * size - 1 - index
+            }
+        }
+    }
+}
+
+impl fmt::Debug for ImageTexture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImageTexture")
+            .field("width", &self.levels[0].width())
+            .field("height", &self.levels[0].height())
+            .field("levels", &self.levels.len())
+            .field("filter", &self.filter)
+            .field("wrap", &self.wrap)
+            .finish()
+    }
+}
+
+impl UvPattern for ImageTexture {
+    fn uv_color_at(&self, u: f64, v: f64) -> Color {
+        self.sample(u, v)
+    }
+
+    fn uv_color_over(&self, u: f64, v: f64, width: f64) -> Color {
+        let image = &self.levels[0];
+        let pixels = width * image.width().max(image.height()) as f64;
+        self.sample_footprint(u, v, pixels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pattern::Pattern;
+    use crate::tuple::Point;
+    use crate::uv::{TextureMap, UvMapping};
+    use std::rc::Rc;
+
+    /// A 2x2 image: black and red on top, green and blue at the bottom.
+    fn image() -> Canvas {
+        let mut canvas = Canvas::new(2, 2);
+        canvas.write_pixel(1, 0, Color::new(1, 0, 0));
+        canvas.write_pixel(0, 1, Color::new(0, 1, 0));
+        canvas.write_pixel(1, 1, Color::new(0, 0, 1));
+        canvas
+    }
+
+    /// Nearest filtering picks the pixel the coordinates fall in, v going up.
+    #[test]
+    fn nearest_filtering() {
+        let texture = ImageTexture::new(image()).with_sampling(Filter::Nearest, Wrap::Repeat);
+        assert_eq!(texture.sample(0.25, 0.75), Color::new(0, 0, 0));
+        assert_eq!(texture.sample(0.75, 0.75), Color::new(1, 0, 0));
+        assert_eq!(texture.sample(0.25, 0.25), Color::new(0, 1, 0));
+        assert_eq!(texture.sample(0.7, 0.1), Color::new(0, 0, 1));
+    }
+
+    /// Bilinear filtering blends the pixels around the coordinates.
+    #[test]
+    fn bilinear_filtering() {
+        let texture = ImageTexture::new(image()).with_sampling(Filter::Bilinear, Wrap::Clamp);
+        assert_eq!(texture.sample(0.25, 0.75), Color::new(0, 0, 0));
+        assert_eq!(texture.sample(0.5, 0.75), Color::new(0.5, 0, 0));
+        assert_eq!(texture.sample(0.5, 0.5), Color::new(0.25, 0.25, 0.25));
+        assert_eq!(texture.sample(0.375, 0.25), Color::new(0, 0.75, 0.25));
+        // Clamped edges keep the color of the edge pixels.
+        assert_eq!(texture.sample(0.0, 1.0), Color::new(0, 0, 0));
+    }
+
+    /// Coordinates outside of the image repeat, clamp or mirror it.
+    #[test]
+    fn wrap_modes() {
+        let sample = |wrap, u, v| {
+            ImageTexture::new(image())
+                .with_sampling(Filter::Nearest, wrap)
+                .sample(u, v)
+        };
+        assert_eq!(sample(Wrap::Repeat, 1.75, 0.75), Color::new(1, 0, 0));
+        assert_eq!(sample(Wrap::Repeat, -0.25, 0.75), Color::new(1, 0, 0));
+        assert_eq!(sample(Wrap::Clamp, 1.75, 0.75), Color::new(1, 0, 0));
+        assert_eq!(sample(Wrap::Clamp, -3.0, -3.0), Color::new(0, 1, 0));
+        assert_eq!(sample(Wrap::Mirror, 1.25, 0.75), Color::new(1, 0, 0));
+        assert_eq!(sample(Wrap::Mirror, 1.75, 0.75), Color::new(0, 0, 0));
+        assert_eq!(sample(Wrap::Mirror, -0.25, 0.75), Color::new(0, 0, 0));
+        assert_eq!(wrap(Wrap::Mirror, 5, 3), 0);
+    }
+
+    /// Mipmaps halve the image down to a single pixel of its average color.
+    #[test]
+    fn mipmaps() {
+        let mut canvas = Canvas::new(5, 3);
+        canvas.set_all_pixel(Color::new(0.2, 0.4, 0.6));
+        let texture = ImageTexture::new(canvas).with_mipmaps();
+        assert_eq!(texture.levels(), 3);
+        assert_eq!(
+            (texture.level(1).width(), texture.level(1).height()),
+            (2, 1)
+        );
+        assert_eq!(texture.level(2).pixel_at(0, 0), Color::new(0.2, 0.4, 0.6));
+
+        let texture = ImageTexture::new(image()).with_mipmaps();
+        assert_eq!(texture.levels(), 2);
+        let average = Color::new(0.25, 0.25, 0.25);
+        assert_eq!(texture.level(1).pixel_at(0, 0), average);
+        // Wide footprints use the smaller levels.
+        assert_eq!(
+            texture.sample_footprint(0.25, 0.75, 1.0),
+            Color::new(0, 0, 0)
+        );
+        assert_eq!(texture.sample_footprint(0.25, 0.75, 2.0), average);
+        assert_eq!(texture.sample_footprint(0.25, 0.75, 64.0), average);
+        assert_eq!(
+            texture.sample_footprint(0.25, 0.75, 2f64.sqrt()),
+            average * 0.5
+        );
+    }
+
+    /// Texels of odd sides share the pixels in between, so that the last column and
+    /// row count too.
+    #[test]
+    fn mipmaps_odd_sides() {
+        let mut canvas = Canvas::new(5, 3);
+        for y in 0..3 {
+            canvas.write_pixel(4, y, Color::new(1, 1, 1));
+        }
+        let texture = ImageTexture::new(canvas).with_mipmaps();
+        assert_eq!(texture.level(1).pixel_at(0, 0), Color::new(0, 0, 0));
+        assert_eq!(texture.level(1).pixel_at(1, 0), Color::new(0.4, 0.4, 0.4));
+        assert_eq!(texture.level(2).pixel_at(0, 0), Color::new(0.2, 0.2, 0.2));
+
+        let mut canvas = Canvas::new(5, 3);
+        for x in 0..5 {
+            canvas.write_pixel(x, 2, Color::new(1, 0, 0));
+        }
+        let texture = ImageTexture::new(canvas).with_mipmaps();
+        let third = Color::new(1.0 / 3.0, 0, 0);
+        assert_eq!(texture.level(1).pixel_at(0, 0), third);
+        assert_eq!(texture.level(2).pixel_at(0, 0), third);
+    }
+
+    /// An image wrapped around a sphere.
+    #[test]
+    fn image_on_sphere() {
+        let texture = ImageTexture::new(image()).with_sampling(Filter::Nearest, Wrap::Repeat);
+        let pattern = TextureMap::new(Rc::new(texture), UvMapping::Spherical);
+        assert_eq!(
+            pattern.color_at(Point::new(-0.1, 1, 0)),
+            Color::new(1, 0, 0)
+        );
+        assert_eq!(
+            pattern.color_at(Point::new(-0.1, -1, 0)),
+            Color::new(0, 0, 1)
+        );
+    }
+}
@@ -0,0 +1,248 @@
+use crate::bump::SurfaceFrame;
+use crate::material::Material;
+use crate::matrix::Matrix;
+use crate::ray::Ray;
+use crate::shape::{Intersection, Shape};
+use crate::tuple::{Point, Vector};
+use std::rc::Rc;
+
+/// A shape placed in the scene with its own transformation and material.
+///
+/// The shape is shared, so placing a mesh many times keeps a single copy of its
+/// triangles and bounding groups however many instances refer to it.
+#[derive(Clone)]
+pub struct Instance {
+    pub shape: Rc<dyn Shape>,
+    pub material: Material,
+    /// Set when a light of the world samples the emission of this instance directly, as
+    /// World::add_mesh_light does. The path tracer then only adds the emission seen
+    /// where light sampling could not account for it.
+    pub is_light: bool,
+    transform: Matrix,
+    inverse: Matrix,
+}
+
+impl Instance {
+    /// Return a new Instance of shape, moved from object space into the scene by transform.
+    pub fn new(shape: Rc<dyn Shape>, transform: Matrix, material: Material) -> Self {
+        Instance {
+            shape,
+            material,
+            is_light: false,
+            transform,
+            inverse: transform.inverse(),
+        }
+    }
+
+    /// Returns the transformation from object space to world space.
+    pub fn transform(&self) -> Matrix {
+        self.transform
+    }
+
+    /// Replace the transformation from object space to world space.
+    pub fn set_transform(&mut self, transform: Matrix) {
+        self.transform = transform;
+        self.inverse = transform.inverse();
+    }
+
+    /// Returns the intersections of a world space ray with the shape. The t of each
+    /// intersection is a distance along the world space ray.
+    pub fn intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        self.shape.local_intersect(&(self.inverse * *ray))
+    }
+
+    /// Returns the material at a world space point of a hit on this instance, colored by
+    /// its pattern or, without one, by the color of the shape at the hit, such as the
+    /// vertex colors of a mesh. The pattern is blurred over a footprint width world space
+    /// units wide, the width of the beam of the ray which found the hit.
+    pub fn material_at(&self, point: Point, hit: &Intersection, width: f64) -> Material {
+        let object_point = self.inverse * point;
+        if self.material.pattern.is_none() {
+            if let Some(color) = hit.color_at(object_point) {
+                return self.material.with_color(color);
+            }
+        }
+        self.material
+            .over(object_point, width * self.inverse.stretch())
+    }
+
+    /// Returns the world space normal at a world space point of a hit on this instance.
+    pub fn normal_at(&self, point: Point, hit: &Intersection) -> Vector {
+        let object_normal = hit.normal_at(self.inverse * point);
+        (self.inverse.transpose() * object_normal).normalize()
+    }
+
+    /// Returns the world space normal to shade a world space point of a hit on this
+    /// instance with: the normal tilted by the bump of the material, if it has one.
+    pub fn shading_normal_at(&self, point: Point, hit: &Intersection) -> Vector {
+        let Some(bump) = &self.material.bump else {
+            return self.normal_at(point, hit);
+        };
+        let object_point = self.inverse * point;
+        let object_normal = bump.normal_at(&SurfaceFrame {
+            point: object_point,
+            normal: hit.normal_at(object_point).normalize(),
+            uv: hit.uv_at(object_point),
+            tangent: hit.tangent_at(object_point),
+        });
+        (self.inverse.transpose() * object_normal).normalize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bump::NormalMap;
+    use crate::canvas::Canvas;
+    use crate::color::Color;
+    use crate::image_texture::ImageTexture;
+    use crate::mesh::TriangleMesh;
+    use crate::sphere::Sphere;
+    use crate::uv::UvMapping;
+    use std::f64::consts::PI;
+
+    fn ts(xs: &[Intersection]) -> Vec<f64> {
+        xs.iter().map(|x| x.t).collect()
+    }
+
+    /// Intersecting a scaled shape with a ray.
+    #[test]
+    fn intersect_scaled_instance() {
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        let i = Instance::new(
+            Rc::new(Sphere::default()),
+            Matrix::scaling(2, 2, 2),
+            Material::default(),
+        );
+        assert_eq!(ts(&i.intersect(&r)), vec![3.0, 7.0]);
+    }
+
+    /// Intersecting a translated shape with a ray.
+    #[test]
+    fn intersect_translated_instance() {
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        let mut i = Instance::new(
+            Rc::new(Sphere::default()),
+            Matrix::identity(),
+            Material::default(),
+        );
+        i.set_transform(Matrix::translation(5, 0, 0));
+        assert!(i.intersect(&r).is_empty());
+    }
+
+    /// Computing the normal on a translated shape.
+    #[test]
+    fn normal_translated_instance() {
+        let i = Instance::new(
+            Rc::new(Sphere::default()),
+            Matrix::translation(0, 1, 0),
+            Material::default(),
+        );
+        let hit = Intersection::new(0.0, i.shape.as_ref());
+        let k = 2f64.sqrt() / 2.0;
+        let n = i.normal_at(Point::new(0, 1.0 + k, -k), &hit);
+        assert_eq!(n, Vector::new(0, k, -k));
+    }
+
+    /// Computing the normal on a transformed shape.
+    #[test]
+    fn normal_transformed_instance() {
+        let i = Instance::new(
+            Rc::new(Sphere::default()),
+            Matrix::rotation_z(PI / 5.0).then(Matrix::scaling(1, 0.5, 1)),
+            Material::default(),
+        );
+        let hit = Intersection::new(0.0, i.shape.as_ref());
+        let k = 2f64.sqrt() / 2.0;
+        let n = i.normal_at(Point::new(0, k, -k), &hit);
+        assert_eq!(n, Vector::new(0, 0.97014, -0.24254));
+    }
+
+    /// Instances of a mesh share its triangles.
+    #[test]
+    fn instances_share_shape() {
+        let mesh = TriangleMesh::new(
+            vec![
+                Point::new(-1, 0, 0),
+                Point::new(1, 0, 0),
+                Point::new(0, 1, 0),
+            ],
+            vec![[0, 1, 2]],
+        );
+        let group: Rc<dyn Shape> = Rc::new(mesh.to_group());
+        let red = Material {
+            color: Color::new(1, 0, 0),
+            ..Material::default()
+        };
+        let instances: Vec<Instance> = (0..10)
+            .map(|n| Instance::new(group.clone(), Matrix::translation(3 * n, 0, 0), red.clone()))
+            .collect();
+        assert_eq!(Rc::strong_count(&group), 11);
+
+        let r = Ray::new(Point::new(6, 0.5, -5), Vector::new(0, 0, 1));
+        let hits: Vec<usize> = instances
+            .iter()
+            .enumerate()
+            .filter(|(_, i)| !i.intersect(&r).is_empty())
+            .map(|(n, _)| n)
+            .collect();
+        assert_eq!(hits, vec![2]);
+        assert_eq!(instances[2].material.color, red.color);
+    }
+
+    /// A normal map tilts the shading normal of a mesh along its tangents, and turns
+    /// with the instance.
+    #[test]
+    fn shading_normal_mesh_normal_map() {
+        let mut mesh = TriangleMesh::new(
+            vec![
+                Point::new(-1, -1, 0),
+                Point::new(1, -1, 0),
+                Point::new(1, 1, 0),
+                Point::new(-1, 1, 0),
+            ],
+            vec![[0, 1, 2], [0, 2, 3]],
+        );
+        mesh.uvs = Some(vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]);
+        mesh.compute_tangents();
+        let mut image = Canvas::new(1, 1);
+        image.set_all_pixel(Color::new(1, 0.5, 1));
+        let normal_map = NormalMap::new(Rc::new(ImageTexture::new(image)), UvMapping::Planar);
+        let mut i = Instance::new(
+            Rc::new(mesh.to_group()),
+            Matrix::rotation_y(PI / 2.0),
+            Material::default(),
+        );
+        let r = Ray::new(Point::new(5, 0.2, 0.3), Vector::new(-1, 0, 0));
+        let xs = i.intersect(&r);
+        let point = r.position(xs[0].t);
+        assert_eq!(i.shading_normal_at(point, &xs[0]), Vector::new(1, 0, 0));
+
+        i.material.bump = Some(Rc::new(normal_map));
+        let xs = i.intersect(&r);
+        assert_eq!(i.normal_at(point, &xs[0]), Vector::new(1, 0, 0));
+        assert_eq!(
+            i.shading_normal_at(point, &xs[0]),
+            Vector::new(1, 0, -1).normalize()
+        );
+
+        // Tilts along v follow v up the mesh, whether the texture is mirrored or not.
+        let mut image = Canvas::new(1, 1);
+        image.set_all_pixel(Color::new(0.5, 1, 1));
+        let normal_map = NormalMap::new(Rc::new(ImageTexture::new(image)), UvMapping::Planar);
+        i.material.bump = Some(Rc::new(normal_map));
+        for uvs in [
+            vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+            vec![(1.0, 0.0), (0.0, 0.0), (0.0, 1.0), (1.0, 1.0)],
+        ] {
+            mesh.uvs = Some(uvs);
+            mesh.compute_tangents();
+            i.shape = Rc::new(mesh.to_group());
+            let xs = i.intersect(&r);
+            assert_eq!(
+                i.shading_normal_at(point, &xs[0]),
+                Vector::new(1, 1, 0).normalize()
+            );
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use crate::bsdf::{Bsdf, BsdfSample};
+use crate::color::Color;
+use crate::microfacet::{fresnel_schlick, ggx_distribution, smith_g1, PbrMaterial, MIN_ALPHA};
+use crate::random::{cosine_hemisphere, RandomSource};
+use crate::tuple::Vector;
+use std::f64::consts::PI;
+
+/// Reflectance of the clear coat at normal incidence, that of a varnish of index 1.5.
+const CLEARCOAT_F0: f64 = 0.04;
+
+/// Layered material for car paint, brushed metal and lacquered surfaces: a
+/// metallic/roughness base whose specular highlights may be stretched along a tangent
+/// direction, under an optional clear coat with its own roughness.
+///
+/// Light reflected by the coat does not reach the base, which is darkened accordingly
+/// on the way in and on the way out.
+#[derive(Debug, Clone, Copy)]
+pub struct LayeredMaterial {
+    pub base: PbrMaterial,
+    /// From 0, for round highlights, to 1, for highlights stretched along tangent.
+    pub anisotropy: f64,
+    /// World space direction of the brushing or the flakes, projected onto the surface
+    /// at each point.
+    pub tangent: Vector,
+    /// Strength of the clear coat, from 0 (none) to 1.
+    pub clearcoat: f64,
+    /// Perceptual roughness of the clear coat.
+    pub clearcoat_roughness: f64,
+}
+
+/// Orthonormal shading frame of a point, with the tangent along the brushing.
+struct Frame {
+    tangent: Vector,
+    bitangent: Vector,
+    normal: Vector,
+}
+
+impl Frame {
+    fn to_local(&self, v: Vector) -> (f64, f64, f64) {
+        (
+            v.dot(&self.tangent),
+            v.dot(&self.bitangent),
+            v.dot(&self.normal),
+        )
+    }
+
+    fn to_world(&self, (x, y, z): (f64, f64, f64)) -> Vector {
+        self.tangent * x + self.bitangent * y + self.normal * z
+    }
+}
+
+impl LayeredMaterial {
+    /// Return a new LayeredMaterial over base, isotropic and without a clear coat.
+    pub fn new(base: PbrMaterial) -> Self {
+        LayeredMaterial {
+            base,
+            anisotropy: 0.0,
+            tangent: Vector::new(1, 0, 0),
+            clearcoat: 0.0,
+            clearcoat_roughness: 0.0,
+        }
+    }
+
+    /// Return the material with highlights stretched along tangent by anisotropy.
+    pub fn with_anisotropy(self, anisotropy: f64, tangent: Vector) -> Self {
+        LayeredMaterial {
+            anisotropy,
+            tangent,
+            ..self
+        }
+    }
+
+    /// Return the material under a clear coat of strength clearcoat.
+    pub fn with_clearcoat(self, clearcoat: f64, roughness: f64) -> Self {
+        LayeredMaterial {
+            clearcoat,
+            clearcoat_roughness: roughness,
+            ..self
+        }
+    }
+
+    fn frame(&self, normal: Vector) -> Frame {
+        let projected = self.tangent - normal * normal.dot(&self.tangent);
+        let (tangent, bitangent) = if projected.magnitude() < 1e-6 {
+            normal.orthonormal_basis()
+        } else {
+            let tangent = projected.normalize();
+            (tangent, normal.cross(&tangent))
+        };
+        Frame {
+            tangent,
+            bitangent,
+            normal,
+        }
+    }
+
+    /// GGX alphas along the tangent and the bitangent.
+    fn alphas(&self) -> (f64, f64) {
+        let alpha = self.base.roughness * self.base.roughness;
+        let aspect = (1.0 - 0.9 * self.anisotropy.clamp(0.0, 1.0)).sqrt();
+        (
+            (alpha / aspect).max(MIN_ALPHA),
+            (alpha * aspect).max(MIN_ALPHA),
+        )
+    }
+
+    fn clearcoat_alpha(&self) -> f64 {
+        (self.clearcoat_roughness * self.clearcoat_roughness).max(MIN_ALPHA)
+    }
+
+    /// Share of the light the coat reflects at a cosine to the normal.
+    fn clearcoat_fresnel(&self, cos: f64) -> f64 {
+        let f0 = Color::new(CLEARCOAT_F0, CLEARCOAT_F0, CLEARCOAT_F0);
+        self.clearcoat.clamp(0.0, 1.0) * fresnel_schlick(f0, cos).red()
+    }
+
+    /// Probabilities of sampling the coat, the base highlight and the base diffuse lobe.
+    fn lobe_probabilities(&self, cos_o: f64) -> [f64; 3] {
+        let coat = self.clearcoat_fresnel(cos_o);
+        let specular = (1.0 - coat) * fresnel_schlick(self.base.f0(), cos_o).luminance();
+        let diffuse = (1.0 - coat) * self.base.base_color.luminance() * (1.0 - self.base.metallic);
+        let total = coat + specular + diffuse;
+        if total <= 0.0 {
+            return [0.0, 1.0, 0.0];
+        }
+        [coat / total, specular / total, diffuse / total]
+    }
+}
+
+/// Anisotropic GGX distribution of a microfacet normal in the local shading frame.
+fn ggx_anisotropic((x, y, z): (f64, f64, f64), ax: f64, ay: f64) -> f64 {
+    if z <= 0.0 {
+        return 0.0;
+    }
+    let d = x * x / (ax * ax) + y * y / (ay * ay) + z * z;
+    1.0 / (PI * ax * ay * d * d)
+}
+
+/// Smith masking of the anisotropic GGX distribution for a direction in the local
+/// shading frame.
+fn smith_g1_anisotropic((x, y, z): (f64, f64, f64), ax: f64, ay: f64) -> f64 {
+    if z <= 0.0 {
+        return 0.0;
+    }
+    let tan2 = (ax * ax * x * x + ay * ay * y * y) / (z * z);
+    2.0 / (1.0 + (1.0 + tan2).sqrt())
+}
+
+impl Bsdf for LayeredMaterial {
+    fn evaluate(&self, wo: Vector, wi: Vector, normal: Vector) -> Color {
+        let (cos_o, cos_i) = (wo.dot(&normal), wi.dot(&normal));
+        if cos_o <= 0.0 || cos_i <= 0.0 {
+            return Color::new(0, 0, 0);
+        }
+        let frame = self.frame(normal);
+        let h = (wo + wi).normalize();
+        let cos_d = wi.dot(&h);
+
+        let coat_alpha = self.clearcoat_alpha();
+        let coat = self.clearcoat_fresnel(cos_d)
+            * ggx_distribution(normal.dot(&h), coat_alpha)
+            * smith_g1(cos_o, coat_alpha)
+            * smith_g1(cos_i, coat_alpha)
+            / (4.0 * cos_o * cos_i);
+        let through_coat =
+            (1.0 - self.clearcoat_fresnel(cos_o)) * (1.0 - self.clearcoat_fresnel(cos_i));
+
+        let (ax, ay) = self.alphas();
+        let fresnel = fresnel_schlick(self.base.f0(), cos_d);
+        let specular = fresnel
+            * (ggx_anisotropic(frame.to_local(h), ax, ay)
+                * smith_g1_anisotropic(frame.to_local(wo), ax, ay)
+                * smith_g1_anisotropic(frame.to_local(wi), ax, ay)
+                / (4.0 * cos_o * cos_i));
+        let diffuse = (Color::new(1, 1, 1) - fresnel)
+            * self.base.base_color
+            * ((1.0 - self.base.metallic) / PI);
+
+        (Color::new(coat, coat, coat) + (specular + diffuse) * through_coat) * cos_i
+    }
+
+    fn pdf(&self, wo: Vector, wi: Vector, normal: Vector) -> f64 {
+        let (cos_o, cos_i) = (wo.dot(&normal), wi.dot(&normal));
+        if cos_o <= 0.0 || cos_i <= 0.0 {
+            return 0.0;
+        }
+        let frame = self.frame(normal);
+        let h = (wo + wi).normalize();
+        let (cos_h, o_dot_h) = (normal.dot(&h), wo.dot(&h));
+
+        let (ax, ay) = self.alphas();
+        let coat_pdf = ggx_distribution(cos_h, self.clearcoat_alpha()) * cos_h / (4.0 * o_dot_h);
+        let specular_pdf = ggx_anisotropic(frame.to_local(h), ax, ay) * cos_h / (4.0 * o_dot_h);
+        let [coat, specular, diffuse] = self.lobe_probabilities(cos_o);
+        coat * coat_pdf + specular * specular_pdf + diffuse * cos_i / PI
+    }
+
+    fn sample(&self, wo: Vector, normal: Vector, rng: &mut dyn RandomSource) -> Option<BsdfSample> {
+        let cos_o = wo.dot(&normal);
+        if cos_o <= 0.0 {
+            return None;
+        }
+        let [coat, specular, _] = self.lobe_probabilities(cos_o);
+        let lobe = rng.next_f64();
+        let direction = if lobe < coat + specular {
+            // Sample the slope of a microfacet, stretched by the alphas of the lobe, and
+            // mirror wo on it.
+            let (ax, ay) = if lobe < coat {
+                (self.clearcoat_alpha(), self.clearcoat_alpha())
+            } else {
+                self.alphas()
+            };
+            let (xi1, xi2) = (rng.next_f64(), rng.next_f64());
+            let slope = (xi1 / (1.0 - xi1).max(1e-12)).sqrt();
+            let phi = 2.0 * PI * xi2;
+            let frame = self.frame(normal);
+            let h = frame
+                .to_world((ax * slope * phi.cos(), ay * slope * phi.sin(), 1.0))
+                .normalize();
+            (-wo).reflect(&h)
+        } else {
+            cosine_hemisphere(normal, rng)
+        };
+
+        let pdf = self.pdf(wo, direction, normal);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(BsdfSample {
+            direction,
+            weight: self.evaluate(wo, direction, normal) * (1.0 / pdf),
+            pdf,
+            specular: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::XorShift;
+
+    fn up() -> Vector {
+        Vector::new(0, 1, 0)
+    }
+
+    fn paint() -> LayeredMaterial {
+        LayeredMaterial::new(PbrMaterial::new(Color::new(0.6, 0.05, 0.05), 0.5, 0.4, 1.5))
+    }
+
+    /// Without anisotropy or a clear coat, the base reflects like a PbrMaterial.
+    #[test]
+    fn plain_base_matches_pbr() {
+        let m = paint();
+        let wo = Vector::new(0.3, 1, 0.2).normalize();
+        let mut rng = XorShift::new(3);
+        for _ in 0..50 {
+            let wi = cosine_hemisphere(up(), &mut rng);
+            assert_eq!(m.evaluate(wo, wi, up()), m.base.evaluate(wo, wi, up()));
+        }
+    }
+
+    /// Anisotropic highlights spread further along the tangent than across it.
+    #[test]
+    fn anisotropic_highlight() {
+        let brushed = LayeredMaterial::new(PbrMaterial::new(Color::new(1, 1, 1), 1.0, 0.3, 1.5))
+            .with_anisotropy(0.9, Vector::new(1, 0, 0));
+        let (sin, cos) = 0.5f64.sin_cos();
+        let along = brushed.evaluate(up(), Vector::new(sin, cos, 0), up());
+        let across = brushed.evaluate(up(), Vector::new(0, cos, sin), up());
+        assert!(along.red() > 2.0 * across.red(), "{along:?} {across:?}");
+        // The tangent is projected onto the surface.
+        let tilted = brushed.with_anisotropy(0.9, Vector::new(1, 1, 0));
+        assert_eq!(tilted.evaluate(up(), Vector::new(sin, cos, 0), up()), along);
+    }
+
+    /// A clear coat adds a sharp reflection to a rough base.
+    #[test]
+    fn clearcoat_reflection() {
+        let base = LayeredMaterial::new(PbrMaterial::new(Color::new(0.1, 0.1, 0.1), 0.0, 0.9, 1.5));
+        let coated = base.with_clearcoat(1.0, 0.05);
+        let wo = Vector::new(1, 1, 0).normalize();
+        let mirror = Vector::new(-1, 1, 0).normalize();
+        assert!(
+            coated.evaluate(wo, mirror, up()).red() > 10.0 * base.evaluate(wo, mirror, up()).red()
+        );
+        // Light reflected by the coat no longer reaches the base.
+        let aside = Vector::new(0.3, 1, 0.8).normalize();
+        assert!(coated.evaluate(wo, aside, up()).red() < base.evaluate(wo, aside, up()).red());
+    }
+
+    /// Sampled weights match evaluate / pdf and conserve energy.
+    #[test]
+    fn sample_consistent_with_evaluate() {
+        let mut rng = XorShift::new(7);
+        let wo = Vector::new(0.4, 1, -0.3).normalize();
+        for m in [
+            paint().with_clearcoat(1.0, 0.1),
+            paint().with_anisotropy(0.8, Vector::new(0, 0, 1)),
+            LayeredMaterial::new(PbrMaterial::new(Color::new(1, 1, 1), 1.0, 0.5, 1.5))
+                .with_anisotropy(1.0, Vector::new(1, 0, 1))
+                .with_clearcoat(0.5, 0.2),
+        ] {
+            let n = 20_000;
+            let mut total = 0.0;
+            for _ in 0..n {
+                if let Some(s) = m.sample(wo, up(), &mut rng) {
+                    let expected = m.evaluate(wo, s.direction, up()) * (1.0 / s.pdf);
+                    assert_eq!(s.weight, expected);
+                    assert!((s.pdf - m.pdf(wo, s.direction, up())).abs() < 1e-9);
+                    total += s.weight.luminance();
+                }
+            }
+            let albedo = total / n as f64;
+            assert!(albedo > 0.1 && albedo <= 1.01, "{m:?}: {albedo}");
+        }
+    }
+}
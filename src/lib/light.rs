@@ -0,0 +1,634 @@
+use crate::bsdf::Bsdf;
+use crate::color::Color;
+use crate::material::Material;
+use crate::random::RandomSource;
+use crate::tuple::{Point, Vector};
+use std::f64::consts::PI;
+
+/// Light reaching a point from one point of a light source.
+#[derive(Debug, Clone, Copy)]
+pub struct LightSample {
+    /// Unit vector from the lit point toward the light.
+    pub direction: Vector,
+    /// Distance from the lit point to the light, which shadow rays must not go beyond.
+    pub distance: f64,
+    /// Light arriving at the lit point.
+    pub intensity: Color,
+}
+
+/// Source of light in a scene.
+pub trait Light {
+    /// Returns the intensity of the light, from which the ambient term is computed.
+    fn intensity(&self) -> Color;
+
+    /// Returns samples of the light seen from point. Lighting and shadows are averaged
+    /// over all of them, so lights with an extent return several.
+    fn samples(&self, point: Point, rng: &mut dyn RandomSource) -> Vec<LightSample>;
+}
+
+/// Falloff of a light with distance d: its intensity is divided by
+/// constant + linear * d + quadratic * d².
+#[derive(Debug, Clone, Copy)]
+pub struct Attenuation {
+    pub constant: f64,
+    pub linear: f64,
+    pub quadratic: f64,
+}
+
+impl Attenuation {
+    /// Return a new Attenuation.
+    pub fn new(constant: f64, linear: f64, quadratic: f64) -> Self {
+        Attenuation {
+            constant,
+            linear,
+            quadratic,
+        }
+    }
+
+    /// Returns the fraction of the intensity left at distance.
+    pub fn factor(&self, distance: f64) -> f64 {
+        1.0 / (self.constant + distance * (self.linear + distance * self.quadratic))
+    }
+}
+
+/// No falloff with distance.
+impl Default for Attenuation {
+    fn default() -> Self {
+        Attenuation::new(1.0, 0.0, 0.0)
+    }
+}
+
+/// Light source with no size, casting hard shadows.
+#[derive(Debug, Clone, Copy)]
+pub struct PointLight {
+    pub position: Point,
+    pub intensity: Color,
+    pub attenuation: Attenuation,
+}
+
+impl PointLight {
+    /// Return a new PointLight, as bright at any distance.
+    pub fn new(position: Point, intensity: Color) -> Self {
+        PointLight::with_attenuation(position, intensity, Attenuation::default())
+    }
+
+    /// Return a new PointLight fading with distance.
+    pub fn with_attenuation(position: Point, intensity: Color, attenuation: Attenuation) -> Self {
+        PointLight {
+            position,
+            intensity,
+            attenuation,
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn intensity(&self) -> Color {
+        self.intensity
+    }
+
+    fn samples(&self, point: Point, _rng: &mut dyn RandomSource) -> Vec<LightSample> {
+        let to_light = self.position - point;
+        let distance = to_light.magnitude();
+        vec![LightSample {
+            direction: to_light / distance,
+            distance,
+            intensity: self.intensity * self.attenuation.factor(distance),
+        }]
+    }
+}
+
+/// Infinitely distant light, like the sun, shining along direction everywhere.
+#[derive(Debug, Clone, Copy)]
+pub struct DirectionalLight {
+    pub direction: Vector,
+    pub intensity: Color,
+}
+
+impl DirectionalLight {
+    /// Return a new DirectionalLight shining along direction.
+    pub fn new(direction: Vector, intensity: Color) -> Self {
+        DirectionalLight {
+            direction: direction.normalize(),
+            intensity,
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn intensity(&self) -> Color {
+        self.intensity
+    }
+
+    /// The light is infinitely far away: shadow rays toward it have no length limit.
+    fn samples(&self, _point: Point, _rng: &mut dyn RandomSource) -> Vec<LightSample> {
+        vec![LightSample {
+            direction: -self.direction,
+            distance: f64::INFINITY,
+            intensity: self.intensity,
+        }]
+    }
+}
+
+/// Point light shining in a cone around direction. Points within inner_angle of the
+/// axis get the full intensity, which fades out smoothly to nothing at outer_angle.
+/// Both angles are measured from the axis, in radians.
+#[derive(Debug, Clone, Copy)]
+pub struct SpotLight {
+    pub position: Point,
+    pub direction: Vector,
+    pub inner_angle: f64,
+    pub outer_angle: f64,
+    pub intensity: Color,
+}
+
+impl SpotLight {
+    /// Return a new SpotLight. An inner cone wider than the outer one is narrowed to it,
+    /// which gives the light a hard edge.
+    pub fn new(
+        position: Point,
+        direction: Vector,
+        inner_angle: f64,
+        outer_angle: f64,
+        intensity: Color,
+    ) -> Self {
+        SpotLight {
+            position,
+            direction: direction.normalize(),
+            inner_angle: inner_angle.min(outer_angle),
+            outer_angle,
+            intensity,
+        }
+    }
+
+    /// Returns the fraction of the intensity reaching direction, a unit vector from the
+    /// light.
+    pub fn falloff(&self, direction: Vector) -> f64 {
+        let cos_theta = direction.dot(&self.direction);
+        let (cos_outer, cos_inner) = (self.outer_angle.cos(), self.inner_angle.cos());
+        if cos_theta >= cos_inner {
+            return 1.0;
+        }
+        if cos_theta <= cos_outer {
+            return 0.0;
+        }
+        let x = (cos_theta - cos_outer) / (cos_inner - cos_outer);
+        x * x * (3.0 - 2.0 * x)
+    }
+}
+
+impl Light for SpotLight {
+    fn intensity(&self) -> Color {
+        self.intensity
+    }
+
+    fn samples(&self, point: Point, _rng: &mut dyn RandomSource) -> Vec<LightSample> {
+        let to_light = self.position - point;
+        let distance = to_light.magnitude();
+        let direction = to_light / distance;
+        vec![LightSample {
+            direction,
+            distance,
+            intensity: self.intensity * self.falloff(-direction),
+        }]
+    }
+}
+
+/// Rectangular light spanned by two edges from a corner and split into usteps by
+/// vsteps cells. One sample is taken in each cell, at a random place within the cell
+/// when jitter is on and at its center otherwise, which softens the shadows.
+#[derive(Debug, Clone, Copy)]
+pub struct AreaLight {
+    pub corner: Point,
+    pub uvec: Vector,
+    pub usteps: usize,
+    pub vvec: Vector,
+    pub vsteps: usize,
+    pub intensity: Color,
+    pub jitter: bool,
+}
+
+impl AreaLight {
+    /// Return a new jittered AreaLight. full_uvec and full_vvec are the whole edges of
+    /// the light. Steps of 0 are taken as 1, a single cell along that edge.
+    pub fn new(
+        corner: Point,
+        full_uvec: Vector,
+        usteps: usize,
+        full_vvec: Vector,
+        vsteps: usize,
+        intensity: Color,
+    ) -> Self {
+        let (usteps, vsteps) = (usteps.max(1), vsteps.max(1));
+        AreaLight {
+            corner,
+            uvec: full_uvec / usteps as f64,
+            usteps,
+            vvec: full_vvec / vsteps as f64,
+            vsteps,
+            intensity,
+            jitter: true,
+        }
+    }
+
+    /// Returns the number of samples taken on the light.
+    pub fn sample_count(&self) -> usize {
+        self.usteps * self.vsteps
+    }
+
+    /// Returns the center of the light.
+    pub fn position(&self) -> Point {
+        self.corner
+            + self.uvec * (self.usteps as f64 / 2.0)
+            + self.vvec * (self.vsteps as f64 / 2.0)
+    }
+
+    /// Returns a point in cell u, v of the light.
+    pub fn point_on_light(&self, u: usize, v: usize, rng: &mut dyn RandomSource) -> Point {
+        let mut offset = || if self.jitter { rng.next_f64() } else { 0.5 };
+        let (du, dv) = (offset(), offset());
+        self.corner + self.uvec * (u as f64 + du) + self.vvec * (v as f64 + dv)
+    }
+}
+
+impl Light for AreaLight {
+    fn intensity(&self) -> Color {
+        self.intensity
+    }
+
+    fn samples(&self, point: Point, rng: &mut dyn RandomSource) -> Vec<LightSample> {
+        let mut samples = Vec::with_capacity(self.sample_count());
+        for v in 0..self.vsteps {
+            for u in 0..self.usteps {
+                let to_light = self.point_on_light(u, v, rng) - point;
+                let distance = to_light.magnitude();
+                samples.push(LightSample {
+                    direction: to_light / distance,
+                    distance,
+                    intensity: self.intensity,
+                });
+            }
+        }
+        samples
+    }
+}
+
+/// Geometry of a point being shaded.
+#[derive(Debug, Clone, Copy)]
+pub struct ShadingPoint {
+    pub point: Point,
+    /// Unit vector from the point toward the eye.
+    pub eyev: Vector,
+    /// Unit surface normal, facing the eye.
+    pub normalv: Vector,
+}
+
+/// Phong shading of a point lit by light. is_shadowed tells whether a sample of the
+/// light is blocked; diffuse and specular light are averaged over the unblocked
+/// samples, which gives soft shadows for lights with several samples.
+///
+/// Materials with a physically based surface reflect π times their BSDF instead of
+/// the Phong diffuse and specular terms, so that a white Lambertian surface is as
+/// bright as a Phong surface with a diffuse of 1.
+pub fn lighting(
+    material: &Material,
+    light: &dyn Light,
+    shading: &ShadingPoint,
+    rng: &mut dyn RandomSource,
+    is_shadowed: &mut dyn FnMut(&LightSample) -> bool,
+) -> Color {
+    let ambient = material.color * light.intensity() * material.ambient;
+
+    let samples = light.samples(shading.point, rng);
+    let mut sum = Color::new(0, 0, 0);
+    for sample in &samples {
+        let light_dot_normal = sample.direction.dot(&shading.normalv);
+        // Light behind the surface does not reach it.
+        if light_dot_normal < 0.0 || is_shadowed(sample) {
+            continue;
+        }
+        if material.is_physically_based() {
+            let f = material.evaluate(shading.eyev, sample.direction, shading.normalv);
+            sum = sum + f * sample.intensity * PI;
+            continue;
+        }
+        sum = sum + material.color * sample.intensity * (material.diffuse * light_dot_normal);
+
+        let reflectv = (-sample.direction).reflect(&shading.normalv);
+        let reflect_dot_eye = reflectv.dot(&shading.eyev);
+        if reflect_dot_eye > 0.0 {
+            let factor = reflect_dot_eye.powf(material.shininess);
+            sum = sum + sample.intensity * (material.specular * factor);
+        }
+    }
+
+    ambient + sum * (1.0 / samples.len().max(1) as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::microfacet::PbrMaterial;
+    use crate::random::Sequence;
+
+    fn shade(light: &dyn Light, eyev: Vector, normalv: Vector, in_shadow: bool) -> Color {
+        let shading = ShadingPoint {
+            point: Point::new(0, 0, 0),
+            eyev,
+            normalv,
+        };
+        let mut rng = Sequence::new(vec![0.5]);
+        lighting(&Material::default(), light, &shading, &mut rng, &mut |_| {
+            in_shadow
+        })
+    }
+
+    /// Lighting with the eye between the light and the surface.
+    #[test]
+    fn lighting_eye_between() {
+        let light = PointLight::new(Point::new(0, 0, -10), Color::new(1, 1, 1));
+        let c = shade(&light, Vector::new(0, 0, -1), Vector::new(0, 0, -1), false);
+        assert_eq!(c, Color::new(1.9, 1.9, 1.9));
+    }
+
+    /// Lighting with the eye between light and surface, eye offset 45°.
+    #[test]
+    fn lighting_eye_offset() {
+        let k = 2f64.sqrt() / 2.0;
+        let light = PointLight::new(Point::new(0, 0, -10), Color::new(1, 1, 1));
+        let c = shade(&light, Vector::new(0, k, -k), Vector::new(0, 0, -1), false);
+        assert_eq!(c, Color::new(1, 1, 1));
+    }
+
+    /// Lighting with eye opposite surface, light offset 45°.
+    #[test]
+    fn lighting_light_offset() {
+        let light = PointLight::new(Point::new(0, 10, -10), Color::new(1, 1, 1));
+        let c = shade(&light, Vector::new(0, 0, -1), Vector::new(0, 0, -1), false);
+        assert_eq!(c, Color::new(0.7364, 0.7364, 0.7364));
+    }
+
+    /// Lighting with eye in the path of the reflection vector.
+    #[test]
+    fn lighting_eye_reflection() {
+        let k = 2f64.sqrt() / 2.0;
+        let light = PointLight::new(Point::new(0, 10, -10), Color::new(1, 1, 1));
+        let c = shade(&light, Vector::new(0, -k, -k), Vector::new(0, 0, -1), false);
+        assert_eq!(c, Color::new(1.63639, 1.63639, 1.63639));
+    }
+
+    /// Lighting with the light behind the surface.
+    #[test]
+    fn lighting_light_behind() {
+        let light = PointLight::new(Point::new(0, 0, 10), Color::new(1, 1, 1));
+        let c = shade(&light, Vector::new(0, 0, -1), Vector::new(0, 0, -1), false);
+        assert_eq!(c, Color::new(0.1, 0.1, 0.1));
+    }
+
+    /// Lighting with the surface in shadow.
+    #[test]
+    fn lighting_in_shadow() {
+        let light = PointLight::new(Point::new(0, 0, -10), Color::new(1, 1, 1));
+        let c = shade(&light, Vector::new(0, 0, -1), Vector::new(0, 0, -1), true);
+        assert_eq!(c, Color::new(0.1, 0.1, 0.1));
+    }
+
+    /// The intensity of a point light with attenuation decreases with distance.
+    #[test]
+    fn point_light_attenuation() {
+        let light = PointLight::with_attenuation(
+            Point::new(0, 0, -10),
+            Color::new(1, 1, 1),
+            Attenuation::new(1.0, 0.5, 0.25),
+        );
+        let mut rng = Sequence::new(vec![0.5]);
+        let samples = light.samples(Point::new(0, 0, -8), &mut rng);
+        assert_eq!(samples[0].distance, 2.0);
+        assert_eq!(
+            samples[0].intensity,
+            Color::new(1.0 / 3.0, 1.0 / 3.0, 1.0 / 3.0)
+        );
+
+        let plain = PointLight::new(Point::new(0, 0, -10), Color::new(1, 1, 1));
+        let samples = plain.samples(Point::new(0, 0, 100), &mut rng);
+        assert_eq!(samples[0].intensity, Color::new(1, 1, 1));
+    }
+
+    /// A directional light shines from the same direction everywhere, from infinitely far.
+    #[test]
+    fn directional_light() {
+        let light = DirectionalLight::new(Vector::new(0, -2, 0), Color::new(1, 1, 1));
+        let mut rng = Sequence::new(vec![0.5]);
+        for point in [Point::new(0, 0, 0), Point::new(100, -50, 3)] {
+            let samples = light.samples(point, &mut rng);
+            assert_eq!(samples.len(), 1);
+            assert_eq!(samples[0].direction, Vector::new(0, 1, 0));
+            assert!(samples[0].distance.is_infinite());
+        }
+
+        let c = shade(&light, Vector::new(0, 1, 0), Vector::new(0, 1, 0), false);
+        assert_eq!(c, Color::new(1.9, 1.9, 1.9));
+    }
+
+    /// The falloff of a spotlight is smooth between its inner and outer cones.
+    #[test]
+    fn spot_light_falloff() {
+        let light = SpotLight::new(
+            Point::new(0, 10, 0),
+            Vector::new(0, -2, 0),
+            PI / 6.0,
+            PI / 3.0,
+            Color::new(1, 1, 1),
+        );
+        let towards = |angle: f64| Vector::new(angle.sin(), -angle.cos(), 0);
+        assert_eq!(light.falloff(towards(0.0)), 1.0);
+        assert_eq!(light.falloff(towards(PI / 6.0 - 1e-9)), 1.0);
+        assert!(light.falloff(towards(PI / 4.0)) > 0.0);
+        assert!(light.falloff(towards(PI / 4.0)) < 1.0);
+        assert!(light.falloff(towards(0.3)) > light.falloff(towards(0.9)));
+        assert_eq!(light.falloff(towards(PI / 3.0 + 1e-9)), 0.0);
+        assert_eq!(light.falloff(Vector::new(0, 1, 0)), 0.0);
+
+        let hard = SpotLight::new(
+            Point::new(0, 10, 0),
+            Vector::new(0, -1, 0),
+            PI / 3.0,
+            PI / 6.0,
+            Color::new(1, 1, 1),
+        );
+        assert_eq!(hard.inner_angle, PI / 6.0);
+        assert_eq!(hard.falloff(towards(PI / 6.0 - 1e-9)), 1.0);
+        assert_eq!(hard.falloff(towards(PI / 6.0 + 1e-9)), 0.0);
+    }
+
+    /// Lighting with a spotlight only lights points within its cone.
+    #[test]
+    fn lighting_spot_light() {
+        let light = SpotLight::new(
+            Point::new(0, 0, -10),
+            Vector::new(0, 0, 1),
+            PI / 8.0,
+            PI / 4.0,
+            Color::new(1, 1, 1),
+        );
+        let c = shade(&light, Vector::new(0, 0, -1), Vector::new(0, 0, -1), false);
+        assert_eq!(c, Color::new(1.9, 1.9, 1.9));
+        let c = shade(&light, Vector::new(0, 0, -1), Vector::new(0, 0, -1), true);
+        assert_eq!(c, Color::new(0.1, 0.1, 0.1));
+
+        let shading = ShadingPoint {
+            point: Point::new(20, 0, 0),
+            eyev: Vector::new(0, 0, -1),
+            normalv: Vector::new(0, 0, -1),
+        };
+        let mut rng = Sequence::new(vec![0.5]);
+        let material = Material::default();
+        let c = lighting(&material, &light, &shading, &mut rng, &mut |_| false);
+        assert_eq!(c, Color::new(0.1, 0.1, 0.1));
+    }
+
+    /// A physically based surface is lit through its BSDF.
+    #[test]
+    fn lighting_pbr_material() {
+        let light = PointLight::new(Point::new(0, 0, -10), Color::new(1, 1, 1));
+        let material = Material {
+            ambient: 0.0,
+            pbr: Some(PbrMaterial::new(Color::new(1, 1, 1), 0.0, 1.0, 1.0)),
+            ..Material::default()
+        };
+        let shading = ShadingPoint {
+            point: Point::new(0, 0, 0),
+            eyev: Vector::new(0, 0, -1),
+            normalv: Vector::new(0, 0, -1),
+        };
+        let mut rng = Sequence::new(vec![0.5]);
+        // An ior of 1 reflects nothing at the surface, leaving a Lambertian surface.
+        let c = lighting(&material, &light, &shading, &mut rng, &mut |_| false);
+        assert_eq!(c, Color::new(1, 1, 1));
+    }
+
+    /// Creating an area light.
+    #[test]
+    fn create_area_light() {
+        let light = AreaLight::new(
+            Point::new(0, 0, 0),
+            Vector::new(2, 0, 0),
+            4,
+            Vector::new(0, 0, 1),
+            2,
+            Color::new(1, 1, 1),
+        );
+        assert_eq!(light.uvec, Vector::new(0.5, 0, 0));
+        assert_eq!(light.vvec, Vector::new(0, 0, 0.5));
+        assert_eq!(light.sample_count(), 8);
+        assert_eq!(light.position(), Point::new(1, 0, 0.5));
+
+        let single = AreaLight::new(
+            Point::new(0, 0, 0),
+            Vector::new(2, 0, 0),
+            0,
+            Vector::new(0, 0, 1),
+            0,
+            Color::new(1, 1, 1),
+        );
+        assert_eq!(single.uvec, Vector::new(2, 0, 0));
+        assert_eq!(single.sample_count(), 1);
+    }
+
+    /// Finding a single point on an area light, without and with jitter.
+    #[test]
+    fn point_on_area_light() {
+        let mut light = AreaLight::new(
+            Point::new(0, 0, 0),
+            Vector::new(2, 0, 0),
+            4,
+            Vector::new(0, 0, 1),
+            2,
+            Color::new(1, 1, 1),
+        );
+        light.jitter = false;
+        let mut rng = Sequence::new(vec![0.5]);
+        let cases = [
+            (0, 0, Point::new(0.25, 0, 0.25), Point::new(0.15, 0, 0.35)),
+            (1, 0, Point::new(0.75, 0, 0.25), Point::new(0.65, 0, 0.35)),
+            (0, 1, Point::new(0.25, 0, 0.75), Point::new(0.15, 0, 0.85)),
+            (2, 0, Point::new(1.25, 0, 0.25), Point::new(1.15, 0, 0.35)),
+            (3, 1, Point::new(1.75, 0, 0.75), Point::new(1.65, 0, 0.85)),
+        ];
+        for (u, v, center, _) in cases {
+            assert_eq!(light.point_on_light(u, v, &mut rng), center);
+        }
+
+        light.jitter = true;
+        let mut rng = Sequence::new(vec![0.3, 0.7]);
+        for (u, v, _, jittered) in cases {
+            assert_eq!(light.point_on_light(u, v, &mut rng), jittered);
+        }
+    }
+
+    /// The lighting function samples the area light.
+    #[test]
+    fn lighting_samples_area_light() {
+        let mut light = AreaLight::new(
+            Point::new(-0.5, -0.5, -5),
+            Vector::new(1, 0, 0),
+            2,
+            Vector::new(0, 1, 0),
+            2,
+            Color::new(1, 1, 1),
+        );
+        light.jitter = false;
+        let material = Material {
+            ambient: 0.1,
+            diffuse: 0.9,
+            specular: 0.0,
+            ..Material::default()
+        };
+        let eye = Point::new(0, 0, -5);
+        let k = 2f64.sqrt() / 2.0;
+        for (point, expected) in [
+            (Point::new(0, 0, -1), Color::new(0.9965, 0.9965, 0.9965)),
+            (Point::new(0, k, -k), Color::new(0.62318, 0.62318, 0.62318)),
+        ] {
+            let shading = ShadingPoint {
+                point,
+                eyev: (eye - point).normalize(),
+                normalv: point - Point::new(0, 0, 0),
+            };
+            let mut rng = Sequence::new(vec![0.5]);
+            let c = lighting(&material, &light, &shading, &mut rng, &mut |_| false);
+            assert_eq!(c, expected);
+        }
+    }
+
+    /// Shadows of an area light are averaged over its samples.
+    #[test]
+    fn area_light_soft_shadow() {
+        let light = AreaLight::new(
+            Point::new(-1, 10, -1),
+            Vector::new(2, 0, 0),
+            2,
+            Vector::new(0, 0, 2),
+            2,
+            Color::new(1, 1, 1),
+        );
+        let material = Material {
+            ambient: 0.0,
+            diffuse: 1.0,
+            specular: 0.0,
+            ..Material::default()
+        };
+        let shading = ShadingPoint {
+            point: Point::new(0, 0, 0),
+            eyev: Vector::new(0, 1, 0),
+            normalv: Vector::new(0, 1, 0),
+        };
+        let mut rng = Sequence::new(vec![0.5]);
+        let lit = lighting(&material, &light, &shading, &mut rng, &mut |_| false);
+        // Block the samples on the negative x half of the light.
+        let half = lighting(&material, &light, &shading, &mut rng, &mut |s| {
+            s.direction.x() < 0.0
+        });
+        assert_eq!(half, lit * 0.5);
+    }
+}
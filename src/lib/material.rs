@@ -0,0 +1,251 @@
+use crate::bsdf::{Bsdf, BsdfSample};
+use crate::bump::Bump;
+use crate::color::Color;
+use crate::layered::LayeredMaterial;
+use crate::microfacet::PbrMaterial;
+use crate::pattern::Pattern;
+use crate::random::{cosine_hemisphere, RandomSource};
+use crate::subsurface::Subsurface;
+use crate::tuple::{Point, Vector};
+use std::f64::consts::PI;
+use std::rc::Rc;
+
+/// Surface attributes of the Phong reflection model, plus the light given off by the
+/// surface, used by the path tracer.
+///
+/// When pbr or layered is set, the surface reflects light with that physically based
+/// material instead of the Phong diffuse, specular and reflective attributes, layered
+/// taking precedence. For the path tracer, a Phong surface reflects like a mirror with
+/// probability reflective, and is Lambertian with albedo color * diffuse otherwise.
+#[derive(Debug, Clone)]
+pub struct Material {
+    pub color: Color,
+    pub ambient: f64,
+    pub diffuse: f64,
+    pub specular: f64,
+    pub shininess: f64,
+    pub reflective: f64,
+    /// Color of the light given off by the surface, scaled by emission_strength.
+    pub emission: Color,
+    pub emission_strength: f64,
+    pub pbr: Option<PbrMaterial>,
+    pub layered: Option<LayeredMaterial>,
+    /// Colors the surface in place of color, when set. It replaces the base color of
+    /// physically based materials too.
+    pub pattern: Option<Rc<dyn Pattern>>,
+    /// Makes the path tracer follow light beneath the surface, when set. The light
+    /// leaves the surface diffusely wherever it comes back out. Whitted shading
+    /// ignores it.
+    pub subsurface: Option<Subsurface>,
+    /// Tilts the normal the surface is shaded with, when set, for detail too fine to
+    /// model.
+    pub bump: Option<Rc<dyn Bump>>,
+}
+
+/// White, matte with a bright highlight, opaque, not reflective and not emissive.
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            color: Color::new(1, 1, 1),
+            ambient: 0.1,
+            diffuse: 0.9,
+            specular: 0.9,
+            shininess: 200.0,
+            reflective: 0.0,
+            emission: Color::new(0, 0, 0),
+            emission_strength: 1.0,
+            pbr: None,
+            layered: None,
+            pattern: None,
+            subsurface: None,
+            bump: None,
+        }
+    }
+}
+
+impl Material {
+    /// Returns true when the surface reflects light with a physically based material
+    /// rather than the Phong model.
+    pub fn is_physically_based(&self) -> bool {
+        self.pbr.is_some() || self.layered.is_some()
+    }
+
+    /// Returns the material with the color of its pattern at an object space point, or
+    /// the material itself without a pattern.
+    pub fn at(&self, point: Point) -> Material {
+        match &self.pattern {
+            Some(pattern) => self.with_color(pattern.color_at(point)),
+            None => self.clone(),
+        }
+    }
+
+    /// Returns the material with the color of its pattern blurred over a footprint width
+    /// object space units wide around a point, or the material itself without a pattern.
+    pub fn over(&self, point: Point, width: f64) -> Material {
+        match &self.pattern {
+            Some(pattern) => self.with_color(pattern.color_over(point, width)),
+            None => self.clone(),
+        }
+    }
+
+    /// Returns the material with color in place of its color, and of the base color of
+    /// its physically based material.
+    pub fn with_color(&self, color: Color) -> Material {
+        let mut material = self.clone();
+        material.color = color;
+        if let Some(pbr) = &mut material.pbr {
+            pbr.base_color = color;
+        }
+        if let Some(layered) = &mut material.layered {
+            layered.base.base_color = color;
+        }
+        material
+    }
+
+    /// Returns the light given off by the surface.
+    pub fn emitted(&self) -> Color {
+        self.emission * self.emission_strength
+    }
+}
+
+impl Bsdf for Material {
+    fn evaluate(&self, wo: Vector, wi: Vector, normal: Vector) -> Color {
+        if let Some(layered) = &self.layered {
+            return layered.evaluate(wo, wi, normal);
+        }
+        if let Some(pbr) = &self.pbr {
+            return pbr.evaluate(wo, wi, normal);
+        }
+        let cos_i = wi.dot(&normal);
+        if cos_i <= 0.0 {
+            return Color::new(0, 0, 0);
+        }
+        self.color * (self.diffuse * (1.0 - self.reflective) * cos_i / PI)
+    }
+
+    fn pdf(&self, wo: Vector, wi: Vector, normal: Vector) -> f64 {
+        if let Some(layered) = &self.layered {
+            return layered.pdf(wo, wi, normal);
+        }
+        if let Some(pbr) = &self.pbr {
+            return pbr.pdf(wo, wi, normal);
+        }
+        (1.0 - self.reflective) * wi.dot(&normal).max(0.0) / PI
+    }
+
+    fn sample(&self, wo: Vector, normal: Vector, rng: &mut dyn RandomSource) -> Option<BsdfSample> {
+        if let Some(layered) = &self.layered {
+            return layered.sample(wo, normal, rng);
+        }
+        if let Some(pbr) = &self.pbr {
+            return pbr.sample(wo, normal, rng);
+        }
+        // Picking the mirror with probability reflective cancels its weight.
+        if rng.next_f64() < self.reflective {
+            return Some(BsdfSample {
+                direction: (-wo).reflect(&normal),
+                weight: Color::new(1, 1, 1),
+                pdf: 0.0,
+                specular: true,
+            });
+        }
+        let direction = cosine_hemisphere(normal, rng);
+        Some(BsdfSample {
+            direction,
+            weight: self.color * self.diffuse,
+            pdf: self.pdf(wo, direction, normal),
+            specular: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The default material.
+    #[test]
+    fn default_material() {
+        let m = Material::default();
+        assert_eq!(m.color, Color::new(1, 1, 1));
+        assert_eq!(m.ambient, 0.1);
+        assert_eq!(m.diffuse, 0.9);
+        assert_eq!(m.specular, 0.9);
+        assert_eq!(m.shininess, 200.0);
+        assert_eq!(m.reflective, 0.0);
+        assert_eq!(m.emission, Color::new(0, 0, 0));
+        assert!(m.pbr.is_none());
+    }
+
+    /// A pattern colors the material, physically based or not.
+    #[test]
+    fn material_with_pattern() {
+        #[derive(Debug)]
+        struct Stripes;
+
+        impl Pattern for Stripes {
+            fn color_at(&self, point: Point) -> Color {
+                if point.x().floor() as i64 % 2 == 0 {
+                    Color::new(1, 1, 1)
+                } else {
+                    Color::new(0, 0, 0)
+                }
+            }
+        }
+
+        let m = Material {
+            color: Color::new(1, 0, 0),
+            pbr: Some(PbrMaterial::new(Color::new(1, 0, 0), 0.0, 0.5, 1.5)),
+            pattern: Some(Rc::new(Stripes)),
+            ..Material::default()
+        };
+        let black = m.at(Point::new(1.5, 0, 0));
+        assert_eq!(black.color, Color::new(0, 0, 0));
+        assert_eq!(black.pbr.unwrap().base_color, Color::new(0, 0, 0));
+        assert_eq!(m.at(Point::new(0.5, 0, 0)).color, Color::new(1, 1, 1));
+        assert_eq!(
+            Material::default().at(Point::new(1.5, 0, 0)).color,
+            Color::new(1, 1, 1)
+        );
+    }
+
+    /// The light given off by a material is its emission color times its strength.
+    #[test]
+    fn emitted_light() {
+        let m = Material {
+            emission: Color::new(1, 0.5, 0.25),
+            emission_strength: 4.0,
+            ..Material::default()
+        };
+        assert_eq!(m.emitted(), Color::new(4, 2, 1));
+        assert_eq!(Material::default().emitted(), Color::new(0, 0, 0));
+    }
+
+    /// A Phong material scatters like a Lambertian surface or a mirror.
+    #[test]
+    fn phong_bsdf() {
+        use crate::random::Sequence;
+
+        let n = Vector::new(0, 1, 0);
+        let wo = Vector::new(1, 1, 0).normalize();
+        let m = Material {
+            color: Color::new(1, 0.5, 0),
+            diffuse: 0.8,
+            reflective: 0.25,
+            ..Material::default()
+        };
+        let f = m.evaluate(wo, n, n);
+        assert_eq!(f, Color::new(0.6, 0.3, 0) * (1.0 / PI));
+        assert_eq!(m.evaluate(wo, -n, n), Color::new(0, 0, 0));
+
+        let mirror = m.sample(wo, n, &mut Sequence::new(vec![0.1])).unwrap();
+        assert!(mirror.specular);
+        assert_eq!(mirror.direction, Vector::new(-1, 1, 0).normalize());
+
+        let diffuse = m.sample(wo, n, &mut Sequence::new(vec![0.5])).unwrap();
+        assert!(!diffuse.specular);
+        assert_eq!(diffuse.weight, Color::new(0.8, 0.4, 0));
+        let expected = m.evaluate(wo, diffuse.direction, n) * (1.0 / diffuse.pdf);
+        assert_eq!(diffuse.weight, expected);
+    }
+}
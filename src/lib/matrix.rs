@@ -0,0 +1,311 @@
+use crate::ray::Ray;
+use crate::tuple::{Point, Vector};
+use std::ops::Mul;
+use vecmath::{mat4_id, mat4_inv, mat4_transposed, row_mat4_mul, row_mat4_transform, Matrix4};
+
+/// 4x4 transformation matrix, stored row by row.
+#[derive(Debug, Clone, Copy)]
+pub struct Matrix(Matrix4<f64>);
+
+impl Matrix {
+    /// Return a new Matrix from its rows.
+    pub fn new(rows: [[f64; 4]; 4]) -> Self {
+        Matrix(rows)
+    }
+
+    /// The identity matrix.
+    pub fn identity() -> Self {
+        Matrix(mat4_id())
+    }
+
+    /// Moves points by x, y, z. Vectors are not affected.
+    pub fn translation(x: impl Into<f64>, y: impl Into<f64>, z: impl Into<f64>) -> Self {
+        let mut m = mat4_id();
+        m[0][3] = x.into();
+        m[1][3] = y.into();
+        m[2][3] = z.into();
+        Matrix(m)
+    }
+
+    /// Scales by x, y, z along each axis.
+    pub fn scaling(x: impl Into<f64>, y: impl Into<f64>, z: impl Into<f64>) -> Self {
+        let mut m = mat4_id();
+        m[0][0] = x.into();
+        m[1][1] = y.into();
+        m[2][2] = z.into();
+        Matrix(m)
+    }
+
+    /// Rotates by radians around the x axis.
+    pub fn rotation_x(radians: f64) -> Self {
+        let (sin, cos) = radians.sin_cos();
+        Matrix::new([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, cos, -sin, 0.0],
+            [0.0, sin, cos, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Rotates by radians around the y axis.
+    pub fn rotation_y(radians: f64) -> Self {
+        let (sin, cos) = radians.sin_cos();
+        Matrix::new([
+            [cos, 0.0, sin, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [-sin, 0.0, cos, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Rotates by radians around the z axis.
+    pub fn rotation_z(radians: f64) -> Self {
+        let (sin, cos) = radians.sin_cos();
+        Matrix::new([
+            [cos, -sin, 0.0, 0.0],
+            [sin, cos, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Moves each component in proportion to the two others: xy is how much x moves in
+    /// proportion to y, and so on.
+    pub fn shearing(xy: f64, xz: f64, yx: f64, yz: f64, zx: f64, zy: f64) -> Self {
+        Matrix::new([
+            [1.0, xy, xz, 0.0],
+            [yx, 1.0, yz, 0.0],
+            [zx, zy, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Returns the element at row, column.
+    pub fn at(&self, row: usize, column: usize) -> f64 {
+        self.0[row][column]
+    }
+
+    /// Returns the transposed matrix.
+    pub fn transpose(&self) -> Self {
+        Matrix(mat4_transposed(self.0))
+    }
+
+    /// Returns the inverse matrix. Transformations are expected to be invertible.
+    pub fn inverse(&self) -> Self {
+        Matrix(mat4_inv(self.0))
+    }
+
+    /// Returns the average factor by which the transformation scales lengths along the
+    /// axes.
+    pub fn stretch(&self) -> f64 {
+        let axes = [
+            Vector::new(1, 0, 0),
+            Vector::new(0, 1, 0),
+            Vector::new(0, 0, 1),
+        ];
+        axes.iter()
+            .map(|&axis| (*self * axis).magnitude())
+            .sum::<f64>()
+            / 3.0
+    }
+
+    /// Returns this transformation followed by other, i.e. other * self.
+    pub fn then(self, other: Matrix) -> Self {
+        other * self
+    }
+}
+
+impl Default for Matrix {
+    fn default() -> Self {
+        Matrix::identity()
+    }
+}
+
+impl Mul<Matrix> for Matrix {
+    type Output = Matrix;
+
+    fn mul(self, rhs: Matrix) -> Self::Output {
+        Matrix(row_mat4_mul(self.0, rhs.0))
+    }
+}
+
+impl Mul<Point> for Matrix {
+    type Output = Point;
+
+    fn mul(self, rhs: Point) -> Self::Output {
+        let p = row_mat4_transform(self.0, [rhs.x(), rhs.y(), rhs.z(), 1.0]);
+        Point::new(p[0], p[1], p[2])
+    }
+}
+
+impl Mul<Vector> for Matrix {
+    type Output = Vector;
+
+    fn mul(self, rhs: Vector) -> Self::Output {
+        let v = row_mat4_transform(self.0, [rhs.x(), rhs.y(), rhs.z(), 0.0]);
+        Vector::new(v[0], v[1], v[2])
+    }
+}
+
+impl Mul<Ray> for Matrix {
+    type Output = Ray;
+
+    /// Transforms the origin and direction of the ray. The direction is not normalized,
+    /// so distances t along the transformed ray match the original ray.
+    fn mul(self, rhs: Ray) -> Self::Output {
+        Ray {
+            origin: self * rhs.origin,
+            direction: self * rhs.direction,
+            ..rhs
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approximate_equation::ApproximateEq;
+    use std::f64::consts::PI;
+
+    /// Required by assert_eq for comparing equality of Matrix and approximating using Epsilon.
+    impl PartialEq for Matrix {
+        fn eq(&self, other: &Self) -> bool {
+            (0..4).all(|r| (0..4).all(|c| self.at(r, c).approx_eq(&other.at(r, c))))
+        }
+    }
+
+    /// Multiplying two matrices.
+    #[test]
+    fn multiply_matrices() {
+        let a = Matrix::new([
+            [1.0, 2.0, 3.0, 4.0],
+            [5.0, 6.0, 7.0, 8.0],
+            [9.0, 8.0, 7.0, 6.0],
+            [5.0, 4.0, 3.0, 2.0],
+        ]);
+        let b = Matrix::new([
+            [-2.0, 1.0, 2.0, 3.0],
+            [3.0, 2.0, 1.0, -1.0],
+            [4.0, 3.0, 6.0, 5.0],
+            [1.0, 2.0, 7.0, 8.0],
+        ]);
+        let expected = Matrix::new([
+            [20.0, 22.0, 50.0, 48.0],
+            [44.0, 54.0, 114.0, 108.0],
+            [40.0, 58.0, 110.0, 102.0],
+            [16.0, 26.0, 46.0, 42.0],
+        ]);
+        assert_eq!(a * b, expected);
+        assert_eq!(a * Matrix::identity(), a);
+    }
+
+    /// Transposing a matrix.
+    #[test]
+    fn transpose_matrix() {
+        let a = Matrix::new([
+            [0.0, 9.0, 3.0, 0.0],
+            [9.0, 8.0, 0.0, 8.0],
+            [1.0, 8.0, 5.0, 3.0],
+            [0.0, 0.0, 5.0, 8.0],
+        ]);
+        assert_eq!(a.transpose().at(0, 1), 9.0);
+        assert_eq!(a.transpose().at(3, 2), 3.0);
+        assert_eq!(a.transpose().transpose(), a);
+    }
+
+    /// Multiplying a product by its inverse.
+    #[test]
+    fn multiply_product_by_inverse() {
+        let a = Matrix::new([
+            [3.0, -9.0, 7.0, 3.0],
+            [3.0, -8.0, 2.0, -9.0],
+            [-4.0, 4.0, 4.0, 1.0],
+            [-6.0, 5.0, -1.0, 1.0],
+        ]);
+        let b = Matrix::new([
+            [8.0, 2.0, 2.0, 2.0],
+            [3.0, -1.0, 7.0, 0.0],
+            [7.0, 0.0, 5.0, 4.0],
+            [6.0, -2.0, 0.0, 5.0],
+        ]);
+        assert_eq!(a * b * b.inverse(), a);
+    }
+
+    /// Multiplying by a translation matrix moves points but not vectors.
+    #[test]
+    fn translation() {
+        let transform = Matrix::translation(5, -3, 2);
+        assert_eq!(transform * Point::new(-3, 4, 5), Point::new(2, 1, 7));
+        assert_eq!(
+            transform.inverse() * Point::new(-3, 4, 5),
+            Point::new(-8, 7, 3)
+        );
+        assert_eq!(transform * Vector::new(-3, 4, 5), Vector::new(-3, 4, 5));
+    }
+
+    /// A scaling matrix applied to a point and a vector.
+    #[test]
+    fn scaling() {
+        let transform = Matrix::scaling(2, 3, 4);
+        assert_eq!(transform * Point::new(-4, 6, 8), Point::new(-8, 18, 32));
+        assert_eq!(transform * Vector::new(-4, 6, 8), Vector::new(-8, 18, 32));
+        assert_eq!(
+            transform.inverse() * Vector::new(-4, 6, 8),
+            Vector::new(-2, 2, 2)
+        );
+    }
+
+    /// Rotating a point around each axis.
+    #[test]
+    fn rotation() {
+        let k = 2f64.sqrt() / 2.0;
+        assert_eq!(
+            Matrix::rotation_x(PI / 4.0) * Point::new(0, 1, 0),
+            Point::new(0, k, k)
+        );
+        assert_eq!(
+            Matrix::rotation_y(PI / 4.0) * Point::new(0, 0, 1),
+            Point::new(k, 0, k)
+        );
+        assert_eq!(
+            Matrix::rotation_z(PI / 2.0) * Point::new(0, 1, 0),
+            Point::new(-1, 0, 0)
+        );
+    }
+
+    /// Stretch averages how much each axis is scaled, whichever way it is turned.
+    #[test]
+    fn stretch() {
+        let transform = Matrix::scaling(1, 2, 6).then(Matrix::rotation_y(1.0));
+        assert!((transform.stretch() - 3.0).abs() < 1e-9);
+        assert!((Matrix::translation(3, 4, 5).stretch() - 1.0).abs() < 1e-9);
+    }
+
+    /// A shearing transformation moves x in proportion to y.
+    #[test]
+    fn shearing() {
+        let transform = Matrix::shearing(1.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+        assert_eq!(transform * Point::new(2, 3, 4), Point::new(5, 3, 4));
+    }
+
+    /// Chained transformations must be applied in sequence.
+    #[test]
+    fn chained_transformations() {
+        let transform = Matrix::rotation_x(PI / 2.0)
+            .then(Matrix::scaling(5, 5, 5))
+            .then(Matrix::translation(10, 5, 7));
+        assert_eq!(transform * Point::new(1, 0, 1), Point::new(15, 0, 7));
+    }
+
+    /// Translating and scaling a ray.
+    #[test]
+    fn transform_ray() {
+        let r = Ray::new(Point::new(1, 2, 3), Vector::new(0, 1, 0));
+        let r2 = Matrix::translation(3, 4, 5) * r;
+        assert_eq!(r2.origin, Point::new(4, 6, 8));
+        assert_eq!(r2.direction, Vector::new(0, 1, 0));
+        let r3 = Matrix::scaling(2, 3, 4) * r;
+        assert_eq!(r3.origin, Point::new(2, 6, 12));
+        assert_eq!(r3.direction, Vector::new(0, 3, 0));
+    }
+}
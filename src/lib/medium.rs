@@ -0,0 +1,335 @@
+use crate::color::Color;
+use crate::matrix::Matrix;
+use crate::random::RandomSource;
+use crate::ray::Ray;
+use crate::shape::Shape;
+use crate::tuple::{Point, Vector};
+use std::f64::consts::PI;
+use std::rc::Rc;
+
+/// Density of a heterogeneous medium at a point, between 0 and 1.
+pub type Density = Rc<dyn Fn(Point) -> f64>;
+
+/// Henyey–Greenstein phase function: how much of the light travelling through a medium
+/// is scattered into each direction.
+///
+/// g is the average cosine of the scattering angle, from -1 (all light goes back) through
+/// 0 (the same in every direction) to 1 (all light keeps going).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HenyeyGreenstein {
+    pub g: f64,
+}
+
+impl HenyeyGreenstein {
+    /// Return a new HenyeyGreenstein phase function.
+    pub fn new(g: f64) -> Self {
+        HenyeyGreenstein { g }
+    }
+
+    /// Returns the density of light travelling along direction being scattered toward
+    /// scattered, per unit solid angle. Both vectors are normalized.
+    pub fn evaluate(&self, direction: Vector, scattered: Vector) -> f64 {
+        let g = self.g;
+        let denominator = 1.0 + g * g - 2.0 * g * direction.dot(&scattered);
+        (1.0 - g * g) / (4.0 * PI * denominator * denominator.max(1e-12).sqrt())
+    }
+
+    /// Returns a scattered direction for light travelling along direction, picked with
+    /// a density of evaluate(direction, scattered).
+    pub fn sample(&self, direction: Vector, rng: &mut dyn RandomSource) -> Vector {
+        let g = self.g;
+        let (xi1, xi2) = (rng.next_f64(), rng.next_f64());
+        let cos_theta = if g.abs() < 1e-3 {
+            1.0 - 2.0 * xi1
+        } else {
+            let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * xi1);
+            ((1.0 + g * g - s * s) / (2.0 * g)).clamp(-1.0, 1.0)
+        };
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * xi2;
+        let (tangent, bitangent) = direction.orthonormal_basis();
+        tangent * (sin_theta * phi.cos())
+            + bitangent * (sin_theta * phi.sin())
+            + direction * cos_theta
+    }
+}
+
+/// Fog, smoke or any other participating medium, which absorbs and scatters light
+/// travelling through it rather than only at surfaces.
+///
+/// The coefficients are per unit of distance in world space. Without a density the medium is
+/// homogeneous; otherwise they are scaled by the density at each point, found by delta
+/// tracking against the densest the medium can be.
+#[derive(Clone)]
+pub struct Medium {
+    /// Fraction of light absorbed per unit distance.
+    pub absorption: f64,
+    /// Fraction of light scattered per unit distance.
+    pub scattering: f64,
+    /// Tints the scattered light.
+    pub color: Color,
+    pub phase: HenyeyGreenstein,
+    /// Varies the medium over space, when set.
+    pub density: Option<Density>,
+}
+
+impl Medium {
+    /// Return a new homogeneous, white Medium scattering the same in every direction.
+    pub fn new(absorption: f64, scattering: f64) -> Self {
+        Medium {
+            absorption,
+            scattering,
+            color: Color::new(1, 1, 1),
+            phase: HenyeyGreenstein::new(0.0),
+            density: None,
+        }
+    }
+
+    /// Returns the fraction of light absorbed or scattered per unit distance where the
+    /// medium is densest.
+    pub fn extinction(&self) -> f64 {
+        self.absorption + self.scattering
+    }
+
+    /// Returns the share of light scattered rather than absorbed at each collision.
+    pub fn albedo(&self) -> Color {
+        let extinction = self.extinction();
+        if extinction <= 0.0 {
+            return Color::new(0, 0, 0);
+        }
+        self.color * (self.scattering / extinction)
+    }
+
+    fn density_at(&self, point: Point) -> f64 {
+        match &self.density {
+            Some(density) => density(point).clamp(0.0, 1.0),
+            None => 1.0,
+        }
+    }
+
+    /// Returns where light travelling along ray between t_min and t_max first collides
+    /// with the medium, if it does. Density is looked up at the points of ray moved by
+    /// to_medium into the space of the medium.
+    pub fn sample_collision(
+        &self,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+        to_medium: Matrix,
+        rng: &mut dyn RandomSource,
+    ) -> Option<f64> {
+        let extinction = self.extinction() * ray.direction.magnitude();
+        if extinction <= 0.0 {
+            return None;
+        }
+        let mut t = t_min;
+        loop {
+            t -= (1.0 - rng.next_f64()).ln() / extinction;
+            if t >= t_max {
+                return None;
+            }
+            if self.density.is_none()
+                || rng.next_f64() < self.density_at(to_medium * ray.position(t))
+            {
+                return Some(t);
+            }
+        }
+    }
+
+    /// Returns the fraction of light making it along ray from t_min to t_max. It is
+    /// exact for homogeneous media and an unbiased estimate, by ratio tracking, otherwise.
+    pub fn transmittance(
+        &self,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+        to_medium: Matrix,
+        rng: &mut dyn RandomSource,
+    ) -> f64 {
+        let extinction = self.extinction() * ray.direction.magnitude();
+        if extinction <= 0.0 || t_max <= t_min {
+            return 1.0;
+        }
+        if self.density.is_none() {
+            return (-extinction * (t_max - t_min)).exp();
+        }
+        let mut transmittance = 1.0;
+        let mut t = t_min;
+        loop {
+            t -= (1.0 - rng.next_f64()).ln() / extinction;
+            if t >= t_max {
+                return transmittance;
+            }
+            transmittance *= 1.0 - self.density_at(to_medium * ray.position(t));
+        }
+    }
+}
+
+/// A medium filling the inside of a closed shape, such as smoke in a box or the murky
+/// water of a fish tank. The shape itself is invisible, so pair it with a surface when
+/// the medium should look enclosed. Densities are looked up in object space.
+#[derive(Clone)]
+pub struct Volume {
+    pub shape: Rc<dyn Shape>,
+    pub medium: Medium,
+    transform: Matrix,
+    inverse: Matrix,
+}
+
+impl Volume {
+    /// Return a new Volume filling shape, moved into the scene by transform.
+    pub fn new(shape: Rc<dyn Shape>, transform: Matrix, medium: Medium) -> Self {
+        Volume {
+            shape,
+            medium,
+            transform,
+            inverse: transform.inverse(),
+        }
+    }
+
+    /// Returns the transformation from object space to world space.
+    pub fn transform(&self) -> Matrix {
+        self.transform
+    }
+
+    /// Returns the transformation from world space to object space.
+    pub fn inverse(&self) -> Matrix {
+        self.inverse
+    }
+
+    /// Returns the spans of t, from 0 on, where the world space ray is inside the shape.
+    pub fn segments(&self, ray: &Ray) -> Vec<(f64, f64)> {
+        let mut ts: Vec<f64> = self
+            .shape
+            .local_intersect(&(self.inverse * *ray))
+            .iter()
+            .map(|x| x.t)
+            .collect();
+        ts.sort_by(f64::total_cmp);
+        // Entries and exits alternate along the ray, including behind its origin.
+        ts.chunks_exact(2)
+            .filter(|span| span[1] > 0.0)
+            .map(|span| (span[0].max(0.0), span[1]))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approximate_equation::ApproximateEq;
+    use crate::random::XorShift;
+    use crate::sdf::{sphere, Sdf};
+    use crate::sphere::Sphere;
+
+    /// The Henyey–Greenstein phase function integrates to one over the sphere.
+    #[test]
+    fn phase_function_normalized() {
+        let direction = Vector::new(0, 0, 1);
+        for g in [-0.7, 0.0, 0.3, 0.9] {
+            let phase = HenyeyGreenstein::new(g);
+            let steps = 2000;
+            let total: f64 = (0..steps)
+                .map(|i| {
+                    let cos = -1.0 + 2.0 * (i as f64 + 0.5) / steps as f64;
+                    let sin = (1.0 - cos * cos).sqrt();
+                    phase.evaluate(direction, Vector::new(sin, 0, cos)) * 2.0 * PI * 2.0
+                        / steps as f64
+                })
+                .sum();
+            assert!((total - 1.0).abs() < 1e-2, "g = {g}: {total}");
+        }
+    }
+
+    /// Sampled directions have an average cosine of g.
+    #[test]
+    fn phase_function_sampling() {
+        let direction = Vector::new(1, 1, 0).normalize();
+        let mut rng = XorShift::new(5);
+        for g in [-0.5, 0.0, 0.8] {
+            let phase = HenyeyGreenstein::new(g);
+            let n = 20000;
+            let mean = (0..n)
+                .map(|_| phase.sample(direction, &mut rng).dot(&direction))
+                .sum::<f64>()
+                / n as f64;
+            assert!((mean - g).abs() < 0.02, "g = {g}: {mean}");
+        }
+    }
+
+    /// Light through a homogeneous medium falls off exponentially, and collisions happen
+    /// as often as light is lost.
+    #[test]
+    fn homogeneous_medium() {
+        let fog = Medium::new(0.2, 0.3);
+        let r = Ray::new(Point::new(0, 0, 0), Vector::new(0, 0, 2));
+        let mut rng = XorShift::new(1);
+        let expected = (-0.5f64 * 4.0).exp();
+        assert!(fog
+            .transmittance(&r, 0.0, 2.0, Matrix::identity(), &mut rng)
+            .approx_eq(&expected));
+        let n = 20000;
+        let passed = (0..n)
+            .filter(|_| {
+                fog.sample_collision(&r, 0.0, 2.0, Matrix::identity(), &mut rng)
+                    .is_none()
+            })
+            .count();
+        assert!((passed as f64 / n as f64 - expected).abs() < 0.01);
+        assert_eq!(fog.albedo(), Color::new(0.6, 0.6, 0.6));
+    }
+
+    /// Delta and ratio tracking agree with the analytic transmittance of a heterogeneous
+    /// medium.
+    #[test]
+    fn heterogeneous_medium() {
+        let smoke = Medium {
+            density: Some(Rc::new(|p: Point| p.x() / 4.0)),
+            ..Medium::new(0.0, 1.0)
+        };
+        let r = Ray::new(Point::new(0, 0, 0), Vector::new(1, 0, 0));
+        // The integral of x / 4 from 0 to 4 is 2.
+        let expected = (-2f64).exp();
+        let mut rng = XorShift::new(9);
+        let n = 20000;
+        let ratio = (0..n)
+            .map(|_| smoke.transmittance(&r, 0.0, 4.0, Matrix::identity(), &mut rng))
+            .sum::<f64>()
+            / n as f64;
+        assert!((ratio - expected).abs() < 0.01, "{ratio}");
+        let passed = (0..n)
+            .filter(|_| {
+                smoke
+                    .sample_collision(&r, 0.0, 4.0, Matrix::identity(), &mut rng)
+                    .is_none()
+            })
+            .count();
+        assert!((passed as f64 / n as f64 - expected).abs() < 0.01);
+    }
+
+    /// A volume covers the spans of a ray inside its shape.
+    #[test]
+    fn volume_segments() {
+        let volume = Volume::new(
+            Rc::new(Sphere::default()),
+            Matrix::scaling(2, 2, 2),
+            Medium::new(0.0, 1.0),
+        );
+        let outside = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        assert_eq!(volume.segments(&outside), vec![(3.0, 7.0)]);
+        let inside = Ray::new(Point::new(0, 0, 0), Vector::new(0, 0, 1));
+        assert_eq!(volume.segments(&inside), vec![(0.0, 2.0)]);
+        let behind = Ray::new(Point::new(0, 0, 5), Vector::new(0, 0, 1));
+        assert!(volume.segments(&behind).is_empty());
+
+        // Distance fields report where rays from inside entered them too.
+        let volume = Volume::new(
+            Rc::new(Sdf::new(sphere(2.0))),
+            Matrix::identity(),
+            Medium::new(0.0, 1.0),
+        );
+        let (start, end) = volume.segments(&inside)[0];
+        assert_eq!(start, 0.0);
+        assert!((end - 2.0).abs() < 1e-3);
+    }
+}
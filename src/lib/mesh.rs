@@ -0,0 +1,272 @@
+use crate::color::Color;
+use crate::group::{bounding_box, Group};
+use crate::shape::Shape;
+use crate::triangle::Triangle;
+use crate::tuple::{Point, Vector};
+
+/// Indexed triangle mesh, as produced by the STL and PLY readers.
+#[derive(Debug, Clone, Default)]
+pub struct TriangleMesh {
+    pub vertices: Vec<Point>,
+    /// Optional per-vertex colors, one for each entry of `vertices`.
+    pub colors: Option<Vec<Color>>,
+    /// Optional per-vertex normals, one for each entry of `vertices`.
+    pub normals: Option<Vec<Vector>>,
+    /// Optional per-vertex texture coordinates u, v, one for each entry of `vertices`.
+    pub uvs: Option<Vec<(f64, f64)>>,
+    /// Optional per-vertex unit tangents pointing toward increasing u, one for each
+    /// entry of `vertices`, as computed by `compute_tangents`. Normal maps need them.
+    /// Each comes with the handedness of the texture coordinates: 1 where v increases
+    /// along normal × tangent, and -1 where the texture is mirrored.
+    pub tangents: Option<Vec<(Vector, f64)>>,
+    /// Triangles as indices into `vertices`.
+    pub faces: Vec<[usize; 3]>,
+}
+
+impl TriangleMesh {
+    /// Return a new TriangleMesh without vertex colors, normals, texture coordinates or
+    /// tangents.
+    pub fn new(vertices: Vec<Point>, faces: Vec<[usize; 3]>) -> Self {
+        TriangleMesh {
+            vertices,
+            colors: None,
+            normals: None,
+            uvs: None,
+            tangents: None,
+            faces,
+        }
+    }
+
+    /// Returns the three corner points of a face.
+    pub fn triangle(&self, face: usize) -> (Point, Point, Point) {
+        let [a, b, c] = self.faces[face];
+        (self.vertices[a], self.vertices[b], self.vertices[c])
+    }
+
+    /// Returns the normal of a face, following the counter-clockwise winding order.
+    pub fn face_normal(&self, face: usize) -> Vector {
+        let (p1, p2, p3) = self.triangle(face);
+        (p2 - p1).cross(&(p3 - p1)).normalize()
+    }
+
+    /// Returns the vertex color interpolated at the barycentric coordinates u, v of a face.
+    /// u weights the second vertex and v the third one, the same way a smooth triangle
+    /// interpolates its normals.
+    pub fn color_at(&self, face: usize, u: f64, v: f64) -> Option<Color> {
+        let colors = self.colors.as_ref()?;
+        let [a, b, c] = self.faces[face];
+        Some(colors[b] * u + colors[c] * v + colors[a] * (1.0 - u - v))
+    }
+
+    /// Compute the tangents of the vertices from their texture coordinates: the
+    /// direction in which u increases over the faces around each vertex, made
+    /// perpendicular to its normal, and whether v increases along normal × tangent or
+    /// against it. Without texture coordinates the mesh is left without tangents.
+    pub fn compute_tangents(&mut self) {
+        let Some(uvs) = &self.uvs else {
+            self.tangents = None;
+            return;
+        };
+        let mut tangents = vec![Vector::new(0, 0, 0); self.vertices.len()];
+        let mut bitangents = vec![Vector::new(0, 0, 0); self.vertices.len()];
+        let mut face_normals = vec![Vector::new(0, 0, 0); self.vertices.len()];
+        for (face, &[a, b, c]) in self.faces.iter().enumerate() {
+            let (p1, p2, p3) = self.triangle(face);
+            let (e1, e2) = (p2 - p1, p3 - p1);
+            let (du1, dv1) = (uvs[b].0 - uvs[a].0, uvs[b].1 - uvs[a].1);
+            let (du2, dv2) = (uvs[c].0 - uvs[a].0, uvs[c].1 - uvs[a].1);
+            // Summing unnormalized tangents and normals weights faces by their area.
+            let normal = e1.cross(&e2);
+            for i in [a, b, c] {
+                face_normals[i] = face_normals[i] + normal;
+            }
+            let det = du1 * dv2 - du2 * dv1;
+            if det.abs() < 1e-12 {
+                continue;
+            }
+            let tangent = (e1 * dv2 - e2 * dv1) * (1.0 / det);
+            let bitangent = (e2 * du1 - e1 * du2) * (1.0 / det);
+            for i in [a, b, c] {
+                tangents[i] = tangents[i] + tangent;
+                bitangents[i] = bitangents[i] + bitangent;
+            }
+        }
+        let tangents = tangents
+            .iter()
+            .enumerate()
+            .map(|(i, &tangent)| {
+                let normal = match &self.normals {
+                    Some(normals) => normals[i],
+                    None => face_normals[i],
+                };
+                if normal.magnitude() < 1e-12 {
+                    // Vertices of no face, or of faces without area, have no surface to
+                    // follow.
+                    if tangent.magnitude() < 1e-12 {
+                        return (Vector::new(1, 0, 0), 1.0);
+                    }
+                    return (tangent.normalize(), 1.0);
+                }
+                let normal = normal.normalize();
+                let tangent = tangent - normal * tangent.dot(&normal);
+                if tangent.magnitude() < 1e-12 {
+                    return (normal.orthonormal_basis().0, 1.0);
+                }
+                let tangent = tangent.normalize();
+                let sign = if normal.cross(&tangent).dot(&bitangents[i]) < 0.0 {
+                    -1.0
+                } else {
+                    1.0
+                };
+                (tangent, sign)
+            })
+            .collect();
+        self.tangents = Some(tangents);
+    }
+
+    /// Build a bounded Group with one Triangle per face, smooth shaded when the mesh
+    /// has vertex normals.
+    pub fn to_group(&self) -> Group {
+        let children = self
+            .faces
+            .iter()
+            .map(|&[a, b, c]| {
+                let (p1, p2, p3) = (self.vertices[a], self.vertices[b], self.vertices[c]);
+                let mut triangle = match &self.normals {
+                    Some(n) => Triangle::smooth(p1, p2, p3, n[a], n[b], n[c]),
+                    None => Triangle::new(p1, p2, p3),
+                };
+                triangle.colors = self
+                    .colors
+                    .as_ref()
+                    .map(|colors| [colors[a], colors[b], colors[c]]);
+                triangle.uvs = self.uvs.as_ref().map(|uvs| [uvs[a], uvs[b], uvs[c]]);
+                triangle.tangents = self
+                    .tangents
+                    .as_ref()
+                    .map(|tangents| [tangents[a], tangents[b], tangents[c]]);
+                Box::new(triangle) as Box<dyn Shape>
+            })
+            .collect();
+
+        match bounding_box(self.vertices.iter().copied()) {
+            Some(bounds) => Group::with_bounds(children, bounds),
+            None => Group::new(children),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray::Ray;
+
+    fn single_triangle() -> TriangleMesh {
+        TriangleMesh::new(
+            vec![
+                Point::new(0, 1, 0),
+                Point::new(-1, 0, 0),
+                Point::new(1, 0, 0),
+            ],
+            vec![[0, 1, 2]],
+        )
+    }
+
+    /// The normal of a mesh face.
+    #[test]
+    fn face_normal() {
+        let mesh = single_triangle();
+        assert_eq!(mesh.face_normal(0), Vector::new(0, 0, 1));
+    }
+
+    /// A mesh without vertex colors has no color at its faces.
+    #[test]
+    fn color_at_without_colors() {
+        let mesh = single_triangle();
+        assert!(mesh.color_at(0, 0.3, 0.3).is_none());
+    }
+
+    /// Interpolating vertex colors with barycentric coordinates.
+    #[test]
+    fn color_at_interpolates() {
+        let mut mesh = single_triangle();
+        mesh.colors = Some(vec![
+            Color::new(1, 0, 0),
+            Color::new(0, 1, 0),
+            Color::new(0, 0, 1),
+        ]);
+        assert_eq!(mesh.color_at(0, 0.0, 0.0), Some(Color::new(1, 0, 0)));
+        assert_eq!(mesh.color_at(0, 1.0, 0.0), Some(Color::new(0, 1, 0)));
+        assert_eq!(
+            mesh.color_at(0, 0.25, 0.5),
+            Some(Color::new(0.25, 0.25, 0.5))
+        );
+    }
+
+    /// Tangents point toward increasing u, perpendicular to the normals.
+    #[test]
+    fn compute_tangents() {
+        let mut mesh = single_triangle();
+        mesh.compute_tangents();
+        assert!(mesh.tangents.is_none());
+
+        mesh.uvs = Some(vec![(0.5, 1.0), (0.0, 0.0), (1.0, 0.0)]);
+        mesh.compute_tangents();
+        let tangents = mesh.tangents.as_ref().unwrap();
+        assert!(tangents
+            .iter()
+            .all(|&(t, sign)| t == Vector::new(1, 0, 0) && sign == 1.0));
+        // Mirrored, u increases toward -x while v still increases along normal × +x.
+        mesh.uvs = Some(vec![(0.5, 1.0), (1.0, 0.0), (0.0, 0.0)]);
+        mesh.compute_tangents();
+        let tangents = mesh.tangents.as_ref().unwrap();
+        assert!(tangents
+            .iter()
+            .all(|&(t, sign)| t == Vector::new(-1, 0, 0) && sign == -1.0));
+
+        // u increasing downward, with normals tilted toward +y.
+        mesh.uvs = Some(vec![(0.0, 0.5), (1.0, 0.0), (1.0, 1.0)]);
+        let tilted = Vector::new(0, 1, 1).normalize();
+        mesh.normals = Some(vec![tilted; 3]);
+        mesh.compute_tangents();
+        let (tangent, sign) = mesh.tangents.as_ref().unwrap()[0];
+        assert_eq!(tangent, Vector::new(0, -1, 1).normalize());
+        assert!(tangent.dot(&tilted).abs() < 1e-9);
+        assert_eq!(sign, 1.0);
+
+        // Faces without an extent in u, v and vertices of no face still get unit tangents.
+        mesh.normals = None;
+        mesh.vertices.push(Point::new(0, 0, 5));
+        mesh.uvs = Some(vec![(0.5, 0.5); 4]);
+        mesh.compute_tangents();
+        let tangents = mesh.tangents.as_ref().unwrap();
+        assert!(tangents
+            .iter()
+            .all(|(t, _)| (t.magnitude() - 1.0).abs() < 1e-9));
+        assert!(tangents[0].0.dot(&Vector::new(0, 0, 1)).abs() < 1e-9);
+    }
+
+    /// Converting a mesh into a group of triangles.
+    #[test]
+    fn mesh_to_group() {
+        let mut mesh = single_triangle();
+        mesh.normals = Some(vec![
+            Vector::new(0, 1, 0),
+            Vector::new(-1, 0, 0),
+            Vector::new(1, 0, 0),
+        ]);
+        let g = mesh.to_group();
+        assert_eq!(g.children.len(), 1);
+        let bounds = g.bounds.unwrap();
+        assert_eq!(bounds.min, Point::new(-1, 0, 0));
+        assert_eq!(bounds.max, Point::new(1, 1, 0));
+
+        let r = Ray::new(Point::new(-0.2, 0.3, -2), Vector::new(0, 0, 1));
+        let xs = g.local_intersect(&r);
+        assert_eq!(xs.len(), 1);
+        assert_eq!(
+            xs[0].normal_at(r.position(xs[0].t)),
+            Vector::new(-0.5547, 0.83205, 0)
+        );
+    }
+}
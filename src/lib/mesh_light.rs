@@ -0,0 +1,172 @@
+use crate::color::Color;
+use crate::light::{Light, LightSample};
+use crate::matrix::Matrix;
+use crate::mesh::TriangleMesh;
+use crate::random::RandomSource;
+use crate::tuple::{Point, Vector};
+use std::f64::consts::PI;
+
+/// Emissive triangle mesh sampled as a light, so that direct light from modelled
+/// light panels and signs is found without waiting for paths to hit them.
+///
+/// Points are picked uniformly over the surface of the mesh. Both sides of each
+/// triangle emit, like emissive surfaces seen by the path tracer.
+#[derive(Debug, Clone)]
+pub struct MeshLight {
+    /// World space corners of each triangle.
+    triangles: Vec<[Point; 3]>,
+    /// Cumulative areas of the triangles.
+    cumulative_area: Vec<f64>,
+    /// Light given off by every point of the surface.
+    pub radiance: Color,
+    /// Number of points sampled each time the light reaches a point.
+    pub samples: usize,
+}
+
+impl MeshLight {
+    /// Return a new MeshLight for mesh placed in the scene by transform.
+    pub fn new(mesh: &TriangleMesh, transform: Matrix, radiance: Color, samples: usize) -> Self {
+        let triangles: Vec<[Point; 3]> = (0..mesh.faces.len())
+            .map(|face| {
+                let (p1, p2, p3) = mesh.triangle(face);
+                [transform * p1, transform * p2, transform * p3]
+            })
+            .collect();
+        let mut total = 0.0;
+        let cumulative_area = triangles
+            .iter()
+            .map(|&[p1, p2, p3]| {
+                total += (p2 - p1).cross(&(p3 - p1)).magnitude() / 2.0;
+                total
+            })
+            .collect();
+        MeshLight {
+            triangles,
+            cumulative_area,
+            radiance,
+            samples,
+        }
+    }
+
+    /// Returns the total area of the mesh.
+    pub fn area(&self) -> f64 {
+        self.cumulative_area.last().copied().unwrap_or(0.0)
+    }
+
+    /// Returns a point picked uniformly over the surface of the mesh and the normal there.
+    pub fn sample_point(&self, rng: &mut dyn RandomSource) -> (Point, Vector) {
+        let target = rng.next_f64() * self.area();
+        let index = self
+            .cumulative_area
+            .partition_point(|&a| a <= target)
+            .min(self.triangles.len() - 1);
+        let [p1, p2, p3] = self.triangles[index];
+
+        let s = rng.next_f64().sqrt();
+        let t = rng.next_f64();
+        let point = p1 + (p2 - p1) * (s * (1.0 - t)) + (p3 - p1) * (s * t);
+        (point, (p2 - p1).cross(&(p3 - p1)).normalize())
+    }
+}
+
+impl Light for MeshLight {
+    /// The mesh only lights what it can see, so it adds no ambient light.
+    fn intensity(&self) -> Color {
+        Color::new(0, 0, 0)
+    }
+
+    /// Each sample carries radiance * cos θ * area / (π d²), θ being the angle at the
+    /// light: the light of the whole mesh, seen from point, as if it all came from the
+    /// sampled spot.
+    fn samples(&self, point: Point, rng: &mut dyn RandomSource) -> Vec<LightSample> {
+        if self.triangles.is_empty() || self.area() <= 0.0 {
+            return vec![];
+        }
+        (0..self.samples)
+            .map(|_| {
+                let (on_light, normal) = self.sample_point(rng);
+                let to_light = on_light - point;
+                let distance = to_light.magnitude();
+                let direction = to_light / distance;
+                let cos_light = direction.dot(&normal).abs();
+                LightSample {
+                    direction,
+                    // Stop shadow rays just short of the light, which would block itself.
+                    distance: distance * (1.0 - 1e-6) - 1e-4,
+                    intensity: self.radiance
+                        * (cos_light * self.area() / (PI * distance * distance)),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::light::{lighting, ShadingPoint};
+    use crate::material::Material;
+    use crate::random::XorShift;
+
+    /// Square from -size/2 to size/2 in x and z, facing down.
+    fn square(size: f64) -> TriangleMesh {
+        let h = size / 2.0;
+        TriangleMesh::new(
+            vec![
+                Point::new(-h, 0, -h),
+                Point::new(h, 0, -h),
+                Point::new(h, 0, h),
+                Point::new(-h, 0, h),
+            ],
+            vec![[0, 1, 2], [0, 2, 3]],
+        )
+    }
+
+    /// Points sampled on a mesh light lie on its transformed triangles.
+    #[test]
+    fn sample_mesh_light() {
+        let light = MeshLight::new(
+            &square(2.0),
+            Matrix::translation(0, 5, 0),
+            Color::new(1, 1, 1),
+            4,
+        );
+        assert_eq!(light.area(), 4.0);
+        let mut rng = XorShift::new(4);
+        for _ in 0..100 {
+            let (p, n) = light.sample_point(&mut rng);
+            assert!((p.y() - 5.0).abs() < 1e-9);
+            assert!(p.x().abs() <= 1.0 && p.z().abs() <= 1.0);
+            assert_eq!(n.y().abs(), 1.0);
+        }
+        let samples = light.samples(Point::new(0, 0, 0), &mut rng);
+        assert_eq!(samples.len(), 4);
+        assert!(samples.iter().all(|s| s.distance < 5.0 * 1.5));
+    }
+
+    /// A small far away mesh light lights like a point light.
+    #[test]
+    fn small_mesh_light() {
+        // Seen from 10 units away, 0.01 square units emitting 1000 π radiance.
+        let light = MeshLight::new(
+            &square(0.1),
+            Matrix::translation(0, 10, 0),
+            Color::new(1, 1, 1) * (1000.0 * PI),
+            4,
+        );
+        let material = Material {
+            ambient: 0.0,
+            diffuse: 1.0,
+            specular: 0.0,
+            ..Material::default()
+        };
+        let shading = ShadingPoint {
+            point: Point::new(0, 0, 0),
+            eyev: Vector::new(0, 1, 0),
+            normalv: Vector::new(0, 1, 0),
+        };
+        let mut rng = XorShift::new(8);
+        let c = lighting(&material, &light, &shading, &mut rng, &mut |_| false);
+        assert!((c.red() - 0.1).abs() < 1e-3, "{c:?}");
+    }
+}
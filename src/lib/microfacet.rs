@@ -0,0 +1,256 @@
+use crate::bsdf::{Bsdf, BsdfSample};
+use crate::color::Color;
+use crate::random::{cosine_hemisphere, RandomSource};
+use crate::tuple::Vector;
+use std::f64::consts::PI;
+
+/// Smallest GGX alpha, below which the distribution is too sharp to evaluate reliably.
+pub(crate) const MIN_ALPHA: f64 = 1e-3;
+
+/// Physically based material of the metallic/roughness workflow.
+///
+/// Specular reflection follows the GGX microfacet distribution with separable Smith
+/// shadowing and Schlick's Fresnel approximation. Metals tint their reflection with
+/// base_color; dielectrics reflect (ior - 1)² / (ior + 1)² at normal incidence and
+/// scatter base_color diffusely underneath.
+#[derive(Debug, Clone, Copy)]
+pub struct PbrMaterial {
+    pub base_color: Color,
+    pub metallic: f64,
+    /// Perceptual roughness, from 0 (polished) to 1 (matte). GGX alpha is its square.
+    pub roughness: f64,
+    pub ior: f64,
+}
+
+impl PbrMaterial {
+    /// Return a new PbrMaterial.
+    pub fn new(base_color: Color, metallic: f64, roughness: f64, ior: f64) -> Self {
+        PbrMaterial {
+            base_color,
+            metallic,
+            roughness,
+            ior,
+        }
+    }
+
+    fn alpha(&self) -> f64 {
+        (self.roughness * self.roughness).max(MIN_ALPHA)
+    }
+
+    /// Reflectance at normal incidence.
+    pub fn f0(&self) -> Color {
+        let r = ((self.ior - 1.0) / (self.ior + 1.0)).powi(2);
+        Color::new(r, r, r) * (1.0 - self.metallic) + self.base_color * self.metallic
+    }
+
+    /// Probability of sampling the specular lobe rather than the diffuse one.
+    fn specular_probability(&self, cos_o: f64) -> f64 {
+        let specular = fresnel_schlick(self.f0(), cos_o).luminance();
+        let diffuse = self.base_color.luminance() * (1.0 - self.metallic);
+        if specular + diffuse <= 0.0 {
+            return 1.0;
+        }
+        (specular / (specular + diffuse)).clamp(0.1, 1.0)
+    }
+}
+
+/// Fresnel reflectance for the cosine of the angle of incidence (Schlick).
+pub fn fresnel_schlick(f0: Color, cos_theta: f64) -> Color {
+    let k = (1.0 - cos_theta.clamp(0.0, 1.0)).powi(5);
+    f0 + (Color::new(1, 1, 1) - f0) * k
+}
+
+/// GGX (Trowbridge-Reitz) distribution of microfacet normals with cosine cos_h to the
+/// surface normal.
+pub fn ggx_distribution(cos_h: f64, alpha: f64) -> f64 {
+    if cos_h <= 0.0 {
+        return 0.0;
+    }
+    let a2 = alpha * alpha;
+    let d = cos_h * cos_h * (a2 - 1.0) + 1.0;
+    a2 / (PI * d * d)
+}
+
+/// Smith masking of the GGX distribution for a direction with cosine cos to the normal.
+pub fn smith_g1(cos: f64, alpha: f64) -> f64 {
+    if cos <= 0.0 {
+        return 0.0;
+    }
+    let a2 = alpha * alpha;
+    2.0 * cos / (cos + (a2 + (1.0 - a2) * cos * cos).sqrt())
+}
+
+impl Bsdf for PbrMaterial {
+    fn evaluate(&self, wo: Vector, wi: Vector, normal: Vector) -> Color {
+        let (cos_o, cos_i) = (wo.dot(&normal), wi.dot(&normal));
+        if cos_o <= 0.0 || cos_i <= 0.0 {
+            return Color::new(0, 0, 0);
+        }
+        let h = (wo + wi).normalize();
+        let alpha = self.alpha();
+        let fresnel = fresnel_schlick(self.f0(), wi.dot(&h));
+        let specular = fresnel
+            * (ggx_distribution(normal.dot(&h), alpha)
+                * smith_g1(cos_o, alpha)
+                * smith_g1(cos_i, alpha)
+                / (4.0 * cos_o * cos_i));
+        // Light not reflected at the surface reaches the diffuse layer beneath.
+        let diffuse =
+            (Color::new(1, 1, 1) - fresnel) * self.base_color * ((1.0 - self.metallic) / PI);
+        (specular + diffuse) * cos_i
+    }
+
+    fn pdf(&self, wo: Vector, wi: Vector, normal: Vector) -> f64 {
+        let (cos_o, cos_i) = (wo.dot(&normal), wi.dot(&normal));
+        if cos_o <= 0.0 || cos_i <= 0.0 {
+            return 0.0;
+        }
+        let h = (wo + wi).normalize();
+        let cos_h = normal.dot(&h);
+        let specular_pdf = ggx_distribution(cos_h, self.alpha()) * cos_h / (4.0 * wo.dot(&h));
+        let p = self.specular_probability(cos_o);
+        p * specular_pdf + (1.0 - p) * cos_i / PI
+    }
+
+    fn sample(&self, wo: Vector, normal: Vector, rng: &mut dyn RandomSource) -> Option<BsdfSample> {
+        let cos_o = wo.dot(&normal);
+        if cos_o <= 0.0 {
+            return None;
+        }
+        let direction = if rng.next_f64() < self.specular_probability(cos_o) {
+            // Sample a microfacet normal from the GGX distribution and mirror wo on it.
+            let (xi1, xi2) = (rng.next_f64(), rng.next_f64());
+            let alpha = self.alpha();
+            let tan2 = alpha * alpha * xi1 / (1.0 - xi1).max(1e-12);
+            let cos_h = 1.0 / (1.0 + tan2).sqrt();
+            let sin_h = (1.0 - cos_h * cos_h).max(0.0).sqrt();
+            let phi = 2.0 * PI * xi2;
+            let (tangent, bitangent) = normal.orthonormal_basis();
+            let h =
+                tangent * (sin_h * phi.cos()) + bitangent * (sin_h * phi.sin()) + normal * cos_h;
+            (-wo).reflect(&h)
+        } else {
+            cosine_hemisphere(normal, rng)
+        };
+
+        let pdf = self.pdf(wo, direction, normal);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(BsdfSample {
+            direction,
+            weight: self.evaluate(wo, direction, normal) * (1.0 / pdf),
+            pdf,
+            specular: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approximate_equation::ApproximateEq;
+    use crate::random::XorShift;
+
+    fn up() -> Vector {
+        Vector::new(0, 1, 0)
+    }
+
+    /// Dielectrics reflect 4% at normal incidence and everything at grazing angles.
+    #[test]
+    fn fresnel_dielectric() {
+        let m = PbrMaterial::new(Color::new(1, 0, 0), 0.0, 0.5, 1.5);
+        assert_eq!(m.f0(), Color::new(0.04, 0.04, 0.04));
+        assert_eq!(fresnel_schlick(m.f0(), 1.0), Color::new(0.04, 0.04, 0.04));
+        assert_eq!(fresnel_schlick(m.f0(), 0.0), Color::new(1, 1, 1));
+        let gold = PbrMaterial::new(Color::new(1, 0.8, 0.3), 1.0, 0.5, 1.5);
+        assert_eq!(gold.f0(), Color::new(1, 0.8, 0.3));
+    }
+
+    /// The GGX distribution is normalized: its projection on the surface integrates to 1.
+    #[test]
+    fn ggx_normalized() {
+        for alpha in [0.1, 0.5, 1.0] {
+            let steps = 20_000;
+            let sum: f64 = (0..steps)
+                .map(|i| {
+                    let theta = (i as f64 + 0.5) / steps as f64 * PI / 2.0;
+                    let cos = theta.cos();
+                    ggx_distribution(cos, alpha)
+                        * cos
+                        * theta.sin()
+                        * 2.0
+                        * PI
+                        * (PI / 2.0 / steps as f64)
+                })
+                .sum();
+            assert!((sum - 1.0).abs() < 1e-3, "alpha {alpha}: {sum}");
+        }
+        assert!(smith_g1(1.0, 0.5).approx_eq(&1.0));
+        assert_eq!(smith_g1(-0.1, 0.5), 0.0);
+    }
+
+    /// Sampled weights match evaluate / pdf and conserve energy.
+    #[test]
+    fn sample_consistent_with_evaluate() {
+        let mut rng = XorShift::new(5);
+        let wo = Vector::new(0.3, 1, 0.2).normalize();
+        for m in [
+            PbrMaterial::new(Color::new(1, 1, 1), 1.0, 0.4, 1.5),
+            PbrMaterial::new(Color::new(0.8, 0.8, 0.8), 0.0, 0.7, 1.5),
+            PbrMaterial::new(Color::new(1, 1, 1), 0.0, 0.0, 1.5),
+        ] {
+            let n = 20_000;
+            let mut total = 0.0;
+            for _ in 0..n {
+                if let Some(s) = m.sample(wo, up(), &mut rng) {
+                    let expected = m.evaluate(wo, s.direction, up()) * (1.0 / s.pdf);
+                    assert_eq!(s.weight, expected);
+                    assert!((s.pdf - m.pdf(wo, s.direction, up())).abs() < 1e-9);
+                    total += s.weight.luminance();
+                }
+            }
+            let albedo = total / n as f64;
+            assert!(albedo > 0.5 && albedo <= 1.01, "{m:?}: {albedo}");
+        }
+    }
+
+    /// A white rough metal reflects most light, estimated by uniform directions.
+    #[test]
+    fn evaluate_energy() {
+        let m = PbrMaterial::new(Color::new(1, 1, 1), 1.0, 0.5, 1.5);
+        let mut rng = XorShift::new(9);
+        let n = 50_000;
+        let mut sum = 0.0;
+        let mut pdf_sum = 0.0;
+        for _ in 0..n {
+            // Uniform directions on the hemisphere have density 1 / (2π).
+            let z = rng.next_f64();
+            let r = (1.0 - z * z).sqrt();
+            let phi = 2.0 * PI * rng.next_f64();
+            let wi = Vector::new(r * phi.cos(), z, r * phi.sin());
+            sum += m.evaluate(up(), wi, up()).red() * 2.0 * PI;
+            pdf_sum += m.pdf(up(), wi, up()) * 2.0 * PI;
+        }
+        let albedo = sum / n as f64;
+        assert!(albedo > 0.8 && albedo <= 1.02, "{albedo}");
+        // Microfacet normals tilted past 45° reflect below the surface, where the pdf is 0.
+        let pdf_total = pdf_sum / n as f64;
+        assert!(pdf_total > 0.9 && pdf_total <= 1.01, "{pdf_total}");
+    }
+
+    /// A polished metal reflects like a mirror.
+    #[test]
+    fn polished_metal_mirror() {
+        let m = PbrMaterial::new(Color::new(1, 1, 1), 1.0, 0.0, 1.5);
+        let wo = Vector::new(1, 1, 0).normalize();
+        let mut rng = XorShift::new(2);
+        let s = m.sample(wo, up(), &mut rng).unwrap();
+        assert!(s.direction.dot(&Vector::new(-1, 1, 0).normalize()) > 0.999);
+        // Light from below the surface is not reflected.
+        assert_eq!(
+            m.evaluate(wo, Vector::new(0, -1, 0), up()),
+            Color::new(0, 0, 0)
+        );
+    }
+}
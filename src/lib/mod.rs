@@ -1,5 +1,45 @@
 pub mod approximate_equation;
+pub mod bezier;
+pub mod blob;
+pub mod bsdf;
+pub mod bump;
 pub mod canvas;
 pub mod color;
+pub mod csg;
+pub mod cube;
+pub mod disk;
+pub mod environment;
+pub mod group;
+pub mod hdr;
+pub mod heightfield;
+pub mod image_texture;
+pub mod instance;
+pub mod layered;
+pub mod light;
+pub mod material;
+pub mod matrix;
+pub mod medium;
+pub mod mesh;
+pub mod mesh_light;
+pub mod microfacet;
+pub mod noise;
+pub mod path_tracer;
+pub mod pattern;
+pub mod ply;
+pub mod polynomial;
+pub mod procedural;
 pub mod projectile;
+pub mod random;
+pub mod ray;
+pub mod rectangle;
+pub mod sdf;
+pub mod shape;
+pub mod skybox;
+pub mod sphere;
+pub mod stl;
+pub mod subsurface;
+pub mod torus;
+pub mod triangle;
 pub mod tuple;
+pub mod uv;
+pub mod world;
@@ -0,0 +1,263 @@
+use crate::random::{RandomSource, XorShift};
+use crate::tuple::Point;
+
+/// Gradient directions of 3D Perlin and simplex noise: the midpoints of the edges of a
+/// cube.
+const GRADIENTS: [[f64; 3]; 12] = [
+    [1.0, 1.0, 0.0],
+    [-1.0, 1.0, 0.0],
+    [1.0, -1.0, 0.0],
+    [-1.0, -1.0, 0.0],
+    [1.0, 0.0, 1.0],
+    [-1.0, 0.0, 1.0],
+    [1.0, 0.0, -1.0],
+    [-1.0, 0.0, -1.0],
+    [0.0, 1.0, 1.0],
+    [0.0, -1.0, 1.0],
+    [0.0, 1.0, -1.0],
+    [0.0, -1.0, -1.0],
+];
+
+/// Smooth pseudo-random 3D noise, the building block of procedural textures such as
+/// marble, wood and clouds.
+///
+/// Values depend only on the point and on the seed the noise was made with, so the
+/// same seed always gives the same textures. Both kinds of noise are 0 on average and
+/// stay within -1 and 1.
+#[derive(Debug, Clone)]
+pub struct Noise {
+    /// Shuffled lattice indices, repeated once so that sums of indices need no wrapping.
+    permutation: Vec<usize>,
+}
+
+impl Noise {
+    /// Return a new Noise, shuffled by seed.
+    pub fn new(seed: u64) -> Self {
+        let mut rng = XorShift::new(seed);
+        let mut permutation: Vec<usize> = (0..256).collect();
+        for i in (1..permutation.len()).rev() {
+            let j = ((rng.next_f64() * (i + 1) as f64) as usize).min(i);
+            permutation.swap(i, j);
+        }
+        permutation.extend_from_within(..);
+        Noise { permutation }
+    }
+
+    fn gradient(&self, x: i64, y: i64, z: i64) -> [f64; 3] {
+        let p = &self.permutation;
+        let hash = p[p[p[(x & 255) as usize] + (y & 255) as usize] + (z & 255) as usize];
+        GRADIENTS[hash % 12]
+    }
+
+    /// Returns Perlin's improved gradient noise at point. It is 0 at points with integer
+    /// coordinates and varies over about one unit.
+    pub fn perlin(&self, point: Point) -> f64 {
+        let (x, y, z) = (point.x(), point.y(), point.z());
+        let (x0, y0, z0) = (x.floor(), y.floor(), z.floor());
+        let (fx, fy, fz) = (x - x0, y - y0, z - z0);
+        let (ix, iy, iz) = (x0 as i64, y0 as i64, z0 as i64);
+
+        let corner = |dx: i64, dy: i64, dz: i64| {
+            let g = self.gradient(ix + dx, iy + dy, iz + dz);
+            g[0] * (fx - dx as f64) + g[1] * (fy - dy as f64) + g[2] * (fz - dz as f64)
+        };
+        let (u, v, w) = (fade(fx), fade(fy), fade(fz));
+        let lerp = |t: f64, a: f64, b: f64| a + t * (b - a);
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, corner(0, 0, 0), corner(1, 0, 0)),
+                lerp(u, corner(0, 1, 0), corner(1, 1, 0)),
+            ),
+            lerp(
+                v,
+                lerp(u, corner(0, 0, 1), corner(1, 0, 1)),
+                lerp(u, corner(0, 1, 1), corner(1, 1, 1)),
+            ),
+        )
+    }
+
+    /// Returns simplex noise at point: like Perlin noise, with fewer grid artifacts and
+    /// at a lower cost, as it sums four corners of a tetrahedron instead of eight of a
+    /// cube.
+    pub fn simplex(&self, point: Point) -> f64 {
+        const SKEW: f64 = 1.0 / 3.0;
+        const UNSKEW: f64 = 1.0 / 6.0;
+        let (x, y, z) = (point.x(), point.y(), point.z());
+
+        // Find the cell of the skewed grid, and the simplex of the cell holding point.
+        let s = (x + y + z) * SKEW;
+        let (i, j, k) = ((x + s).floor(), (y + s).floor(), (z + s).floor());
+        let t = (i + j + k) * UNSKEW;
+        let offset0 = [x - (i - t), y - (j - t), z - (k - t)];
+        let [x0, y0, z0] = offset0;
+        let (step1, step2) = if x0 >= y0 {
+            if y0 >= z0 {
+                ([1, 0, 0], [1, 1, 0])
+            } else if x0 >= z0 {
+                ([1, 0, 0], [1, 0, 1])
+            } else {
+                ([0, 0, 1], [1, 0, 1])
+            }
+        } else if y0 < z0 {
+            ([0, 0, 1], [0, 1, 1])
+        } else if x0 < z0 {
+            ([0, 1, 0], [0, 1, 1])
+        } else {
+            ([0, 1, 0], [1, 1, 0])
+        };
+
+        let (i, j, k) = (i as i64, j as i64, k as i64);
+        [[0, 0, 0], step1, step2, [1, 1, 1]]
+            .iter()
+            .enumerate()
+            .map(|(n, step)| {
+                let offset =
+                    [0, 1, 2].map(|axis| offset0[axis] - step[axis] as f64 + n as f64 * UNSKEW);
+                // A radius of sqrt(0.5) keeps each corner within the simplices around it,
+                // so that the noise is continuous across their boundaries.
+                let falloff = 0.5 - offset.iter().map(|o| o * o).sum::<f64>();
+                if falloff <= 0.0 {
+                    return 0.0;
+                }
+                let g = self.gradient(i + step[0], j + step[1], k + step[2]);
+                let dot = g[0] * offset[0] + g[1] * offset[1] + g[2] * offset[2];
+                falloff.powi(4) * dot
+            })
+            .sum::<f64>()
+            // The most a corner gives is (4 / 9)^4 * sqrt(2 / 18), about 1 / 76.9.
+            * 76.0
+    }
+
+    /// Returns fractal Brownian motion at point: octaves layers of Perlin noise, each
+    /// lacunarity times finer and gain times fainter than the previous one. The result
+    /// is scaled back to within -1 and 1.
+    pub fn fbm(&self, point: Point, octaves: usize, lacunarity: f64, gain: f64) -> f64 {
+        self.fractal(point, octaves, lacunarity, gain, |n| n)
+    }
+
+    /// Returns turbulence at point: fractal Brownian motion of the absolute value of
+    /// Perlin noise, between 0 and 1. Its creases suit fire, smoke and marble veins.
+    pub fn turbulence(&self, point: Point, octaves: usize, lacunarity: f64, gain: f64) -> f64 {
+        self.fractal(point, octaves, lacunarity, gain, f64::abs)
+    }
+
+    fn fractal(
+        &self,
+        point: Point,
+        octaves: usize,
+        lacunarity: f64,
+        gain: f64,
+        shape: impl Fn(f64) -> f64,
+    ) -> f64 {
+        let mut sum = 0.0;
+        let mut total_amplitude = 0.0;
+        let (mut frequency, mut amplitude) = (1.0, 1.0);
+        for octave in 0..octaves {
+            // Shift each octave so that their lattice points do not line up.
+            let shift = octave as f64 * 17.31;
+            let p = Point::new(
+                point.x() * frequency + shift,
+                point.y() * frequency + shift,
+                point.z() * frequency + shift,
+            );
+            sum += amplitude * shape(self.perlin(p));
+            total_amplitude += amplitude;
+            frequency *= lacunarity;
+            amplitude *= gain;
+        }
+        if total_amplitude <= 0.0 {
+            return 0.0;
+        }
+        sum / total_amplitude
+    }
+}
+
+/// Perlin's quintic fade curve, whose first and second derivatives are 0 at 0 and 1.
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(count: usize) -> impl Iterator<Item = Point> {
+        let mut rng = XorShift::new(77);
+        (0..count).map(move |_| {
+            Point::new(
+                rng.next_f64() * 20.0 - 10.0,
+                rng.next_f64() * 20.0 - 10.0,
+                rng.next_f64() * 20.0 - 10.0,
+            )
+        })
+    }
+
+    /// Noise depends only on the point and the seed.
+    #[test]
+    fn noise_deterministic() {
+        let (a, b, c) = (Noise::new(1), Noise::new(1), Noise::new(2));
+        let p = Point::new(0.37, 4.81, -1.29);
+        assert_eq!(a.perlin(p), b.perlin(p));
+        assert_eq!(a.simplex(p), b.simplex(p));
+        assert_ne!(a.perlin(p), c.perlin(p));
+        assert_ne!(a.simplex(p), c.simplex(p));
+    }
+
+    /// Perlin noise is 0 on the lattice, and both kinds stay within -1 and 1 with an
+    /// average of about 0.
+    #[test]
+    fn noise_range() {
+        let noise = Noise::new(3);
+        assert_eq!(noise.perlin(Point::new(2, -5, 7)), 0.0);
+        let n = 20_000;
+        let (mut perlin_sum, mut simplex_sum) = (0.0, 0.0);
+        let (mut perlin_max, mut simplex_max) = (0.0f64, 0.0f64);
+        for p in points(n) {
+            let (a, b) = (noise.perlin(p), noise.simplex(p));
+            perlin_sum += a;
+            simplex_sum += b;
+            perlin_max = perlin_max.max(a.abs());
+            simplex_max = simplex_max.max(b.abs());
+        }
+        assert!(perlin_max <= 1.0 && perlin_max > 0.5, "{perlin_max}");
+        assert!(simplex_max <= 1.0 && simplex_max > 0.5, "{simplex_max}");
+        assert!((perlin_sum / n as f64).abs() < 0.02);
+        assert!((simplex_sum / n as f64).abs() < 0.02);
+    }
+
+    /// Noise changes smoothly from point to point.
+    #[test]
+    fn noise_continuous() {
+        let noise = Noise::new(4);
+        for p in points(1000) {
+            let q = Point::new(p.x() + 1e-4, p.y() - 1e-4, p.z() + 1e-4);
+            assert!((noise.perlin(p) - noise.perlin(q)).abs() < 1e-2);
+            assert!((noise.simplex(p) - noise.simplex(q)).abs() < 1e-2);
+        }
+        // Small steps along a line cross many simplex boundaries without a jump.
+        let along = |i: usize| {
+            let t = i as f64 * 2e-5;
+            noise.simplex(Point::new(0.1 + t, 0.3 + 0.7 * t, 0.2 - 0.4 * t))
+        };
+        for i in 0..200_000 {
+            assert!((along(i + 1) - along(i)).abs() < 5e-4);
+        }
+    }
+
+    /// Fractal noise stays within range, and turbulence is never negative.
+    #[test]
+    fn fractal_noise() {
+        let noise = Noise::new(5);
+        for p in points(1000) {
+            let f = noise.fbm(p, 6, 2.0, 0.5);
+            assert!((-1.0..=1.0).contains(&f));
+            let t = noise.turbulence(p, 6, 2.0, 0.5);
+            assert!((0.0..=1.0).contains(&t));
+        }
+        let p = Point::new(0.3, 0.6, 0.9);
+        assert_eq!(noise.fbm(p, 1, 2.0, 0.5), noise.perlin(p));
+        assert_eq!(noise.fbm(p, 0, 2.0, 0.5), 0.0);
+    }
+}
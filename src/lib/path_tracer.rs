@@ -0,0 +1,548 @@
+use crate::bsdf::Bsdf;
+use crate::color::Color;
+use crate::light::LightSample;
+use crate::material::Material;
+use crate::random::RandomSource;
+use crate::ray::Ray;
+use crate::world::World;
+use std::f64::consts::PI;
+
+/// Monte Carlo estimate of the light seen along rays, with global illumination.
+///
+/// Surfaces scatter light according to the Bsdf of their material, and the fog and
+/// volumes of the world according to their phase function, wherever delta tracking finds
+/// light colliding with them. At each bounce the lights of the world are sampled
+/// directly (next-event estimation), as is the environment, whose samples are combined
+/// with the directions chosen by the BSDF or phase function by multiple importance
+/// sampling. Paths are ended by Russian roulette once they are min_depth bounces long.
+///
+/// Light samples follow the convention of the Phong model, where a white diffuse
+/// surface facing a light of intensity I reflects I: they are weighted by π times the
+/// BSDF, and point and spot lights only dim with distance as their attenuation says,
+/// not by the inverse square law. The estimate is unbiased for lights taken this way,
+/// not for physically measured ones.
+#[derive(Debug, Clone, Copy)]
+pub struct PathTracer {
+    /// Bounces before Russian roulette may end a path.
+    pub min_depth: usize,
+    /// Bounces after which a path is ended regardless, as a safety net. Paths this long
+    /// are vanishingly rare, so the bias is negligible.
+    pub max_depth: usize,
+}
+
+impl PathTracer {
+    /// Return a new PathTracer.
+    pub fn new(min_depth: usize, max_depth: usize) -> Self {
+        PathTracer {
+            min_depth,
+            max_depth,
+        }
+    }
+
+    /// Returns one sample of the light arriving along ray. Average many samples per
+    /// pixel to reduce the noise.
+    pub fn radiance(&self, world: &World, ray: &Ray, rng: &mut dyn RandomSource) -> Color {
+        let mut radiance = Color::new(0, 0, 0);
+        let mut throughput = Color::new(1, 1, 1);
+        let mut ray = *ray;
+        // Whether the last bounce was specular, and the density with which the BSDF
+        // picked its direction otherwise, to weigh the environment seen along it.
+        let mut specular_bounce = true;
+        let mut bsdf_pdf = 0.0;
+
+        for depth in 0..self.max_depth {
+            let hit = world.hit(&ray);
+            let t_max = hit.map_or(f64::INFINITY, |hit| hit.intersection.t);
+
+            if let Some((t, medium)) = world.medium_collision(&ray, t_max, rng) {
+                // Scattered by a medium before reaching the next surface.
+                let point = ray.position(t);
+                let direction = ray.direction.normalize();
+                let phase = medium.phase;
+                throughput = throughput * medium.albedo();
+
+                for light in &world.lights {
+                    let samples = light.samples(point, rng);
+                    let mut direct = Color::new(0, 0, 0);
+                    for sample in &samples {
+                        let transmittance = world.transmittance(point, sample, rng);
+                        if transmittance > 0.0 {
+                            let p = phase.evaluate(direction, sample.direction);
+                            direct = direct + sample.intensity * (p * transmittance * PI);
+                        }
+                    }
+                    let count = samples.len().max(1) as f64;
+                    radiance = radiance + throughput * direct * (1.0 / count);
+                }
+
+                if let Some(environment) = &world.environment {
+                    if let Some((light_direction, light_pdf)) = environment.sample(rng) {
+                        let sample = LightSample {
+                            direction: light_direction,
+                            distance: f64::INFINITY,
+                            intensity: environment.radiance(light_direction),
+                        };
+                        let transmittance = world.transmittance(point, &sample, rng);
+                        if transmittance > 0.0 {
+                            let p = phase.evaluate(direction, light_direction);
+                            let weight = power_heuristic(light_pdf, p);
+                            radiance = radiance
+                                + throughput
+                                    * sample.intensity
+                                    * (p * transmittance * weight / light_pdf);
+                        }
+                    }
+                }
+
+                // Sampling the phase function exactly leaves the throughput unchanged.
+                let scattered = phase.sample(direction, rng);
+                specular_bounce = false;
+                bsdf_pdf = phase.evaluate(direction, scattered);
+                ray = Ray::new(point, scattered).with_cone(ray.width_at(t), ray.spread);
+            } else {
+                let hit = match hit {
+                    Some(hit) => hit,
+                    None => {
+                        if let Some(skybox) = world.skybox.as_ref().filter(|_| specular_bounce) {
+                            radiance = radiance + throughput * skybox.radiance(ray.direction);
+                        } else if let Some(environment) = &world.environment {
+                            let weight = if specular_bounce {
+                                1.0
+                            } else {
+                                power_heuristic(bsdf_pdf, environment.pdf(ray.direction))
+                            };
+                            radiance = radiance
+                                + throughput * environment.radiance(ray.direction) * weight;
+                        }
+                        break;
+                    }
+                };
+
+                let mut shading = hit.shading_point(&ray);
+                let width = ray.width_at(hit.intersection.t);
+                let material = &hit
+                    .instance
+                    .material_at(shading.point, &hit.intersection, width);
+                // The emission of mesh lights was already sampled at the previous bounce,
+                // unless it was specular.
+                if specular_bounce || !hit.instance.is_light {
+                    radiance = radiance + throughput * material.emitted();
+                }
+
+                let diffuse_exit = Material {
+                    diffuse: 1.0,
+                    ..Material::default()
+                };
+                let surface_reflection = Material {
+                    reflective: 1.0,
+                    ..Material::default()
+                };
+                let mut bsdf: &dyn Bsdf = material;
+                if let Some(subsurface) = &material.subsurface {
+                    // Picking the reflection off the surface with probability its
+                    // reflectance cancels its weight, as does entering the surface with
+                    // the rest.
+                    if rng.next_f64() < subsurface.reflectance(shading.eyev.dot(&shading.normalv)) {
+                        bsdf = &surface_reflection;
+                    } else {
+                        // The light goes on from wherever it comes back out of the surface.
+                        match subsurface.walk(hit.instance, &shading, rng) {
+                            Some(exit) => {
+                                throughput = throughput * exit.weight;
+                                shading = exit.shading;
+                                bsdf = &diffuse_exit;
+                            }
+                            None => break,
+                        }
+                    }
+                }
+                let (wo, normal) = (shading.eyev, shading.normalv);
+
+                for light in &world.lights {
+                    let samples = light.samples(shading.point, rng);
+                    let mut direct = Color::new(0, 0, 0);
+                    for sample in &samples {
+                        if sample.direction.dot(&normal) > 0.0 {
+                            let transmittance = world.transmittance(shading.point, sample, rng);
+                            if transmittance > 0.0 {
+                                let f = bsdf.evaluate(wo, sample.direction, normal);
+                                direct = direct + f * sample.intensity * (transmittance * PI);
+                            }
+                        }
+                    }
+                    let count = samples.len().max(1) as f64;
+                    radiance = radiance + throughput * direct * (1.0 / count);
+                }
+
+                if let Some(environment) = &world.environment {
+                    if let Some((direction, light_pdf)) = environment.sample(rng) {
+                        let f = bsdf.evaluate(wo, direction, normal);
+                        let sample = LightSample {
+                            direction,
+                            distance: f64::INFINITY,
+                            intensity: environment.radiance(direction),
+                        };
+                        if f.luminance() > 0.0 {
+                            let transmittance = world.transmittance(shading.point, &sample, rng);
+                            let weight =
+                                power_heuristic(light_pdf, bsdf.pdf(wo, direction, normal));
+                            radiance = radiance
+                                + throughput
+                                    * f
+                                    * sample.intensity
+                                    * (transmittance * weight / light_pdf);
+                        }
+                    }
+                }
+
+                let scattered = match bsdf.sample(wo, normal, rng) {
+                    Some(scattered) => scattered,
+                    None => break,
+                };
+                throughput = throughput * scattered.weight;
+                specular_bounce = scattered.specular;
+                bsdf_pdf = scattered.pdf;
+                ray = Ray::new(shading.point, scattered.direction).with_cone(width, ray.spread);
+            }
+
+            if depth + 1 >= self.min_depth {
+                let survival = throughput
+                    .red()
+                    .max(throughput.green())
+                    .max(throughput.blue())
+                    .clamp(0.05, 1.0);
+                if rng.next_f64() >= survival {
+                    break;
+                }
+                throughput = throughput * (1.0 / survival);
+            }
+        }
+
+        radiance
+    }
+}
+
+/// Weight of a sample taken with density pdf, when the same direction could also have
+/// been taken with density other_pdf by another technique (power heuristic).
+fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    if a + b <= 0.0 {
+        return 0.0;
+    }
+    a / (a + b)
+}
+
+/// Roulette from the third bounce on, with up to 64 bounces.
+impl Default for PathTracer {
+    fn default() -> Self {
+        PathTracer::new(3, 64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canvas::Canvas;
+    use crate::environment::EnvironmentMap;
+    use crate::instance::Instance;
+    use crate::light::{DirectionalLight, Light, PointLight};
+    use crate::matrix::Matrix;
+    use crate::medium::{Medium, Volume};
+    use crate::mesh::TriangleMesh;
+    use crate::microfacet::PbrMaterial;
+    use crate::random::XorShift;
+    use crate::skybox::Skybox;
+    use crate::sphere::Sphere;
+    use crate::subsurface::Subsurface;
+    use crate::tuple::{Point, Vector};
+    use crate::world::Fog;
+    use std::rc::Rc;
+
+    fn average(world: &World, ray: &Ray, n: usize) -> Color {
+        let tracer = PathTracer::default();
+        let mut rng = XorShift::new(11);
+        let mut sum = Color::new(0, 0, 0);
+        for _ in 0..n {
+            sum = sum + tracer.radiance(world, ray, &mut rng);
+        }
+        sum * (1.0 / n as f64)
+    }
+
+    fn sphere_world(material: Material) -> World {
+        World::new(
+            vec![Instance::new(
+                Rc::new(Sphere::default()),
+                Matrix::identity(),
+                material,
+            )],
+            vec![],
+        )
+    }
+
+    fn white_environment() -> EnvironmentMap {
+        let mut canvas = Canvas::new(8, 4);
+        canvas.set_all_pixel(Color::new(1, 1, 1));
+        EnvironmentMap::new(&canvas).unwrap()
+    }
+
+    /// A ray missing everything sees the environment.
+    #[test]
+    fn radiance_background() {
+        let mut w = sphere_world(Material::default());
+        w.environment = Some(white_environment());
+        let r = Ray::new(Point::new(0, 5, 0), Vector::new(0, 1, 0));
+        assert_eq!(average(&w, &r, 4), Color::new(1, 1, 1));
+    }
+
+    /// An emissive surface is seen with its own color.
+    #[test]
+    fn radiance_emissive_surface() {
+        let w = sphere_world(Material {
+            color: Color::new(0, 0, 0),
+            emission: Color::new(2, 1, 0.5),
+            ..Material::default()
+        });
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        assert_eq!(average(&w, &r, 16), Color::new(2, 1, 0.5));
+    }
+
+    /// A convex diffuse object in a uniform white environment reflects its albedo
+    /// (furnace test).
+    #[test]
+    fn radiance_furnace() {
+        let mut w = sphere_world(Material {
+            color: Color::new(0.5, 0.5, 0.5),
+            diffuse: 1.0,
+            ..Material::default()
+        });
+        w.environment = Some(white_environment());
+        let r = Ray::new(Point::new(0.3, 0.2, -5), Vector::new(0, 0, 1));
+        let c = average(&w, &r, 400);
+        assert!((c.red() - 0.5).abs() < 0.03, "{c:?}");
+    }
+
+    /// Light bounces between surfaces: the inside of a closed diffuse sphere lit by an
+    /// emissive wall converges to emission / (1 - albedo).
+    #[test]
+    fn radiance_interreflection() {
+        let w = sphere_world(Material {
+            color: Color::new(0.5, 0.5, 0.5),
+            diffuse: 1.0,
+            emission: Color::new(1, 1, 1),
+            ..Material::default()
+        });
+        let r = Ray::new(Point::new(0, 0, 0), Vector::new(0, 0, 1));
+        let c = average(&w, &r, 2000);
+        assert!((c.red() - 2.0).abs() < 0.1, "{c:?}");
+    }
+
+    /// A glossy metal in a uniform white environment reflects close to its color.
+    #[test]
+    fn radiance_pbr_furnace() {
+        let mut w = sphere_world(Material {
+            pbr: Some(PbrMaterial::new(Color::new(0.9, 0.6, 0.3), 1.0, 0.3, 1.5)),
+            ..Material::default()
+        });
+        w.environment = Some(white_environment());
+        let r = Ray::new(Point::new(0.1, 0.2, -5), Vector::new(0, 0, 1));
+        let c = average(&w, &r, 400);
+        assert!((c.red() - 0.9).abs() < 0.05, "{c:?}");
+        assert!((c.blue() - 0.3).abs() < 0.05, "{c:?}");
+    }
+
+    /// Sampling an emissive mesh as a light converges to the same light as finding it
+    /// by chance, with less noise.
+    #[test]
+    fn radiance_mesh_light() {
+        let panel = TriangleMesh::new(
+            vec![
+                Point::new(-1, 0, -1),
+                Point::new(1, 0, -1),
+                Point::new(1, 0, 1),
+                Point::new(-1, 0, 1),
+            ],
+            vec![[0, 1, 2], [0, 2, 3]],
+        );
+        let lamp = Material {
+            color: Color::new(0, 0, 0),
+            emission: Color::new(1, 1, 1),
+            emission_strength: 5.0,
+            ..Material::default()
+        };
+        let floor = Instance::new(
+            Rc::new(Sphere::default()),
+            Matrix::translation(0, -1, 0),
+            Material {
+                diffuse: 1.0,
+                ..Material::default()
+            },
+        );
+        let transform = Matrix::translation(0, 1, 0);
+
+        let mut sampled = World::new(vec![floor.clone()], vec![]);
+        sampled.add_mesh_light(&panel, transform, lamp.clone(), 1);
+        let found = World::new(
+            vec![
+                floor,
+                Instance::new(Rc::new(panel.to_group()), transform, lamp),
+            ],
+            vec![],
+        );
+
+        let r = Ray::new(Point::new(0.2, 3, 0.1), Vector::new(0, -1, 0));
+        let a = average(&sampled, &r, 2000);
+        let b = average(&found, &r, 4000);
+        assert!(a.red() > 0.5);
+        assert!((a.red() - b.red()).abs() < 0.1 * a.red(), "{a:?} {b:?}");
+        // Looking straight at the panel shows its emission.
+        let up = Ray::new(Point::new(0, 0.5, 0), Vector::new(0, 1, 0));
+        assert_eq!(average(&sampled, &up, 4), Color::new(5, 5, 5));
+    }
+
+    /// Fog absorbs the light of what lies behind it exponentially with distance.
+    #[test]
+    fn radiance_through_fog() {
+        let mut w = sphere_world(Material {
+            color: Color::new(0, 0, 0),
+            emission: Color::new(1, 1, 1),
+            ..Material::default()
+        });
+        w.fog = Some(Fog::new(Medium::new(0.25, 0.0), 10.0));
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        let c = average(&w, &r, 4000);
+        assert!((c.red() - (-1f64).exp()).abs() < 0.03, "{c:?}");
+    }
+
+    /// Fog lit by the sun scatters its light toward rays escaping the scene.
+    #[test]
+    fn radiance_fog_in_scattering() {
+        let mut w = World::new(
+            vec![],
+            vec![Box::new(DirectionalLight::new(
+                Vector::new(0, -1, 0),
+                Color::new(1, 1, 1),
+            ))],
+        );
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        assert_eq!(average(&w, &r, 50), Color::new(0, 0, 0));
+        w.fog = Some(Fog::new(Medium::new(0.0, 0.2), 10.0));
+        let lit = average(&w, &r, 500);
+        assert!(lit.red() > 0.0 && lit.red() < 1.0, "{lit:?}");
+    }
+
+    /// A lit volume scatters light toward rays crossing it, unless the light is blocked.
+    #[test]
+    fn radiance_volume_in_scattering() {
+        let smoke = Volume::new(
+            Rc::new(Sphere::default()),
+            Matrix::scaling(2, 2, 2),
+            Medium::new(0.0, 0.5),
+        );
+        let mut w = World::new(
+            vec![],
+            vec![Box::new(PointLight::new(
+                Point::new(0, 10, 0),
+                Color::new(1, 1, 1),
+            ))],
+        );
+        w.volumes.push(smoke);
+        let through = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        let lit = average(&w, &through, 500);
+        assert!(lit.red() > 0.0);
+        let past = Ray::new(Point::new(0, 3, -5), Vector::new(0, 0, 1));
+        assert_eq!(average(&w, &past, 50), Color::new(0, 0, 0));
+
+        w.objects.push(Instance::new(
+            Rc::new(Sphere::default()),
+            Matrix::translation(0, 5, 0),
+            Material::default(),
+        ));
+        let shadowed = average(&w, &through, 500);
+        assert!(shadowed.red() < lit.red() * 0.8, "{shadowed:?} {lit:?}");
+    }
+
+    /// Light lighting one side of a translucent object shines through to the other side.
+    #[test]
+    fn radiance_subsurface() {
+        let translucent = |subsurface| Material {
+            subsurface,
+            ..Material::default()
+        };
+        let light: Box<dyn Light> =
+            Box::new(PointLight::new(Point::new(5, 0, 0), Color::new(1, 1, 1)));
+        let mut w = sphere_world(translucent(None));
+        w.lights.push(light);
+        let back = Ray::new(Point::new(-5, 0, 0.2), Vector::new(1, 0, 0));
+        assert_eq!(average(&w, &back, 50), Color::new(0, 0, 0));
+
+        let wax = Subsurface::new(Color::new(0.99, 0.9, 0.8), Color::new(2, 1, 0.5));
+        w.objects[0].material = translucent(Some(wax));
+        let c = average(&w, &back, 1000);
+        assert!(c.red() > c.blue() && c.blue() > 0.0, "{c:?}");
+    }
+
+    /// Subsurface materials reflect their surroundings at grazing angles, even when no
+    /// light comes back out from beneath their surface.
+    #[test]
+    fn radiance_subsurface_sheen() {
+        let soot = Subsurface::new(Color::new(0, 0, 0), Color::new(0.01, 0.01, 0.01));
+        let mut w = sphere_world(Material {
+            subsurface: Some(soot),
+            ..Material::default()
+        });
+        w.environment = Some(white_environment());
+        let head_on = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        let grazing = Ray::new(Point::new(0, 0.99, -5), Vector::new(0, 0, 1));
+        assert!(average(&w, &head_on, 500).red() < 0.1);
+        assert!(average(&w, &grazing, 500).red() > 0.3);
+    }
+
+    /// The skybox is seen directly and in mirrors, but leaves lighting to the
+    /// environment.
+    #[test]
+    fn radiance_skybox() {
+        let face = || {
+            let mut face = Canvas::new(2, 2);
+            face.set_all_pixel(Color::new(0.5, 0.7, 1));
+            face
+        };
+        let mut w = sphere_world(Material::default());
+        w.skybox = Some(Skybox::from_images(
+            face(),
+            face(),
+            face(),
+            face(),
+            face(),
+            face(),
+        ));
+        let sky = Ray::new(Point::new(0, 5, 0), Vector::new(0, 1, 0));
+        assert_eq!(average(&w, &sky, 4), Color::new(0.5, 0.7, 1));
+        let sphere = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        assert_eq!(average(&w, &sphere, 16), Color::new(0, 0, 0));
+
+        w.objects[0].material.reflective = 1.0;
+        assert_eq!(average(&w, &sphere, 4), Color::new(0.5, 0.7, 1));
+        w.environment = Some(white_environment());
+        assert_eq!(average(&w, &sky, 4), Color::new(0.5, 0.7, 1));
+    }
+
+    /// A mirror shows what it reflects.
+    #[test]
+    fn radiance_mirror() {
+        let mut w = sphere_world(Material {
+            reflective: 1.0,
+            ..Material::default()
+        });
+        w.objects.push(Instance::new(
+            Rc::new(Sphere::default()),
+            Matrix::translation(0, 0, -5),
+            Material {
+                color: Color::new(0, 0, 0),
+                emission: Color::new(0, 1, 0),
+                ..Material::default()
+            },
+        ));
+        let r = Ray::new(Point::new(0, 0, -2), Vector::new(0, 0, 1));
+        assert_eq!(average(&w, &r, 8), Color::new(0, 1, 0));
+    }
+}
@@ -0,0 +1,138 @@
+use crate::color::Color;
+use crate::matrix::Matrix;
+use crate::tuple::Point;
+use std::fmt::Debug;
+use std::rc::Rc;
+
+/// Color varying over the surface of an object, in place of the color of its material.
+///
+/// Patterns are given points in the object space of the instance they color, so they
+/// move, turn and scale along with it.
+pub trait Pattern: Debug {
+    /// Returns the color at an object space point.
+    fn color_at(&self, point: Point) -> Color;
+
+    /// Returns the color around an object space point, blurred over a footprint about
+    /// width object space units wide, as seen by a ray standing for a beam that wide.
+    /// Patterns which do not blur return the color at the point.
+    fn color_over(&self, point: Point, _width: f64) -> Color {
+        self.color_at(point)
+    }
+}
+
+/// A pattern moved, turned or scaled over the object by transform.
+#[derive(Debug, Clone)]
+pub struct TransformedPattern {
+    pub pattern: Rc<dyn Pattern>,
+    transform: Matrix,
+    inverse: Matrix,
+}
+
+impl TransformedPattern {
+    /// Return a new TransformedPattern.
+    pub fn new(pattern: Rc<dyn Pattern>, transform: Matrix) -> Self {
+        TransformedPattern {
+            pattern,
+            transform,
+            inverse: transform.inverse(),
+        }
+    }
+
+    /// Returns the transformation from pattern space to object space.
+    pub fn transform(&self) -> Matrix {
+        self.transform
+    }
+}
+
+impl Pattern for TransformedPattern {
+    fn color_at(&self, point: Point) -> Color {
+        self.pattern.color_at(self.inverse * point)
+    }
+
+    fn color_over(&self, point: Point, width: f64) -> Color {
+        let width = width * self.inverse.stretch();
+        self.pattern.color_over(self.inverse * point, width)
+    }
+}
+
+/// Colors blended along a line from 0 to 1, for mapping noise and other values to
+/// colors. Positions outside the stops take the color of the nearest one.
+#[derive(Debug, Clone)]
+pub struct ColorRamp {
+    /// Positions and colors, sorted by position.
+    stops: Vec<(f64, Color)>,
+}
+
+impl ColorRamp {
+    /// Return a new ColorRamp through stops of a position and a color, in any order.
+    pub fn new(mut stops: Vec<(f64, Color)>) -> Self {
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        ColorRamp { stops }
+    }
+
+    /// Returns the positions and colors of the ramp, sorted by position.
+    pub fn stops(&self) -> &[(f64, Color)] {
+        &self.stops
+    }
+
+    /// Returns the color at position t, blended linearly between the stops around it.
+    pub fn at(&self, t: f64) -> Color {
+        let Some(&(first, first_color)) = self.stops.first() else {
+            return Color::new(0, 0, 0);
+        };
+        if t <= first {
+            return first_color;
+        }
+        for pair in self.stops.windows(2) {
+            let ((a, color_a), (b, color_b)) = (pair[0], pair[1]);
+            if t <= b {
+                let s = if b > a { (t - a) / (b - a) } else { 1.0 };
+                return color_a * (1.0 - s) + color_b * s;
+            }
+        }
+        self.stops[self.stops.len() - 1].1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Gradient;
+
+    impl Pattern for Gradient {
+        fn color_at(&self, point: Point) -> Color {
+            Color::new(point.x(), 0, 0)
+        }
+    }
+
+    /// A color ramp blends between its stops and keeps the end colors past them.
+    #[test]
+    fn color_ramp() {
+        let ramp = ColorRamp::new(vec![
+            (1.0, Color::new(0, 0, 1)),
+            (0.0, Color::new(1, 0, 0)),
+            (0.5, Color::new(0, 1, 0)),
+        ]);
+        assert_eq!(ramp.stops()[0].0, 0.0);
+        assert_eq!(ramp.at(-1.0), Color::new(1, 0, 0));
+        assert_eq!(ramp.at(0.25), Color::new(0.5, 0.5, 0));
+        assert_eq!(ramp.at(0.5), Color::new(0, 1, 0));
+        assert_eq!(ramp.at(0.875), Color::new(0, 0.25, 0.75));
+        assert_eq!(ramp.at(3.0), Color::new(0, 0, 1));
+        assert_eq!(ColorRamp::new(vec![]).at(0.5), Color::new(0, 0, 0));
+    }
+
+    /// A pattern with a transformation.
+    #[test]
+    fn transformed_pattern() {
+        let pattern = TransformedPattern::new(Rc::new(Gradient), Matrix::scaling(2, 2, 2));
+        assert_eq!(pattern.color_at(Point::new(1, 3, 0)), Color::new(0.5, 0, 0));
+        let moved = TransformedPattern::new(Rc::new(pattern), Matrix::translation(0.25, 0, 0));
+        assert_eq!(
+            moved.color_at(Point::new(1.25, 0, 0)),
+            Color::new(0.5, 0, 0)
+        );
+    }
+}
@@ -0,0 +1,534 @@
+use crate::color::Color;
+use crate::mesh::TriangleMesh;
+use crate::tuple::{Point, Vector};
+use std::io::{Error, ErrorKind, Read};
+use std::str::SplitAsciiWhitespace;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ScalarType {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> std::io::Result<Self> {
+        match name {
+            "char" | "int8" => Ok(ScalarType::Int8),
+            "uchar" | "uint8" => Ok(ScalarType::UInt8),
+            "short" | "int16" => Ok(ScalarType::Int16),
+            "ushort" | "uint16" => Ok(ScalarType::UInt16),
+            "int" | "int32" => Ok(ScalarType::Int32),
+            "uint" | "uint32" => Ok(ScalarType::UInt32),
+            "float" | "float32" => Ok(ScalarType::Float32),
+            "double" | "float64" => Ok(ScalarType::Float64),
+            _ => Err(invalid_data(&format!(
+                "unknown PLY property type '{}'",
+                name
+            ))),
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            ScalarType::Int8 | ScalarType::UInt8 => 1,
+            ScalarType::Int16 | ScalarType::UInt16 => 2,
+            ScalarType::Int32 | ScalarType::UInt32 | ScalarType::Float32 => 4,
+            ScalarType::Float64 => 8,
+        }
+    }
+
+    /// Integer color channels are stored as 0..=255, floating point ones as 0..=1.
+    fn color_scale(&self) -> f64 {
+        match self {
+            ScalarType::Float32 | ScalarType::Float64 => 1.0,
+            _ => 1.0 / 255.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Property {
+    name: String,
+    kind: ScalarType,
+    /// Type of the element count when the property is a list.
+    list_count: Option<ScalarType>,
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    fn property_index(&self, name: &str) -> Option<usize> {
+        self.properties.iter().position(|p| p.name == name)
+    }
+}
+
+/// Source of the values following the header, in either of the PLY encodings.
+enum Body<'a> {
+    Ascii(SplitAsciiWhitespace<'a>),
+    Binary {
+        data: &'a [u8],
+        position: usize,
+        big_endian: bool,
+    },
+}
+
+impl Body<'_> {
+    fn read(&mut self, kind: ScalarType) -> std::io::Result<f64> {
+        match self {
+            Body::Ascii(tokens) => tokens
+                .next()
+                .ok_or_else(|| invalid_data("unexpected end of PLY data"))?
+                .parse::<f64>()
+                .map_err(|_| invalid_data("invalid number in PLY data")),
+            Body::Binary {
+                data,
+                position,
+                big_endian,
+            } => {
+                let size = kind.size();
+                let bytes = data
+                    .get(*position..*position + size)
+                    .ok_or_else(|| invalid_data("unexpected end of PLY data"))?;
+                *position += size;
+
+                let mut raw = [0u8; 8];
+                raw[..size].copy_from_slice(bytes);
+                if *big_endian {
+                    raw[..size].reverse();
+                }
+                Ok(match kind {
+                    ScalarType::Int8 => raw[0] as i8 as f64,
+                    ScalarType::UInt8 => raw[0] as f64,
+                    ScalarType::Int16 => i16::from_le_bytes([raw[0], raw[1]]) as f64,
+                    ScalarType::UInt16 => u16::from_le_bytes([raw[0], raw[1]]) as f64,
+                    ScalarType::Int32 => i32::from_le_bytes(raw[..4].try_into().unwrap()) as f64,
+                    ScalarType::UInt32 => u32::from_le_bytes(raw[..4].try_into().unwrap()) as f64,
+                    ScalarType::Float32 => f32::from_le_bytes(raw[..4].try_into().unwrap()) as f64,
+                    ScalarType::Float64 => f64::from_le_bytes(raw),
+                })
+            }
+        }
+    }
+
+    /// Read all properties of one element, lists flattened after their count.
+    fn read_element(&mut self, element: &Element) -> std::io::Result<Vec<Vec<f64>>> {
+        element
+            .properties
+            .iter()
+            .map(|property| match property.list_count {
+                Some(count_kind) => {
+                    let count = self.read(count_kind)? as usize;
+                    (0..count).map(|_| self.read(property.kind)).collect()
+                }
+                None => Ok(vec![self.read(property.kind)?]),
+            })
+            .collect()
+    }
+}
+
+/// Parse an ASCII or binary PLY file into a TriangleMesh.
+///
+/// Vertices are read from the `x`, `y`, `z` properties of the `vertex` element and colors from
+/// `red`, `green`, `blue` when all three are present. Polygons of the `face` element are
+/// triangulated as fans. Any other element is skipped.
+///
+/// Normals are read from `nx`, `ny`, `nz` when all three are present, and texture
+/// coordinates from `u`, `v`, `s`, `t` or `texture_u`, `texture_v`.
+pub fn parse_ply(mut reader: impl Read) -> std::io::Result<TriangleMesh> {
+    let mut data = Vec::<u8>::new();
+    reader.read_to_end(&mut data)?;
+
+    let (format, elements, body_start) = parse_header(&data)?;
+    let mut body = match format {
+        Format::Ascii => Body::Ascii(
+            std::str::from_utf8(&data[body_start..])
+                .map_err(|_| invalid_data("PLY ASCII data is not valid UTF-8"))?
+                .split_ascii_whitespace(),
+        ),
+        Format::BinaryLittleEndian | Format::BinaryBigEndian => Body::Binary {
+            data: &data[body_start..],
+            position: 0,
+            big_endian: format == Format::BinaryBigEndian,
+        },
+    };
+
+    let mut mesh = TriangleMesh::default();
+
+    for element in &elements {
+        match element.name.as_str() {
+            "vertex" => {
+                let index = |name: &str| {
+                    element
+                        .property_index(name)
+                        .ok_or_else(|| invalid_data(&format!("PLY vertex has no '{}'", name)))
+                };
+                let (x, y, z) = (index("x")?, index("y")?, index("z")?);
+                let triple = |a: &str, b: &str, c: &str| match (
+                    element.property_index(a),
+                    element.property_index(b),
+                    element.property_index(c),
+                ) {
+                    (Some(a), Some(b), Some(c)) => Some((a, b, c)),
+                    _ => None,
+                };
+                let rgb = triple("red", "green", "blue");
+                let normal = triple("nx", "ny", "nz");
+                let uv = [("u", "v"), ("s", "t"), ("texture_u", "texture_v")]
+                    .iter()
+                    .find_map(|&(u, v)| {
+                        Some((element.property_index(u)?, element.property_index(v)?))
+                    });
+
+                let mut colors = Vec::<Color>::new();
+                let mut normals = Vec::<Vector>::new();
+                let mut uvs = Vec::<(f64, f64)>::new();
+                for _ in 0..element.count {
+                    let values = body.read_element(element)?;
+                    mesh.vertices
+                        .push(Point::new(values[x][0], values[y][0], values[z][0]));
+                    if let Some((nx, ny, nz)) = normal {
+                        normals.push(Vector::new(values[nx][0], values[ny][0], values[nz][0]));
+                    }
+                    if let Some((u, v)) = uv {
+                        uvs.push((values[u][0], values[v][0]));
+                    }
+                    if let Some((r, g, b)) = rgb {
+                        let scale = element.properties[r].kind.color_scale();
+                        colors.push(Color::new(
+                            values[r][0] * scale,
+                            values[g][0] * scale,
+                            values[b][0] * scale,
+                        ));
+                    }
+                }
+                if rgb.is_some() {
+                    mesh.colors = Some(colors);
+                }
+                if normal.is_some() {
+                    mesh.normals = Some(normals);
+                }
+                if uv.is_some() {
+                    mesh.uvs = Some(uvs);
+                }
+            }
+            "face" => {
+                let indices = element
+                    .property_index("vertex_indices")
+                    .or_else(|| element.property_index("vertex_index"))
+                    .ok_or_else(|| invalid_data("PLY face has no vertex_indices"))?;
+
+                for _ in 0..element.count {
+                    let values = body.read_element(element)?;
+                    let polygon = values[indices]
+                        .iter()
+                        .map(|&i| {
+                            if i.is_finite() && i >= 0.0 && i.fract() == 0.0 {
+                                Ok(i as usize)
+                            } else {
+                                Err(invalid_data("PLY face has an invalid vertex index"))
+                            }
+                        })
+                        .collect::<std::io::Result<Vec<usize>>>()?;
+                    for i in 1..polygon.len().saturating_sub(1) {
+                        mesh.faces.push([polygon[0], polygon[i], polygon[i + 1]]);
+                    }
+                }
+            }
+            _ => {
+                for _ in 0..element.count {
+                    body.read_element(element)?;
+                }
+            }
+        }
+    }
+
+    let vertex_count = mesh.vertices.len();
+    if mesh.faces.iter().flatten().any(|&i| i >= vertex_count) {
+        return Err(invalid_data("PLY face references a missing vertex"));
+    }
+
+    Ok(mesh)
+}
+
+/// Parse the header, returning the format, the element declarations and the offset of the body.
+fn parse_header(data: &[u8]) -> std::io::Result<(Format, Vec<Element>, usize)> {
+    const END: &[u8] = b"end_header";
+    let end = data
+        .windows(END.len())
+        .position(|window| window == END)
+        .ok_or_else(|| invalid_data("PLY header has no end_header"))?;
+    let body_start = data[end..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map(|newline| end + newline + 1)
+        .unwrap_or(data.len());
+
+    let header = std::str::from_utf8(&data[..end])
+        .map_err(|_| invalid_data("PLY header is not valid UTF-8"))?;
+    let mut lines = header.lines().map(str::trim);
+    if lines.next() != Some("ply") {
+        return Err(invalid_data("not a PLY file"));
+    }
+
+    let mut format = None;
+    let mut elements = Vec::<Element>::new();
+    for line in lines {
+        let words: Vec<&str> = line.split_ascii_whitespace().collect();
+        match words.as_slice() {
+            ["format", name, _version] => {
+                format = Some(match *name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    _ => return Err(invalid_data(&format!("unknown PLY format '{}'", name))),
+                });
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| invalid_data("invalid PLY element count"))?,
+                properties: vec![],
+            }),
+            ["property", "list", count_kind, kind, name] => elements
+                .last_mut()
+                .ok_or_else(|| invalid_data("PLY property outside of an element"))?
+                .properties
+                .push(Property {
+                    name: name.to_string(),
+                    kind: ScalarType::parse(kind)?,
+                    list_count: Some(ScalarType::parse(count_kind)?),
+                }),
+            ["property", kind, name] => elements
+                .last_mut()
+                .ok_or_else(|| invalid_data("PLY property outside of an element"))?
+                .properties
+                .push(Property {
+                    name: name.to_string(),
+                    kind: ScalarType::parse(kind)?,
+                    list_count: None,
+                }),
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            _ => return Err(invalid_data(&format!("invalid PLY header line '{}'", line))),
+        }
+    }
+
+    let format = format.ok_or_else(|| invalid_data("PLY header has no format"))?;
+    Ok((format, elements, body_start))
+}
+
+fn invalid_data(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parsing an ASCII PLY file with vertex colors.
+    #[test]
+    fn parse_ascii_ply() {
+        let ply = "\
+ply
+format ascii 1.0
+comment made by hand
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+-1 1 0 255 0 0
+-1 0 0 0 255 0
+1 0 0 0 0 255
+1 1 0 255 255 255
+4 0 1 2 3
+";
+        let mesh = parse_ply(ply.as_bytes()).unwrap();
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.vertices[2], Point::new(1, 0, 0));
+        assert_eq!(mesh.faces, vec![[0, 1, 2], [0, 2, 3]]);
+        let colors = mesh.colors.unwrap();
+        assert_eq!(colors[1], Color::new(0, 1, 0));
+        assert_eq!(colors[3], Color::new(1, 1, 1));
+    }
+
+    /// Parsing texture coordinates.
+    #[test]
+    fn parse_ply_texture_coordinates() {
+        let ply = "\
+ply
+format ascii 1.0
+element vertex 3
+property float x
+property float y
+property float z
+property float s
+property float t
+element face 1
+property list uchar int vertex_indices
+end_header
+0 1 0 0.5 1
+-1 0 0 0 0
+1 0 0 1 0
+3 0 1 2
+";
+        let mut mesh = parse_ply(ply.as_bytes()).unwrap();
+        assert_eq!(mesh.uvs.as_ref().unwrap()[0], (0.5, 1.0));
+        mesh.compute_tangents();
+        assert_eq!(mesh.tangents.unwrap()[1], (Vector::new(1, 0, 0), 1.0));
+    }
+
+    /// Parsing a little endian binary PLY file, skipping unknown elements.
+    #[test]
+    fn parse_binary_ply() {
+        let mut ply = b"\
+ply
+format binary_little_endian 1.0
+element vertex 3
+property float x
+property float y
+property float z
+property float nx
+element material 1
+property uchar index
+element face 1
+property list uchar uint vertex_index
+end_header
+"
+        .to_vec();
+        for vertex in [[0f32, 1.0, 0.0], [-1.0, 0.0, 0.0], [1.0, 0.0, 0.0]] {
+            for value in vertex.iter().chain([0.5f32].iter()) {
+                ply.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        ply.push(7);
+        ply.push(3);
+        for index in [0u32, 1, 2] {
+            ply.extend_from_slice(&index.to_le_bytes());
+        }
+
+        let mesh = parse_ply(ply.as_slice()).unwrap();
+        assert_eq!(mesh.vertices[0], Point::new(0, 1, 0));
+        assert_eq!(mesh.vertices[1], Point::new(-1, 0, 0));
+        assert_eq!(mesh.faces, vec![[0, 1, 2]]);
+        assert!(mesh.colors.is_none());
+    }
+
+    /// Parsing vertex normals.
+    #[test]
+    fn parse_ply_normals() {
+        let ply = "\
+ply
+format ascii 1.0
+element vertex 3
+property float x
+property float y
+property float z
+property float nx
+property float ny
+property float nz
+element face 1
+property list uchar int vertex_indices
+end_header
+0 1 0 0 0 1
+-1 0 0 0 0 1
+1 0 0 0 0 1
+3 0 1 2
+";
+        let mesh = parse_ply(ply.as_bytes()).unwrap();
+        assert_eq!(mesh.normals.unwrap()[2], Vector::new(0, 0, 1));
+    }
+
+    /// Parsing a big endian binary PLY file with floating point colors.
+    #[test]
+    fn parse_big_endian_ply() {
+        let mut ply = b"\
+ply
+format binary_big_endian 1.0
+element vertex 3
+property double x
+property double y
+property double z
+property float red
+property float green
+property float blue
+element face 1
+property list uchar short vertex_indices
+end_header
+"
+        .to_vec();
+        for (vertex, color) in [
+            ([0.0, 1.0, 0.0], [0.5f32, 0.0, 0.0]),
+            ([-1.0, 0.0, 0.0], [0.0, 0.5, 0.0]),
+            ([1.0, 0.0, 0.0], [0.0, 0.0, 0.5]),
+        ] {
+            for value in vertex {
+                ply.extend_from_slice(&f64::to_be_bytes(value));
+            }
+            for value in color {
+                ply.extend_from_slice(&value.to_be_bytes());
+            }
+        }
+        ply.push(3);
+        for index in [2i16, 1, 0] {
+            ply.extend_from_slice(&index.to_be_bytes());
+        }
+
+        let mesh = parse_ply(ply.as_slice()).unwrap();
+        assert_eq!(mesh.vertices[2], Point::new(1, 0, 0));
+        assert_eq!(mesh.faces, vec![[2, 1, 0]]);
+        assert_eq!(mesh.colors.unwrap()[1], Color::new(0, 0.5, 0));
+    }
+
+    /// Malformed PLY files are rejected.
+    #[test]
+    fn parse_invalid_ply() {
+        assert!(parse_ply("solid\n".as_bytes()).is_err());
+        let missing_vertex = "\
+ply
+format ascii 1.0
+element vertex 1
+property float x
+property float y
+property float z
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0
+3 0 1 2
+";
+        let err = parse_ply(missing_vertex.as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        for index in ["-1", "nan", "1.5"] {
+            let bad_index = missing_vertex
+                .replace("element vertex 1", "element vertex 3")
+                .replace("0 0 0\n", "0 0 0\n1 0 0\n0 1 0\n")
+                .replace("3 0 1 2", &format!("3 0 {index} 2"));
+            let err = parse_ply(bad_index.as_bytes()).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+        }
+    }
+}
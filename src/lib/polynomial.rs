@@ -0,0 +1,196 @@
+// Real polynomials stored as coefficients in increasing order of power:
+// `[c0, c1, c2]` is `c0 + c1 * x + c2 * x^2`.
+
+/// Relative size below which a leading coefficient is considered zero.
+const DEGENERATE: f64 = 1e-14;
+/// Relative size below which the value at a critical point is considered a root.
+const TANGENT: f64 = 1e-9;
+const MAX_BISECTIONS: usize = 200;
+
+/// Evaluate the polynomial at x.
+pub fn evaluate(coefficients: &[f64], x: f64) -> f64 {
+    coefficients.iter().rev().fold(0.0, |acc, c| acc * x + c)
+}
+
+/// Returns the derivative of the polynomial.
+pub fn derivative(coefficients: &[f64]) -> Vec<f64> {
+    coefficients
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(power, c)| c * power as f64)
+        .collect()
+}
+
+/// Returns the product of two polynomials.
+pub fn multiply(a: &[f64], b: &[f64]) -> Vec<f64> {
+    if a.is_empty() || b.is_empty() {
+        return vec![];
+    }
+    let mut product = vec![0.0; a.len() + b.len() - 1];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            product[i + j] += x * y;
+        }
+    }
+    product
+}
+
+/// Returns the real roots within [min, max], sorted in increasing order.
+///
+/// The roots are isolated between the critical points of the polynomial, found
+/// recursively from its derivative, and refined by bisection. This never misses a
+/// root through a bad starting guess. A critical point where the polynomial is zero
+/// within rounding, such as a ray grazing a surface, is reported twice so that
+/// roots keep coming in pairs of entering and leaving the surface.
+pub fn real_roots(coefficients: &[f64], min: f64, max: f64) -> Vec<f64> {
+    let coefficients = trim(coefficients);
+    if coefficients.len() < 2 || min > max {
+        return vec![];
+    }
+    if coefficients.len() == 2 {
+        let root = -coefficients[0] / coefficients[1];
+        return if (min..=max).contains(&root) {
+            vec![root]
+        } else {
+            vec![]
+        };
+    }
+
+    let critical_points = real_roots(&derivative(coefficients), min, max);
+    let mut breakpoints = vec![min];
+    for x in critical_points {
+        if x > *breakpoints.last().unwrap() && x < max {
+            breakpoints.push(x);
+        }
+    }
+    breakpoints.push(max);
+
+    let values: Vec<f64> = breakpoints
+        .iter()
+        .map(|&x| snap_to_zero(coefficients, x))
+        .collect();
+
+    let mut roots = vec![];
+    for i in 0..breakpoints.len() {
+        if values[i] == 0.0 {
+            roots.push(breakpoints[i]);
+            let interior = i > 0 && i < breakpoints.len() - 1;
+            if interior && values[i - 1] * values[i + 1] > 0.0 {
+                roots.push(breakpoints[i]);
+            }
+        }
+        if i + 1 < breakpoints.len() && values[i] * values[i + 1] < 0.0 {
+            roots.push(bisect(coefficients, breakpoints[i], breakpoints[i + 1]));
+        }
+    }
+
+    roots
+}
+
+/// Drop leading coefficients which are negligible next to the others.
+fn trim(coefficients: &[f64]) -> &[f64] {
+    let largest = coefficients.iter().fold(0.0f64, |m, c| m.max(c.abs()));
+    let mut len = coefficients.len();
+    while len > 0 && coefficients[len - 1].abs() <= largest * DEGENERATE {
+        len -= 1;
+    }
+    &coefficients[..len]
+}
+
+/// Evaluate the polynomial, returning zero when the value is lost in rounding errors.
+fn snap_to_zero(coefficients: &[f64], x: f64) -> f64 {
+    let value = evaluate(coefficients, x);
+    let magnitude = coefficients
+        .iter()
+        .rev()
+        .fold(0.0, |acc, c| acc * x.abs() + c.abs());
+    if value.abs() <= magnitude * TANGENT {
+        0.0
+    } else {
+        value
+    }
+}
+
+/// Find the root within [low, high], where the polynomial changes sign.
+fn bisect(coefficients: &[f64], mut low: f64, mut high: f64) -> f64 {
+    let low_sign = evaluate(coefficients, low).signum();
+    for _ in 0..MAX_BISECTIONS {
+        let middle = 0.5 * (low + high);
+        if middle <= low || middle >= high {
+            break;
+        }
+        if evaluate(coefficients, middle).signum() == low_sign {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    0.5 * (low + high)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approximate_equation::ApproximateEq;
+
+    fn assert_roots(actual: Vec<f64>, expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len(), "roots: {:?}", actual);
+        for (a, e) in actual.iter().zip(expected) {
+            assert!(
+                a.approx_eq(e),
+                "roots: {:?}, expected: {:?}",
+                actual,
+                expected
+            );
+        }
+    }
+
+    /// Evaluating a polynomial and its derivative.
+    #[test]
+    fn evaluate_polynomial() {
+        let p = [1.0, -2.0, 3.0];
+        assert_eq!(evaluate(&p, 2.0), 9.0);
+        assert_eq!(derivative(&p), vec![-2.0, 6.0]);
+    }
+
+    /// Multiplying polynomials.
+    #[test]
+    fn multiply_polynomials() {
+        // (x - 1)(x + 1)
+        assert_eq!(multiply(&[-1.0, 1.0], &[1.0, 1.0]), vec![-1.0, 0.0, 1.0]);
+        assert_eq!(multiply(&[2.0], &[1.0, 3.0]), vec![2.0, 6.0]);
+    }
+
+    /// The roots of a quadratic.
+    #[test]
+    fn quadratic_roots() {
+        // (x - 1)(x - 3)
+        assert_roots(real_roots(&[3.0, -4.0, 1.0], -10.0, 10.0), &[1.0, 3.0]);
+        // x^2 + 1
+        assert_roots(real_roots(&[1.0, 0.0, 1.0], -10.0, 10.0), &[]);
+    }
+
+    /// The roots of a quartic with four distinct roots.
+    #[test]
+    fn quartic_roots() {
+        // (x + 2)(x + 1)(x - 1)(x - 2) = x^4 - 5x^2 + 4
+        let p = [4.0, 0.0, -5.0, 0.0, 1.0];
+        assert_roots(real_roots(&p, -10.0, 10.0), &[-2.0, -1.0, 1.0, 2.0]);
+        assert_roots(real_roots(&p, 0.0, 10.0), &[1.0, 2.0]);
+    }
+
+    /// A double root is reported twice.
+    #[test]
+    fn double_root() {
+        // (x - 1)^2 (x - 3)^2
+        let p = [9.0, -24.0, 22.0, -8.0, 1.0];
+        assert_roots(real_roots(&p, -10.0, 10.0), &[1.0, 1.0, 3.0, 3.0]);
+    }
+
+    /// A negligible leading coefficient lowers the degree.
+    #[test]
+    fn degenerate_leading_coefficient() {
+        assert_roots(real_roots(&[-2.0, 1.0, 1e-20], -10.0, 10.0), &[2.0]);
+    }
+}
@@ -0,0 +1,214 @@
+use crate::color::Color;
+use crate::noise::Noise;
+use crate::pattern::{ColorRamp, Pattern};
+use crate::tuple::Point;
+use std::f64::consts::PI;
+
+/// Marble: bands along x, bent into veins by turbulence.
+#[derive(Debug, Clone)]
+pub struct Marble {
+    pub noise: Noise,
+    /// Colors from the body of the stone (0) to the veins (1).
+    pub ramp: ColorRamp,
+    /// Bands per unit along x.
+    pub frequency: f64,
+    /// How far turbulence bends the bands.
+    pub distortion: f64,
+    pub octaves: usize,
+}
+
+impl Marble {
+    /// Return a new white Marble with grey veins, about one band per unit.
+    pub fn new(seed: u64) -> Self {
+        Marble {
+            noise: Noise::new(seed),
+            ramp: ColorRamp::new(vec![
+                (0.0, Color::new(0.92, 0.91, 0.88)),
+                (0.7, Color::new(0.8, 0.79, 0.78)),
+                (0.95, Color::new(0.35, 0.35, 0.38)),
+                (1.0, Color::new(0.2, 0.2, 0.22)),
+            ]),
+            frequency: 1.0,
+            distortion: 5.0,
+            octaves: 6,
+        }
+    }
+}
+
+impl Pattern for Marble {
+    fn color_at(&self, point: Point) -> Color {
+        let turbulence = self.noise.turbulence(point, self.octaves, 2.0, 0.5);
+        let band = (PI * (point.x() * self.frequency + self.distortion * turbulence)).sin();
+        // Veins are where the bands peak, which makes them thin.
+        self.ramp.at(band.abs().powi(8))
+    }
+}
+
+/// Wood: growth rings around the y axis, warped by noise.
+#[derive(Debug, Clone)]
+pub struct Wood {
+    pub noise: Noise,
+    /// Colors across a ring, from early wood (0) to late wood (1).
+    pub ramp: ColorRamp,
+    /// Rings per unit of distance from the y axis.
+    pub ring_frequency: f64,
+    /// How far noise moves the rings, in rings.
+    pub distortion: f64,
+    /// Size of the wobbles of the rings; larger values make finer wobbles.
+    pub noise_frequency: f64,
+}
+
+impl Wood {
+    /// Return a new light brown Wood with darker rings, four rings per unit.
+    pub fn new(seed: u64) -> Self {
+        Wood {
+            noise: Noise::new(seed),
+            ramp: ColorRamp::new(vec![
+                (0.0, Color::new(0.76, 0.56, 0.34)),
+                (0.6, Color::new(0.7, 0.49, 0.28)),
+                (1.0, Color::new(0.45, 0.28, 0.14)),
+            ]),
+            ring_frequency: 4.0,
+            distortion: 0.6,
+            noise_frequency: 1.5,
+        }
+    }
+}
+
+impl Pattern for Wood {
+    fn color_at(&self, point: Point) -> Color {
+        let p = Point::new(
+            point.x() * self.noise_frequency,
+            point.y() * self.noise_frequency * 0.25,
+            point.z() * self.noise_frequency,
+        );
+        let distance = (point.x() * point.x() + point.z() * point.z()).sqrt();
+        let rings =
+            distance * self.ring_frequency + self.distortion * self.noise.fbm(p, 4, 2.0, 0.5);
+        // Late wood grows quickly into the dark edge of each ring.
+        self.ramp.at(rings.rem_euclid(1.0).powi(3))
+    }
+}
+
+/// Granite: fine grains of a few minerals.
+#[derive(Debug, Clone)]
+pub struct Granite {
+    pub noise: Noise,
+    /// Colors of the minerals, picked by the noise at each grain.
+    pub ramp: ColorRamp,
+    /// Grains per unit.
+    pub frequency: f64,
+    pub octaves: usize,
+}
+
+impl Granite {
+    /// Return a new pink and grey Granite with black flecks, about twenty grains per
+    /// unit.
+    pub fn new(seed: u64) -> Self {
+        Granite {
+            noise: Noise::new(seed),
+            ramp: ColorRamp::new(vec![
+                (0.0, Color::new(0.08, 0.08, 0.08)),
+                (0.12, Color::new(0.15, 0.14, 0.14)),
+                (0.2, Color::new(0.6, 0.58, 0.57)),
+                (0.45, Color::new(0.75, 0.72, 0.7)),
+                (0.55, Color::new(0.78, 0.55, 0.5)),
+                (1.0, Color::new(0.85, 0.62, 0.56)),
+            ]),
+            frequency: 20.0,
+            octaves: 3,
+        }
+    }
+}
+
+impl Pattern for Granite {
+    fn color_at(&self, point: Point) -> Color {
+        let p = Point::new(
+            point.x() * self.frequency,
+            point.y() * self.frequency,
+            point.z() * self.frequency,
+        );
+        let grain = self.noise.turbulence(p, self.octaves, 2.5, 0.6);
+        self.ramp.at((grain * 2.5).min(1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::{RandomSource, XorShift};
+
+    /// Returns the colors of the pattern at points of the unit cube.
+    fn sample(pattern: &dyn Pattern) -> Vec<Color> {
+        let mut rng = XorShift::new(12);
+        (0..2000)
+            .map(|_| {
+                let p = Point::new(rng.next_f64(), rng.next_f64(), rng.next_f64());
+                pattern.color_at(p)
+            })
+            .collect()
+    }
+
+    fn within_ramp(colors: &[Color], ramp: &ColorRamp) -> bool {
+        let (lowest, highest) = ramp
+            .stops()
+            .iter()
+            .fold((f64::MAX, f64::MIN), |(lo, hi), s| {
+                (lo.min(s.1.luminance()), hi.max(s.1.luminance()))
+            });
+        colors
+            .iter()
+            .all(|c| c.luminance() >= lowest - 1e-9 && c.luminance() <= highest + 1e-9)
+    }
+
+    fn spread(colors: &[Color]) -> f64 {
+        let l: Vec<f64> = colors.iter().map(|c| c.luminance()).collect();
+        l.iter().cloned().fold(f64::MIN, f64::max) - l.iter().cloned().fold(f64::MAX, f64::min)
+    }
+
+    /// Each texture varies across the colors of its ramp, and uses no others.
+    #[test]
+    fn textures_follow_ramps() {
+        let marble = Marble::new(1);
+        let wood = Wood::new(1);
+        let granite = Granite::new(1);
+        for (colors, ramp) in [
+            (sample(&marble), &marble.ramp),
+            (sample(&wood), &wood.ramp),
+            (sample(&granite), &granite.ramp),
+        ] {
+            assert!(within_ramp(&colors, ramp));
+            assert!(spread(&colors) > 0.2);
+        }
+    }
+
+    /// Textures are the same for the same seed, and custom ramps recolor them.
+    #[test]
+    fn textures_seeded_and_configurable() {
+        let p = Point::new(0.3, 0.7, 0.1);
+        assert_eq!(Marble::new(4).color_at(p), Marble::new(4).color_at(p));
+        assert_eq!(Wood::new(4).color_at(p), Wood::new(4).color_at(p));
+        let black_and_white =
+            ColorRamp::new(vec![(0.0, Color::new(0, 0, 0)), (1.0, Color::new(1, 1, 1))]);
+        let granite = Granite {
+            ramp: black_and_white,
+            ..Granite::new(4)
+        };
+        let c = granite.color_at(p);
+        assert_eq!(c.red(), c.blue());
+    }
+
+    /// Wood rings are centered on the y axis: points at the same distance from it
+    /// along a ring are colored alike when undistorted.
+    #[test]
+    fn wood_rings() {
+        let wood = Wood {
+            distortion: 0.0,
+            ..Wood::new(2)
+        };
+        let a = wood.color_at(Point::new(0.3, 0, 0));
+        let b = wood.color_at(Point::new(0, 5, -0.3));
+        assert_eq!(a, b);
+        assert_ne!(a, wood.color_at(Point::new(0.2, 0, 0)));
+    }
+}
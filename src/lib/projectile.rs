@@ -15,7 +15,7 @@ fn tick(env: &Environment, p: &Projectile) -> Projectile {
     let position = p.position + (p.velocity);
     let velocity = p.velocity + env.gravity + env.wind;
 
-    return Projectile { position, velocity };
+    Projectile { position, velocity }
 }
 
 impl Environment {
@@ -0,0 +1,118 @@
+use crate::tuple::Vector;
+use std::f64::consts::PI;
+
+/// Source of random numbers uniformly distributed in [0, 1).
+pub trait RandomSource {
+    /// Returns the next number in [0, 1).
+    fn next_f64(&mut self) -> f64;
+}
+
+/// Fast pseudo-random generator (xorshift64*), reproducible from its seed.
+#[derive(Debug, Clone)]
+pub struct XorShift {
+    state: u64,
+}
+
+impl XorShift {
+    /// Return a new XorShift generator. Any seed, even zero, gives a valid sequence.
+    pub fn new(seed: u64) -> Self {
+        // Scramble the seed so that close seeds give unrelated sequences. The state must
+        // never be zero.
+        let state = (seed ^ 0x9e37_79b9_7f4a_7c15).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        XorShift {
+            state: state.max(1),
+        }
+    }
+}
+
+impl RandomSource for XorShift {
+    fn next_f64(&mut self) -> f64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        let bits = self.state.wrapping_mul(0x2545_f491_4f6c_dd1d);
+        // The top 53 bits fill the mantissa of an f64 in [0, 1).
+        (bits >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Cycles through a fixed list of numbers, to make sampling predictable in tests or to
+/// disable jittering with a single 0.5.
+#[derive(Debug, Clone)]
+pub struct Sequence {
+    values: Vec<f64>,
+    index: usize,
+}
+
+impl Sequence {
+    /// Return a new Sequence repeating values.
+    pub fn new(values: Vec<f64>) -> Self {
+        assert!(!values.is_empty(), "a sequence needs at least one value");
+        Sequence { values, index: 0 }
+    }
+}
+
+impl RandomSource for Sequence {
+    fn next_f64(&mut self) -> f64 {
+        let value = self.values[self.index];
+        self.index = (self.index + 1) % self.values.len();
+        value
+    }
+}
+
+/// Returns a random unit vector in the hemisphere around normal, with a probability
+/// density of cos θ / π where θ is its angle to the normal.
+pub fn cosine_hemisphere(normal: Vector, rng: &mut dyn RandomSource) -> Vector {
+    // Uniform points on the unit disk projected up onto the hemisphere (Malley's method).
+    let r = rng.next_f64().sqrt();
+    let phi = 2.0 * PI * rng.next_f64();
+    let (tangent, bitangent) = normal.orthonormal_basis();
+    let z = (1.0 - r * r).max(0.0).sqrt();
+    tangent * (r * phi.cos()) + bitangent * (r * phi.sin()) + normal * z
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A number generator returns a cyclic sequence of numbers.
+    #[test]
+    fn sequence_cycles() {
+        let mut gen = Sequence::new(vec![0.1, 0.5, 1.0]);
+        let values: Vec<f64> = (0..4).map(|_| gen.next_f64()).collect();
+        assert_eq!(values, vec![0.1, 0.5, 1.0, 0.1]);
+    }
+
+    /// Cosine weighted directions stay above the surface, mostly close to the normal.
+    #[test]
+    fn cosine_hemisphere_distribution() {
+        let normal = Vector::new(1, 1, 0).normalize();
+        let mut rng = XorShift::new(3);
+        let n = 20_000;
+        let mut sum = 0.0;
+        for _ in 0..n {
+            let d = cosine_hemisphere(normal, &mut rng);
+            assert!((d.magnitude() - 1.0).abs() < 1e-9);
+            assert!(d.dot(&normal) >= 0.0);
+            sum += d.dot(&normal);
+        }
+        // The mean of cos θ over the density cos θ / π is 2/3.
+        assert!((sum / n as f64 - 2.0 / 3.0).abs() < 0.01);
+    }
+
+    /// A pseudo-random generator is reproducible and stays in [0, 1).
+    #[test]
+    fn xorshift_range() {
+        let mut a = XorShift::new(0);
+        let mut b = XorShift::new(0);
+        let values: Vec<f64> = (0..10_000).map(|_| a.next_f64()).collect();
+        assert!(values.iter().all(|&x| (0.0..1.0).contains(&x)));
+        assert_eq!(
+            values[..5],
+            (0..5).map(|_| b.next_f64()).collect::<Vec<_>>()[..]
+        );
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        assert!((mean - 0.5).abs() < 0.01);
+        assert_ne!(XorShift::new(1).next_f64(), values[0]);
+    }
+}
@@ -0,0 +1,81 @@
+use crate::tuple::{Point, Vector};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Ray {
+    pub origin: Point,
+    pub direction: Vector,
+    /// Width of the beam of light the ray stands for at its origin, in world units.
+    /// Textures seen along the ray are blurred over the width of the beam where they are
+    /// hit.
+    pub width: f64,
+    /// How much wider the beam grows per unit of distance along the ray, such as the
+    /// angle a pixel covers for rays cast from a camera.
+    pub spread: f64,
+}
+
+impl Ray {
+    /// Return a new Ray standing for a beam of no width.
+    pub fn new(origin: Point, direction: Vector) -> Self {
+        Ray {
+            origin,
+            direction,
+            width: 0.0,
+            spread: 0.0,
+        }
+    }
+
+    /// Return the ray standing for a beam of width at its origin, growing by spread per
+    /// unit of distance.
+    pub fn with_cone(self, width: f64, spread: f64) -> Self {
+        Ray {
+            width,
+            spread,
+            ..self
+        }
+    }
+
+    /// Returns the width of the beam at distance t along the ray.
+    pub fn width_at(&self, t: f64) -> f64 {
+        self.width + self.spread * t * self.direction.magnitude()
+    }
+
+    /// Returns the point at distance t along the ray.
+    pub fn position(&self, t: f64) -> Point {
+        self.origin + self.direction * t
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creating and querying a ray.
+    #[test]
+    fn create_ray() {
+        let origin = Point::new(1, 2, 3);
+        let direction = Vector::new(4, 5, 6);
+        let r = Ray::new(origin, direction);
+        assert_eq!(r.origin, origin);
+        assert_eq!(r.direction, direction);
+    }
+
+    /// Computing a point from a distance.
+    #[test]
+    fn position_ray() {
+        let r = Ray::new(Point::new(2, 3, 4), Vector::new(1, 0, 0));
+        assert_eq!(r.position(0.0), Point::new(2, 3, 4));
+        assert_eq!(r.position(1.0), Point::new(3, 3, 4));
+        assert_eq!(r.position(-1.0), Point::new(1, 3, 4));
+        assert_eq!(r.position(2.5), Point::new(4.5, 3, 4));
+    }
+
+    /// The beam of a ray widens with the distance traveled.
+    #[test]
+    fn ray_cone() {
+        let r = Ray::new(Point::new(0, 0, 0), Vector::new(0, 3, 4));
+        assert_eq!(r.width_at(2.0), 0.0);
+        let r = r.with_cone(0.5, 0.01);
+        assert!((r.width_at(0.0) - 0.5).abs() < 1e-12);
+        assert!((r.width_at(2.0) - 0.6).abs() < 1e-12);
+    }
+}
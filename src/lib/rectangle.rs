@@ -0,0 +1,127 @@
+use crate::ray::Ray;
+use crate::shape::{Intersection, Shape};
+use crate::tuple::{Point, Vector};
+
+/// Flat quad spanned by two edges from a corner. The edges need not be perpendicular,
+/// so any parallelogram can be described. The normal is edge_u × edge_v, and hits carry
+/// u, v going from 0 to 1 along edge_u and edge_v.
+#[derive(Debug, Clone, Copy)]
+pub struct Rectangle {
+    pub corner: Point,
+    pub edge_u: Vector,
+    pub edge_v: Vector,
+}
+
+impl Rectangle {
+    /// Return a new Rectangle.
+    pub fn new(corner: Point, edge_u: Vector, edge_v: Vector) -> Self {
+        Rectangle {
+            corner,
+            edge_u,
+            edge_v,
+        }
+    }
+
+    /// Returns the point of the rectangle at surface coordinates u, v.
+    pub fn point_at(&self, u: f64, v: f64) -> Point {
+        self.corner + self.edge_u * u + self.edge_v * v
+    }
+
+    /// Returns the area of the rectangle.
+    pub fn area(&self) -> f64 {
+        self.edge_u.cross(&self.edge_v).magnitude()
+    }
+}
+
+/// The square from -1 to 1 in x and z at the origin, facing up.
+impl Default for Rectangle {
+    fn default() -> Self {
+        Rectangle::new(
+            Point::new(-1, 0, -1),
+            Vector::new(0, 0, 2),
+            Vector::new(2, 0, 0),
+        )
+    }
+}
+
+impl Shape for Rectangle {
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let n = self.edge_u.cross(&self.edge_v);
+        let denominator = ray.direction.dot(&n);
+        if denominator.abs() < 1e-12 {
+            return vec![];
+        }
+
+        let t = (self.corner - ray.origin).dot(&n) / denominator;
+        let offset = ray.position(t) - self.corner;
+        // Solve offset = u * edge_u + v * edge_v in the plane of the rectangle.
+        let w = n / n.dot(&n);
+        let u = w.dot(&offset.cross(&self.edge_v));
+        let v = w.dot(&self.edge_u.cross(&offset));
+        if !(0.0..=1.0).contains(&u) || !(0.0..=1.0).contains(&v) {
+            return vec![];
+        }
+
+        vec![Intersection::with_uv(t, self, u, v)]
+    }
+
+    fn local_normal_at(&self, _point: Point, _hit: &Intersection) -> Vector {
+        self.edge_u.cross(&self.edge_v).normalize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approximate_equation::ApproximateEq;
+
+    /// A ray hits a rectangle and reports its u/v coordinates.
+    #[test]
+    fn ray_hits_rectangle() {
+        let rect = Rectangle::default();
+        let r = Ray::new(Point::new(0.5, 3, -0.5), Vector::new(0, -1, 0));
+        let xs = rect.local_intersect(&r);
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].t, 3.0);
+        assert!(xs[0].u.approx_eq(&0.25));
+        assert!(xs[0].v.approx_eq(&0.75));
+    }
+
+    /// A ray misses a rectangle outside of its edges or parallel to it.
+    #[test]
+    fn ray_misses_rectangle() {
+        let rect = Rectangle::default();
+        let r = Ray::new(Point::new(1.5, 3, 0), Vector::new(0, -1, 0));
+        assert!(rect.local_intersect(&r).is_empty());
+        let r = Ray::new(Point::new(-5, 0, 0), Vector::new(1, 0, 0));
+        assert!(rect.local_intersect(&r).is_empty());
+    }
+
+    /// A slanted parallelogram only covers the area between its edges.
+    #[test]
+    fn ray_hits_parallelogram() {
+        let rect = Rectangle::new(
+            Point::new(0, 0, 0),
+            Vector::new(2, 0, 0),
+            Vector::new(1, 1, 0),
+        );
+        let r = Ray::new(Point::new(0.2, 0.5, -1), Vector::new(0, 0, 1));
+        assert!(rect.local_intersect(&r).is_empty());
+        let r = Ray::new(Point::new(2.2, 0.5, -1), Vector::new(0, 0, 1));
+        let xs = rect.local_intersect(&r);
+        assert_eq!(xs.len(), 1);
+        assert_eq!(rect.point_at(xs[0].u, xs[0].v), Point::new(2.2, 0.5, 0));
+        assert!(rect.area().approx_eq(&2.0));
+    }
+
+    /// The normal of a rectangle follows its edges.
+    #[test]
+    fn normal_rectangle() {
+        let rect = Rectangle::default();
+        let hit = Intersection::new(0.0, &rect);
+        assert_eq!(
+            rect.local_normal_at(Point::new(0, 0, 0), &hit),
+            Vector::new(0, 1, 0)
+        );
+    }
+}
@@ -0,0 +1,292 @@
+use crate::ray::Ray;
+use crate::shape::{Intersection, Shape};
+use crate::tuple::{Point, Vector};
+
+/// Shape defined by a signed distance function: negative inside, positive outside and,
+/// away from the surface, never larger than the distance to the surface.
+///
+/// Rays are sphere traced: the marcher repeatedly steps forward by the distance to the
+/// surface, which can never overshoot it, and records a hit once it gets closer than
+/// epsilon. The march carries on through the shape so that exits are reported too. Rays
+/// starting inside the shape are also marched backward to report where they entered it,
+/// behind their origin, as other closed shapes do.
+pub struct Sdf<F>
+where
+    F: Fn(Point) -> f64,
+{
+    distance: F,
+    /// Distance along the ray after which marching gives up.
+    pub max_distance: f64,
+    /// Number of steps after which marching gives up.
+    pub max_steps: usize,
+    /// Distance to the surface under which a point counts as on the surface.
+    pub epsilon: f64,
+}
+
+impl<F> Sdf<F>
+where
+    F: Fn(Point) -> f64,
+{
+    /// Return a new Sdf.
+    pub fn new(distance: F) -> Self {
+        Sdf {
+            distance,
+            max_distance: 100.0,
+            max_steps: 512,
+            epsilon: 1e-4,
+        }
+    }
+
+    /// Returns the signed distance from a point to the surface.
+    pub fn distance(&self, point: Point) -> f64 {
+        (self.distance)(point)
+    }
+
+    /// Returns how far back from origin, against the unit direction, the surface is.
+    fn march_back(&self, origin: Point, direction: Vector) -> Option<f64> {
+        let mut s = 0.0;
+        for _ in 0..self.max_steps {
+            if s > self.max_distance {
+                break;
+            }
+            let d = self.distance(origin - direction * s).abs();
+            if d < self.epsilon {
+                return Some(s);
+            }
+            s += d;
+        }
+        None
+    }
+}
+
+impl<F> Shape for Sdf<F>
+where
+    F: Fn(Point) -> f64,
+{
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let length = ray.direction.magnitude();
+        let direction = ray.direction / length;
+        let mut xs = vec![];
+        if self.distance(ray.origin) < -self.epsilon {
+            if let Some(s) = self.march_back(ray.origin, direction) {
+                xs.push(Intersection::new(-s / length, self));
+            }
+        }
+        // A hit is only recorded after the marcher left the surface of the previous one.
+        let mut armed = true;
+        let mut s = 0.0;
+
+        for _ in 0..self.max_steps {
+            if s > self.max_distance {
+                break;
+            }
+            let d = self.distance(ray.origin + direction * s).abs();
+            if d < self.epsilon {
+                if armed {
+                    xs.push(Intersection::new(s / length, self));
+                    armed = false;
+                }
+                s += self.epsilon;
+            } else {
+                armed = true;
+                s += d;
+            }
+        }
+
+        xs
+    }
+
+    fn local_normal_at(&self, point: Point, _hit: &Intersection) -> Vector {
+        // Central differences of the distance field.
+        let h = self.epsilon;
+        let dx = Vector::new(h, 0, 0);
+        let dy = Vector::new(0, h, 0);
+        let dz = Vector::new(0, 0, h);
+        Vector::new(
+            self.distance(point + dx) - self.distance(point - dx),
+            self.distance(point + dy) - self.distance(point - dy),
+            self.distance(point + dz) - self.distance(point - dz),
+        )
+        .normalize()
+    }
+}
+
+/// Sphere of the given radius centered at the origin.
+pub fn sphere(radius: f64) -> impl Fn(Point) -> f64 {
+    move |p| (p - Point::new(0, 0, 0)).magnitude() - radius
+}
+
+/// Box centered at the origin, extending half_extents along each axis.
+pub fn cuboid(half_extents: Vector) -> impl Fn(Point) -> f64 {
+    move |p| {
+        let q = Vector::new(
+            p.x().abs() - half_extents.x(),
+            p.y().abs() - half_extents.y(),
+            p.z().abs() - half_extents.z(),
+        );
+        let outside = Vector::new(q.x().max(0.0), q.y().max(0.0), q.z().max(0.0)).magnitude();
+        let inside = q.x().max(q.y()).max(q.z()).min(0.0);
+        outside + inside
+    }
+}
+
+/// Torus centered at the origin around the y axis, like torus::Torus.
+pub fn torus(major_radius: f64, minor_radius: f64) -> impl Fn(Point) -> f64 {
+    move |p| {
+        let ring = p.x().hypot(p.z()) - major_radius;
+        ring.hypot(p.y()) - minor_radius
+    }
+}
+
+/// Moves a distance function by offset.
+pub fn translate(f: impl Fn(Point) -> f64, offset: Vector) -> impl Fn(Point) -> f64 {
+    move |p| f(p - offset)
+}
+
+/// Union of two distance functions.
+pub fn union(a: impl Fn(Point) -> f64, b: impl Fn(Point) -> f64) -> impl Fn(Point) -> f64 {
+    move |p| a(p).min(b(p))
+}
+
+/// Intersection of two distance functions.
+pub fn intersection(a: impl Fn(Point) -> f64, b: impl Fn(Point) -> f64) -> impl Fn(Point) -> f64 {
+    move |p| a(p).max(b(p))
+}
+
+/// Difference of two distance functions: a with b carved out.
+pub fn difference(a: impl Fn(Point) -> f64, b: impl Fn(Point) -> f64) -> impl Fn(Point) -> f64 {
+    move |p| a(p).max(-b(p))
+}
+
+/// Union blending the two surfaces together where they are closer than k. A k of zero
+/// or less does not blend them.
+pub fn smooth_union(
+    a: impl Fn(Point) -> f64,
+    b: impl Fn(Point) -> f64,
+    k: f64,
+) -> impl Fn(Point) -> f64 {
+    move |p| {
+        let (da, db) = (a(p), b(p));
+        if k <= 0.0 {
+            return da.min(db);
+        }
+        let h = (0.5 + 0.5 * (db - da) / k).clamp(0.0, 1.0);
+        db + (da - db) * h - k * h * (1.0 - h)
+    }
+}
+
+/// Repeats a distance function every period along each axis. An axis with a period
+/// of zero is not repeated. The repeated shape must fit within one period.
+pub fn repeat(f: impl Fn(Point) -> f64, period: Vector) -> impl Fn(Point) -> f64 {
+    let wrap = |x: f64, period: f64| {
+        if period == 0.0 {
+            x
+        } else {
+            x - period * (x / period).round()
+        }
+    };
+    move |p| {
+        f(Point::new(
+            wrap(p.x(), period.x()),
+            wrap(p.y(), period.y()),
+            wrap(p.z(), period.z()),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-3,
+            "{} is not close to {}",
+            actual,
+            expected
+        );
+    }
+
+    /// Distances to the basic distance functions.
+    #[test]
+    fn distance_functions() {
+        let p = Point::new(3, 0, 0);
+        assert_near(sphere(1.0)(p), 2.0);
+        assert_near(cuboid(Vector::new(1, 2, 3))(p), 2.0);
+        assert_near(cuboid(Vector::new(1, 2, 3))(Point::new(0, 0, 0)), -1.0);
+        assert_near(torus(2.0, 0.5)(p), 0.5);
+        assert_near(translate(sphere(1.0), Vector::new(3, 0, 0))(p), -1.0);
+    }
+
+    /// Combining distance functions.
+    #[test]
+    fn combine_distance_functions() {
+        let a = || sphere(1.0);
+        let b = || translate(sphere(1.0), Vector::new(1.5, 0, 0));
+        let p = Point::new(-0.5, 0, 0);
+        assert_near(union(a(), b())(p), -0.5);
+        assert_near(intersection(a(), b())(p), 1.0);
+        assert_near(difference(a(), b())(p), -0.5);
+        // Blending pulls the surface in between the two spheres outwards.
+        let between = Point::new(0.75, 1, 0);
+        assert!(smooth_union(a(), b(), 0.5)(between) < union(a(), b())(between));
+        let halfway = Point::new(0.75, 0, 0);
+        assert_near(smooth_union(a(), b(), 0.0)(halfway), -0.25);
+    }
+
+    /// A ray intersects a distance field sphere when entering and leaving it.
+    #[test]
+    fn ray_intersects_sdf_sphere() {
+        let s = Sdf::new(sphere(1.0));
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 2));
+        let xs = s.local_intersect(&r);
+        assert_eq!(xs.len(), 2);
+        assert_near(xs[0].t, 2.0);
+        assert_near(xs[1].t, 3.0);
+    }
+
+    /// A ray starting inside a distance field also hits where it entered, behind it.
+    #[test]
+    fn ray_inside_sdf_sphere() {
+        let s = Sdf::new(sphere(1.0));
+        let r = Ray::new(Point::new(0, 0, 0), Vector::new(0, 0, 2));
+        let xs = s.local_intersect(&r);
+        assert_eq!(xs.len(), 2);
+        assert_near(xs[0].t, -0.5);
+        assert_near(xs[1].t, 0.5);
+    }
+
+    /// A ray misses a distance field.
+    #[test]
+    fn ray_misses_sdf() {
+        let s = Sdf::new(torus(2.0, 0.5));
+        let r = Ray::new(Point::new(0, 5, 0), Vector::new(0, -1, 0));
+        assert!(s.local_intersect(&r).is_empty());
+    }
+
+    /// A ray hits the next copy of a repeated shape.
+    #[test]
+    fn ray_intersects_repetition() {
+        let s = Sdf::new(repeat(sphere(0.5), Vector::new(0, 0, 4)));
+        let r = Ray::new(Point::new(0, 0, 1), Vector::new(0, 0, 1));
+        let xs = s.local_intersect(&r);
+        assert_near(xs[0].t, 2.5);
+        assert_near(xs[1].t, 3.5);
+    }
+
+    /// The normal of a distance field is estimated by central differences.
+    #[test]
+    fn normal_sdf() {
+        let s = Sdf::new(cuboid(Vector::new(1, 1, 1)));
+        let hit = Intersection::new(0.0, &s);
+        let n = s.local_normal_at(Point::new(1, 0.3, -0.2), &hit);
+        assert_near(n.x(), 1.0);
+        assert_near(n.y(), 0.0);
+
+        let s = Sdf::new(sphere(1.0));
+        let k = 3f64.sqrt() / 3.0;
+        let n = s.local_normal_at(Point::new(k, k, k), &hit);
+        assert_near(n.x(), k);
+        assert_near(n.z(), k);
+    }
+}
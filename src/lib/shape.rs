@@ -0,0 +1,165 @@
+use crate::color::Color;
+use crate::ray::Ray;
+use crate::tuple::{Point, Vector};
+
+/// Geometry which can be intersected by a ray.
+///
+/// Shapes work in their own object space: rays passed to local_intersect and points
+/// passed to local_normal_at are already expressed in the coordinates of the shape.
+pub trait Shape {
+    /// Returns every intersection of the ray with the shape, in no particular order.
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>>;
+
+    /// Returns the normal at a point on the surface. The intersection which produced the
+    /// point is passed along for shapes which need more than the point itself.
+    fn local_normal_at(&self, point: Point, hit: &Intersection) -> Vector;
+
+    /// Returns the color at a point on the surface, for shapes which carry their own,
+    /// such as triangles of meshes with per-vertex colors.
+    fn local_color_at(&self, _point: Point, _hit: &Intersection) -> Option<Color> {
+        None
+    }
+
+    /// Returns the texture coordinates at a point on the surface, for shapes which carry
+    /// their own, such as triangles of meshes with per-vertex texture coordinates.
+    fn local_uv_at(&self, _point: Point, _hit: &Intersection) -> Option<(f64, f64)> {
+        None
+    }
+
+    /// Returns the tangent pointing toward increasing u at a point on the surface, for
+    /// shapes which carry one, with the handedness of the texture coordinates: 1 where v
+    /// increases along normal × tangent and -1 where it decreases. The tangent need not
+    /// be of unit length or perpendicular to the normal.
+    fn local_tangent_at(&self, _point: Point, _hit: &Intersection) -> Option<(Vector, f64)> {
+        None
+    }
+
+    /// Returns true if other is this shape or, for composite shapes, one of its children.
+    /// Shapes are compared by address, so a Shape should never be a zero sized type.
+    fn includes(&self, other: &dyn Shape) -> bool {
+        std::ptr::addr_eq(self, other)
+    }
+}
+
+/// A ray hitting a shape at distance t. u and v carry the surface coordinates of the hit
+/// for shapes which provide them.
+#[derive(Clone, Copy)]
+pub struct Intersection<'a> {
+    pub t: f64,
+    pub object: &'a dyn Shape,
+    pub u: f64,
+    pub v: f64,
+}
+
+impl<'a> Intersection<'a> {
+    /// Return a new Intersection.
+    pub fn new(t: f64, object: &'a dyn Shape) -> Self {
+        Intersection::with_uv(t, object, 0.0, 0.0)
+    }
+
+    /// Return a new Intersection with surface coordinates.
+    pub fn with_uv(t: f64, object: &'a dyn Shape, u: f64, v: f64) -> Self {
+        Intersection { t, object, u, v }
+    }
+
+    /// Returns the normal of the intersected object at a point.
+    pub fn normal_at(&self, point: Point) -> Vector {
+        self.object.local_normal_at(point, self)
+    }
+
+    /// Returns the color of the intersected object at a point, if it has its own.
+    pub fn color_at(&self, point: Point) -> Option<Color> {
+        self.object.local_color_at(point, self)
+    }
+
+    /// Returns the texture coordinates of the intersected object at a point, if it has
+    /// any.
+    pub fn uv_at(&self, point: Point) -> Option<(f64, f64)> {
+        self.object.local_uv_at(point, self)
+    }
+
+    /// Returns the tangent of the intersected object at a point and its handedness, if it
+    /// has one.
+    pub fn tangent_at(&self, point: Point) -> Option<(Vector, f64)> {
+        self.object.local_tangent_at(point, self)
+    }
+}
+
+/// Sort intersections by increasing t.
+pub fn sort_intersections(xs: &mut [Intersection]) {
+    xs.sort_by(|a, b| a.t.total_cmp(&b.t));
+}
+
+/// Returns the visible intersection: the one with the lowest non-negative t.
+pub fn hit<'a>(xs: &[Intersection<'a>]) -> Option<Intersection<'a>> {
+    xs.iter()
+        .filter(|x| x.t >= 0.0)
+        .min_by(|a, b| a.t.total_cmp(&b.t))
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sphere::Sphere;
+
+    /// An intersection encapsulates t and object.
+    #[test]
+    fn intersection() {
+        let s = Sphere::default();
+        let i = Intersection::new(3.5, &s);
+        assert_eq!(i.t, 3.5);
+        assert!(i.object.includes(&s));
+    }
+
+    /// The hit, when all intersections have positive t.
+    #[test]
+    fn hit_positive_t() {
+        let s = Sphere::default();
+        let xs = [Intersection::new(2.0, &s), Intersection::new(1.0, &s)];
+        assert_eq!(hit(&xs).unwrap().t, 1.0);
+    }
+
+    /// The hit, when some intersections have negative t.
+    #[test]
+    fn hit_some_negative_t() {
+        let s = Sphere::default();
+        let xs = [Intersection::new(-1.0, &s), Intersection::new(1.0, &s)];
+        assert_eq!(hit(&xs).unwrap().t, 1.0);
+    }
+
+    /// The hit, when all intersections have negative t.
+    #[test]
+    fn hit_all_negative_t() {
+        let s = Sphere::default();
+        let xs = [Intersection::new(-2.0, &s), Intersection::new(-1.0, &s)];
+        assert!(hit(&xs).is_none());
+    }
+
+    /// The hit is always the lowest nonnegative intersection.
+    #[test]
+    fn hit_lowest_nonnegative() {
+        let s = Sphere::default();
+        let mut xs = vec![
+            Intersection::new(5.0, &s),
+            Intersection::new(7.0, &s),
+            Intersection::new(-3.0, &s),
+            Intersection::new(2.0, &s),
+        ];
+        assert_eq!(hit(&xs).unwrap().t, 2.0);
+        sort_intersections(&mut xs);
+        assert_eq!(
+            xs.iter().map(|x| x.t).collect::<Vec<_>>(),
+            vec![-3.0, 2.0, 5.0, 7.0]
+        );
+    }
+
+    /// Distinct shapes are not included in each other.
+    #[test]
+    fn includes_by_address() {
+        let s1 = Sphere::default();
+        let s2 = Sphere::default();
+        assert!(s1.includes(&s1));
+        assert!(!s1.includes(&s2));
+    }
+}
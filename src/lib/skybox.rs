@@ -0,0 +1,123 @@
+use crate::canvas::Canvas;
+use crate::color::Color;
+use crate::image_texture::{Filter, ImageTexture, Wrap};
+use crate::pattern::Pattern;
+use crate::tuple::{Point, Vector};
+use crate::uv::CubeMap;
+use std::rc::Rc;
+
+/// Background of six images on the faces of a cube around the scene, far enough away
+/// that only the direction of rays matters.
+///
+/// Faces are mapped as seen from inside the cube: the front face is ahead when looking
+/// toward +z with y up, with the right edge of its image toward +x.
+#[derive(Debug, Clone)]
+pub struct Skybox {
+    pub faces: CubeMap,
+    /// Scales the colors of the images.
+    pub strength: f64,
+}
+
+impl Skybox {
+    /// Return a new Skybox showing faces.
+    pub fn new(faces: CubeMap) -> Self {
+        Skybox {
+            faces,
+            strength: 1.0,
+        }
+    }
+
+    /// Return a new Skybox from six images, sampled bilinearly without wrapping around,
+    /// so that the edges of the faces meet without seams.
+    pub fn from_images(
+        left: Canvas,
+        front: Canvas,
+        right: Canvas,
+        back: Canvas,
+        up: Canvas,
+        down: Canvas,
+    ) -> Self {
+        let face =
+            |image| Rc::new(ImageTexture::new(image).with_sampling(Filter::Bilinear, Wrap::Clamp));
+        Skybox::new(CubeMap {
+            left: face(left),
+            front: face(front),
+            right: face(right),
+            back: face(back),
+            up: face(up),
+            down: face(down),
+        })
+    }
+
+    /// Returns the color seen in direction.
+    pub fn radiance(&self, direction: Vector) -> Color {
+        let (x, y, z) = (direction.x(), direction.y(), direction.z());
+        let largest = x.abs().max(y.abs()).max(z.abs());
+        if largest <= 0.0 {
+            return Color::new(0, 0, 0);
+        }
+        // The point where direction leaves the cube from -1 to 1.
+        let point = Point::new(x / largest, y / largest, z / largest);
+        self.faces.color_at(point) * self.strength
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(color: Color) -> Canvas {
+        let mut canvas = Canvas::new(4, 4);
+        canvas.set_all_pixel(color);
+        canvas
+    }
+
+    fn skybox() -> Skybox {
+        Skybox::from_images(
+            solid(Color::new(1, 0, 0)),
+            solid(Color::new(0, 1, 0)),
+            solid(Color::new(0, 0, 1)),
+            solid(Color::new(1, 1, 0)),
+            solid(Color::new(1, 1, 1)),
+            solid(Color::new(0, 0, 0)),
+        )
+    }
+
+    /// Each direction sees the face of the cube it points to.
+    #[test]
+    fn skybox_faces() {
+        let sky = skybox();
+        for (direction, expected) in [
+            (Vector::new(-1, 0.2, 0.3), Color::new(1, 0, 0)),
+            (Vector::new(0.1, -0.4, 2), Color::new(0, 1, 0)),
+            (Vector::new(5, 1, -1), Color::new(0, 0, 1)),
+            (Vector::new(0.5, 0.5, -0.9), Color::new(1, 1, 0)),
+            (Vector::new(0, 1, 0), Color::new(1, 1, 1)),
+            (Vector::new(0.3, -1, 0.3), Color::new(0, 0, 0)),
+        ] {
+            assert_eq!(sky.radiance(direction), expected);
+        }
+    }
+
+    /// The image of a face is seen upright and unmirrored from inside the cube.
+    #[test]
+    fn skybox_orientation() {
+        let mut front = Canvas::new(2, 2);
+        front.write_pixel(1, 0, Color::new(1, 0, 0));
+        let sky = Skybox {
+            strength: 2.0,
+            ..Skybox::from_images(
+                solid(Color::new(0, 0, 0)),
+                front,
+                solid(Color::new(0, 0, 0)),
+                solid(Color::new(0, 0, 0)),
+                solid(Color::new(0, 0, 0)),
+                solid(Color::new(0, 0, 0)),
+            )
+        };
+        // Looking ahead, up and to the right.
+        assert_eq!(sky.radiance(Vector::new(0.6, 0.6, 1)), Color::new(2, 0, 0));
+        assert_eq!(sky.radiance(Vector::new(-0.6, 0.6, 1)), Color::new(0, 0, 0));
+        assert_eq!(sky.radiance(Vector::new(0.6, -0.6, 1)), Color::new(0, 0, 0));
+    }
+}
@@ -0,0 +1,113 @@
+use crate::ray::Ray;
+use crate::shape::{Intersection, Shape};
+use crate::tuple::{Point, Vector};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Sphere {
+    pub center: Point,
+    pub radius: f64,
+}
+
+impl Sphere {
+    /// Return a new Sphere.
+    pub fn new(center: Point, radius: f64) -> Self {
+        Sphere { center, radius }
+    }
+}
+
+/// The unit sphere at the origin.
+impl Default for Sphere {
+    fn default() -> Self {
+        Sphere::new(Point::new(0, 0, 0), 1.0)
+    }
+}
+
+impl Shape for Sphere {
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let sphere_to_ray = ray.origin - self.center;
+        let a = ray.direction.dot(&ray.direction);
+        let b = 2.0 * ray.direction.dot(&sphere_to_ray);
+        let c = sphere_to_ray.dot(&sphere_to_ray) - self.radius * self.radius;
+        let discriminant = b * b - 4.0 * a * c;
+
+        if discriminant < 0.0 {
+            return vec![];
+        }
+
+        let t1 = (-b - discriminant.sqrt()) / (2.0 * a);
+        let t2 = (-b + discriminant.sqrt()) / (2.0 * a);
+        vec![Intersection::new(t1, self), Intersection::new(t2, self)]
+    }
+
+    fn local_normal_at(&self, point: Point, _hit: &Intersection) -> Vector {
+        (point - self.center).normalize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ts(xs: &[Intersection]) -> Vec<f64> {
+        xs.iter().map(|x| x.t).collect()
+    }
+
+    /// A ray intersects a sphere at two points.
+    #[test]
+    fn ray_intersects_sphere() {
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        let s = Sphere::default();
+        assert_eq!(ts(&s.local_intersect(&r)), vec![4.0, 6.0]);
+    }
+
+    /// A ray intersects a sphere at a tangent.
+    #[test]
+    fn ray_tangent_sphere() {
+        let r = Ray::new(Point::new(0, 1, -5), Vector::new(0, 0, 1));
+        let s = Sphere::default();
+        assert_eq!(ts(&s.local_intersect(&r)), vec![5.0, 5.0]);
+    }
+
+    /// A ray misses a sphere.
+    #[test]
+    fn ray_misses_sphere() {
+        let r = Ray::new(Point::new(0, 2, -5), Vector::new(0, 0, 1));
+        let s = Sphere::default();
+        assert!(s.local_intersect(&r).is_empty());
+    }
+
+    /// A ray originates inside a sphere.
+    #[test]
+    fn ray_inside_sphere() {
+        let r = Ray::new(Point::new(0, 0, 0), Vector::new(0, 0, 1));
+        let s = Sphere::default();
+        assert_eq!(ts(&s.local_intersect(&r)), vec![-1.0, 1.0]);
+    }
+
+    /// Intersecting a sphere which is not at the origin.
+    #[test]
+    fn ray_intersects_moved_sphere() {
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        let s = Sphere::new(Point::new(0, 0, 2), 2.0);
+        assert_eq!(ts(&s.local_intersect(&r)), vec![5.0, 9.0]);
+    }
+
+    /// The normal on a sphere at a nonaxial point.
+    #[test]
+    fn normal_nonaxial() {
+        let s = Sphere::default();
+        let k = 3f64.sqrt() / 3.0;
+        let p = Point::new(k, k, k);
+        let n = s.local_normal_at(p, &Intersection::new(0.0, &s));
+        assert_eq!(n, Vector::new(k, k, k));
+        assert_eq!(n, n.normalize());
+    }
+
+    /// The normal on a sphere which is not at the origin.
+    #[test]
+    fn normal_moved_sphere() {
+        let s = Sphere::new(Point::new(0, 1, 0), 2.0);
+        let n = s.local_normal_at(Point::new(0, 3, 0), &Intersection::new(0.0, &s));
+        assert_eq!(n, Vector::new(0, 1, 0));
+    }
+}
@@ -0,0 +1,141 @@
+use crate::color::Color;
+use crate::mesh::TriangleMesh;
+use crate::tuple::Point;
+use std::io::{Error, ErrorKind, Read};
+
+const HEADER_SIZE: usize = 80;
+const FACET_SIZE: usize = 50;
+
+/// Parse a binary STL file into a TriangleMesh.
+///
+/// Every facet gets its own three vertices. Facet colors stored in the attribute
+/// bytes (VisCAM/SolidView convention: bit 15 set, 5 bits per channel) are carried
+/// over to the vertices of the facet; facets without a valid color are left white.
+pub fn parse_stl(mut reader: impl Read) -> std::io::Result<TriangleMesh> {
+    let mut data = Vec::<u8>::new();
+    reader.read_to_end(&mut data)?;
+
+    if data.len() < HEADER_SIZE + 4 {
+        return Err(invalid_data("STL file is too short"));
+    }
+    let count = read_u32(&data, HEADER_SIZE) as usize;
+    if data.len() != HEADER_SIZE + 4 + count * FACET_SIZE {
+        return Err(invalid_data(
+            "STL size does not match the facet count (ASCII STL is not supported)",
+        ));
+    }
+
+    let mut vertices = Vec::with_capacity(count * 3);
+    let mut faces = Vec::with_capacity(count);
+    let mut colors = Vec::with_capacity(count * 3);
+    let mut has_colors = false;
+
+    for facet in data[HEADER_SIZE + 4..].chunks_exact(FACET_SIZE) {
+        // The first 12 bytes hold the facet normal, which is recomputed from the winding order.
+        for corner in 0..3 {
+            let offset = 12 + corner * 12;
+            vertices.push(Point::new(
+                read_f32(facet, offset),
+                read_f32(facet, offset + 4),
+                read_f32(facet, offset + 8),
+            ));
+        }
+        let first = vertices.len() - 3;
+        faces.push([first, first + 1, first + 2]);
+
+        let attribute = u16::from_le_bytes([facet[48], facet[49]]);
+        let color = facet_color(attribute);
+        has_colors |= color.is_some();
+        colors.extend([color.unwrap_or_else(|| Color::new(1, 1, 1)); 3]);
+    }
+
+    Ok(TriangleMesh {
+        vertices,
+        colors: if has_colors { Some(colors) } else { None },
+        normals: None,
+        uvs: None,
+        tangents: None,
+        faces,
+    })
+}
+
+/// Decode the 15-bit facet color from the attribute bytes, if its valid bit is set.
+fn facet_color(attribute: u16) -> Option<Color> {
+    if attribute & 0x8000 == 0 {
+        return None;
+    }
+    let channel = |shift: u16| ((attribute >> shift) & 0x1f) as f64 / 31.0;
+    Some(Color::new(channel(10), channel(5), channel(0)))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_f32(data: &[u8], offset: usize) -> f32 {
+    f32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn invalid_data(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stl_bytes(facets: &[([[f32; 3]; 3], u16)]) -> Vec<u8> {
+        let mut buf = vec![0u8; HEADER_SIZE];
+        buf.extend_from_slice(&(facets.len() as u32).to_le_bytes());
+        for (corners, attribute) in facets {
+            buf.extend_from_slice(&[0u8; 12]);
+            for corner in corners {
+                for coordinate in corner {
+                    buf.extend_from_slice(&coordinate.to_le_bytes());
+                }
+            }
+            buf.extend_from_slice(&attribute.to_le_bytes());
+        }
+        buf
+    }
+
+    /// Parsing the facets of a binary STL file.
+    #[test]
+    fn parse_binary_stl() {
+        let data = stl_bytes(&[
+            ([[0.0, 1.0, 0.0], [-1.0, 0.0, 0.0], [1.0, 0.0, 0.0]], 0),
+            ([[0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]], 0),
+        ]);
+        let mesh = parse_stl(data.as_slice()).unwrap();
+        assert_eq!(mesh.faces, vec![[0, 1, 2], [3, 4, 5]]);
+        assert_eq!(mesh.vertices[1], Point::new(-1, 0, 0));
+        assert_eq!(mesh.vertices[3], Point::new(0, 0, 1));
+        assert!(mesh.colors.is_none());
+    }
+
+    /// Facet colors become vertex colors.
+    #[test]
+    fn parse_stl_facet_colors() {
+        let red = 0x8000 | (31 << 10);
+        let data = stl_bytes(&[
+            ([[0.0, 1.0, 0.0], [-1.0, 0.0, 0.0], [1.0, 0.0, 0.0]], red),
+            ([[0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]], 0),
+        ]);
+        let mesh = parse_stl(data.as_slice()).unwrap();
+        let colors = mesh.colors.unwrap();
+        assert_eq!(colors[2], Color::new(1, 0, 0));
+        assert_eq!(colors[3], Color::new(1, 1, 1));
+    }
+
+    /// A truncated or ASCII STL file is rejected.
+    #[test]
+    fn parse_stl_size_mismatch() {
+        let mut data = stl_bytes(&[([[0.0; 3]; 3], 0)]);
+        data.truncate(data.len() - 1);
+        let err = parse_stl(data.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        let ascii = b"solid cube\nfacet normal 0 0 1\nendsolid cube\n".repeat(3);
+        assert!(parse_stl(ascii.as_slice()).is_err());
+    }
+}
@@ -0,0 +1,214 @@
+use crate::color::Color;
+use crate::instance::Instance;
+use crate::light::ShadingPoint;
+use crate::medium::HenyeyGreenstein;
+use crate::microfacet::fresnel_schlick;
+use crate::random::{cosine_hemisphere, RandomSource};
+use crate::ray::Ray;
+use crate::world::SHADOW_BIAS;
+
+/// Scattering steps after which a walk is given up on, as light this deep has all but
+/// faded away.
+const MAX_STEPS: usize = 256;
+
+/// Light scattering beneath the surface of translucent materials, such as wax, marble,
+/// milk or skin, before leaving it again some distance away.
+///
+/// The path tracer follows light entering the surface on a random walk through the
+/// inside of the object, as if it were filled with a medium, until it finds its way
+/// out. Red light travels further than blue in skin, which is what radius per channel
+/// is for. Part of the light is reflected off the surface before it can enter, the
+/// more at grazing angles, which gives wax and skin their sheen.
+///
+/// Only the path tracer follows light beneath the surface: `World::shade_hit` lights
+/// such materials like any other.
+#[derive(Debug, Clone, Copy)]
+pub struct Subsurface {
+    /// Share of the light kept at each scattering event inside the object, per channel.
+    /// Many events happen before light leaves, so the surface looks darker than this.
+    pub color: Color,
+    /// Average distance light travels between scattering events, per channel.
+    pub radius: Color,
+    pub phase: HenyeyGreenstein,
+    /// Index of refraction of the surface, which sets how much light it reflects.
+    pub ior: f64,
+}
+
+/// Where a random walk leaves the object, and what is left of the light.
+#[derive(Debug, Clone, Copy)]
+pub struct SubsurfaceExit {
+    /// Shading geometry outside the object, with the normal pointing out of it.
+    pub shading: ShadingPoint,
+    /// Factor by which the light leaving here is scaled when it reaches the entry point.
+    pub weight: Color,
+}
+
+impl Subsurface {
+    /// Return a new Subsurface scattering the same in every direction.
+    pub fn new(color: Color, radius: Color) -> Self {
+        Subsurface {
+            color,
+            radius,
+            phase: HenyeyGreenstein::new(0.0),
+            ior: 1.4,
+        }
+    }
+
+    /// Share of the light reflected off the surface, rather than entering it, for a
+    /// viewer at cos_theta to the normal.
+    pub fn reflectance(&self, cos_theta: f64) -> f64 {
+        let f0 = ((self.ior - 1.0) / (self.ior + 1.0)).powi(2);
+        fresnel_schlick(Color::new(f0, f0, f0), cos_theta).red()
+    }
+
+    /// Follow light entering instance at shading, the hit of a ray from outside, through
+    /// its inside. Returns where it leaves the object, or None when it is lost.
+    pub fn walk(
+        &self,
+        instance: &Instance,
+        shading: &ShadingPoint,
+        rng: &mut dyn RandomSource,
+    ) -> Option<SubsurfaceExit> {
+        let extinction = [self.radius.red(), self.radius.green(), self.radius.blue()]
+            .map(|radius| 1.0 / radius.max(1e-9));
+        let albedo = [self.color.red(), self.color.green(), self.color.blue()];
+
+        let mut point = shading.point - shading.normalv * (2.0 * SHADOW_BIAS);
+        let mut direction = cosine_hemisphere(-shading.normalv, rng);
+        let mut weight = [1.0; 3];
+        for _ in 0..MAX_STEPS {
+            // Distances are sampled for one channel picked at random, and weighed by the
+            // average density of the three so that every channel stays unbiased.
+            let channel = ((rng.next_f64() * 3.0) as usize).min(2);
+            let distance = -(1.0 - rng.next_f64()).ln() / extinction[channel];
+
+            let ray = Ray::new(point, direction);
+            let exit = instance
+                .intersect(&ray)
+                .into_iter()
+                .filter(|x| x.t > 0.0)
+                .min_by(|a, b| a.t.total_cmp(&b.t));
+            let Some(exit) = exit else {
+                // Not inside a closed shape after all.
+                return None;
+            };
+
+            if exit.t <= distance {
+                let survival = extinction.map(|sigma| (-sigma * exit.t).exp());
+                let probability = survival.iter().sum::<f64>() / 3.0;
+                for (w, s) in weight.iter_mut().zip(survival) {
+                    *w *= s / probability;
+                }
+                let exit_point = ray.position(exit.t);
+                let mut normal = instance.normal_at(exit_point, &exit);
+                if normal.dot(&direction) < 0.0 {
+                    normal = -normal;
+                }
+                return Some(SubsurfaceExit {
+                    shading: ShadingPoint {
+                        point: exit_point + normal * SHADOW_BIAS,
+                        eyev: normal,
+                        normalv: normal,
+                    },
+                    weight: Color::new(weight[0], weight[1], weight[2]),
+                });
+            }
+
+            let density = extinction.map(|sigma| sigma * (-sigma * distance).exp());
+            let pdf = density.iter().sum::<f64>() / 3.0;
+            for ((w, d), a) in weight.iter_mut().zip(density).zip(albedo) {
+                *w *= a * d / pdf;
+            }
+            if weight.iter().all(|&w| w <= 0.0) {
+                return None;
+            }
+            point = ray.position(distance);
+            direction = self.phase.sample(direction, rng);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+    use crate::matrix::Matrix;
+    use crate::random::XorShift;
+    use crate::sphere::Sphere;
+    use crate::tuple::{Point, Vector};
+    use std::rc::Rc;
+
+    fn distance(a: Point, b: Point) -> f64 {
+        (a - b).magnitude()
+    }
+
+    fn entry() -> ShadingPoint {
+        ShadingPoint {
+            point: Point::new(0, 0, -1.0 - SHADOW_BIAS),
+            eyev: Vector::new(0, 0, -1),
+            normalv: Vector::new(0, 0, -1),
+        }
+    }
+
+    /// Walks leave the object on its surface, and without absorption no light is lost.
+    #[test]
+    fn walk_exits_surface() {
+        let sphere = Instance::new(
+            Rc::new(Sphere::default()),
+            Matrix::identity(),
+            Material::default(),
+        );
+        let wax = Subsurface::new(Color::new(1, 1, 1), Color::new(0.3, 0.3, 0.3));
+        let mut rng = XorShift::new(2);
+        for _ in 0..200 {
+            let exit = wax.walk(&sphere, &entry(), &mut rng).unwrap();
+            let p = exit.shading.point;
+            assert!((distance(p, Point::new(0, 0, 0)) - 1.0).abs() < 1e-3);
+            assert!(exit.shading.normalv.dot(&(p - Point::new(0, 0, 0))) > 0.0);
+            assert_eq!(exit.weight, Color::new(1, 1, 1));
+        }
+    }
+
+    /// Light with a longer radius travels further beneath the surface, and absorbing
+    /// channels lose more light.
+    #[test]
+    fn walk_per_channel() {
+        let sphere = Instance::new(
+            Rc::new(Sphere::default()),
+            Matrix::scaling(10, 10, 10),
+            Material::default(),
+        );
+        let entry = ShadingPoint {
+            point: Point::new(0, 0, -10.0 - SHADOW_BIAS),
+            ..entry()
+        };
+        let skin = Subsurface::new(Color::new(0.95, 0.95, 0.95), Color::new(1, 0.1, 0.1));
+        let mut rng = XorShift::new(6);
+        let n = 2000;
+        let mut total = Color::new(0, 0, 0);
+        let mut red_distance = 0.0;
+        let mut blue_distance = 0.0;
+        for _ in 0..n {
+            if let Some(exit) = skin.walk(&sphere, &entry, &mut rng) {
+                let d = distance(exit.shading.point, entry.point);
+                total = total + exit.weight;
+                red_distance += d * exit.weight.red();
+                blue_distance += d * exit.weight.blue();
+            }
+        }
+        assert!(red_distance / total.red() > 2.0 * blue_distance / total.blue());
+        assert!(total.red() < n as f64);
+        assert!(total.blue() < n as f64);
+    }
+
+    /// The surface reflects a few percent of the light head on, and all of it at
+    /// grazing angles.
+    #[test]
+    fn reflectance_by_angle() {
+        let wax = Subsurface::new(Color::new(1, 1, 1), Color::new(0.3, 0.3, 0.3));
+        assert!((wax.reflectance(1.0) - 0.4f64.powi(2) / 2.4f64.powi(2)).abs() < 1e-9);
+        assert!(wax.reflectance(0.5) > wax.reflectance(1.0));
+        assert!((wax.reflectance(0.0) - 1.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,152 @@
+use crate::polynomial::real_roots;
+use crate::ray::Ray;
+use crate::shape::{Intersection, Shape};
+use crate::tuple::{Point, Vector};
+
+/// Torus centered at the origin and lying in the xz plane, around the y axis.
+/// major_radius is the distance from the center to the middle of the tube,
+/// minor_radius the radius of the tube.
+#[derive(Debug, Clone, Copy)]
+pub struct Torus {
+    pub major_radius: f64,
+    pub minor_radius: f64,
+}
+
+impl Torus {
+    /// Return a new Torus.
+    pub fn new(major_radius: f64, minor_radius: f64) -> Self {
+        Torus {
+            major_radius,
+            minor_radius,
+        }
+    }
+}
+
+impl Shape for Torus {
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        // Solve in units of distance along a unit direction, which keeps the quartic well
+        // conditioned, then scale the roots back to the parameter of the original ray.
+        let length = ray.direction.magnitude();
+        let d = ray.direction / length;
+        let o = ray.origin - Point::new(0, 0, 0);
+
+        // Only search the span of the ray inside the bounding sphere of the torus.
+        let bound = self.major_radius + self.minor_radius;
+        let od = o.dot(&d);
+        let oo = o.dot(&o);
+        let discriminant = od * od - (oo - bound * bound);
+        if discriminant < 0.0 {
+            return vec![];
+        }
+        let (near, far) = (-od - discriminant.sqrt(), -od + discriminant.sqrt());
+
+        // (|p|^2 + R^2 - r^2)^2 = 4 R^2 (px^2 + pz^2), with p = o + s * d and |d| = 1.
+        let r2 = self.major_radius * self.major_radius;
+        let k = oo + r2 - self.minor_radius * self.minor_radius;
+        let dxz = d.x() * d.x() + d.z() * d.z();
+        let odxz = o.x() * d.x() + o.z() * d.z();
+        let oxz = o.x() * o.x() + o.z() * o.z();
+        let coefficients = [
+            k * k - 4.0 * r2 * oxz,
+            4.0 * od * k - 8.0 * r2 * odxz,
+            4.0 * od * od + 2.0 * k - 4.0 * r2 * dxz,
+            4.0 * od,
+            1.0,
+        ];
+
+        // Pad the bounds a little so that grazing hits on the bounding sphere survive.
+        let pad = 1e-6 * bound;
+        real_roots(&coefficients, near - pad, far + pad)
+            .into_iter()
+            .map(|s| Intersection::new(s / length, self))
+            .collect()
+    }
+
+    fn local_normal_at(&self, point: Point, _hit: &Intersection) -> Vector {
+        // Gradient of the implicit surface.
+        let p = point - Point::new(0, 0, 0);
+        let k = p.dot(&p)
+            - self.major_radius * self.major_radius
+            - self.minor_radius * self.minor_radius;
+        Vector::new(
+            p.x() * k,
+            p.y() * (k + 2.0 * self.major_radius * self.major_radius),
+            p.z() * k,
+        )
+        .normalize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approximate_equation::ApproximateEq;
+
+    fn ts(xs: &[Intersection]) -> Vec<f64> {
+        xs.iter().map(|x| x.t).collect()
+    }
+
+    fn assert_ts(xs: &[Intersection], expected: &[f64]) {
+        let actual = ts(xs);
+        assert_eq!(actual.len(), expected.len(), "t: {:?}", actual);
+        for (a, e) in actual.iter().zip(expected) {
+            assert!(a.approx_eq(e), "t: {:?}, expected: {:?}", actual, expected);
+        }
+    }
+
+    /// A ray through the middle of a torus crosses the tube twice.
+    #[test]
+    fn ray_through_torus() {
+        let t = Torus::new(2.0, 0.5);
+        let r = Ray::new(Point::new(-5, 0, 0), Vector::new(1, 0, 0));
+        assert_ts(&t.local_intersect(&r), &[2.5, 3.5, 6.5, 7.5]);
+    }
+
+    /// A ray through the hole of a torus misses it.
+    #[test]
+    fn ray_through_hole() {
+        let t = Torus::new(2.0, 0.5);
+        let r = Ray::new(Point::new(0, 5, 0), Vector::new(0, -1, 0));
+        assert!(t.local_intersect(&r).is_empty());
+    }
+
+    /// A ray hitting the tube from above, with an unnormalized direction.
+    #[test]
+    fn ray_hits_tube() {
+        let t = Torus::new(2.0, 0.5);
+        let r = Ray::new(Point::new(0, 5, -2), Vector::new(0, -2, 0));
+        assert_ts(&t.local_intersect(&r), &[2.25, 2.75]);
+    }
+
+    /// A ray grazing the top of the tube reports both tangent points twice.
+    #[test]
+    fn ray_tangent_torus() {
+        let t = Torus::new(2.0, 0.5);
+        let r = Ray::new(Point::new(-5, 0.5, 0), Vector::new(1, 0, 0));
+        assert_ts(&t.local_intersect(&r), &[3.0, 3.0, 7.0, 7.0]);
+    }
+
+    /// A ray just above the tube misses it.
+    #[test]
+    fn ray_misses_torus() {
+        let t = Torus::new(2.0, 0.5);
+        let r = Ray::new(Point::new(-5, 0.501, 0), Vector::new(1, 0, 0));
+        assert!(t.local_intersect(&r).is_empty());
+    }
+
+    /// The normal on the surface of a torus.
+    #[test]
+    fn normal_torus() {
+        let t = Torus::new(2.0, 0.5);
+        let hit = Intersection::new(0.0, &t);
+        let cases = [
+            (Point::new(2.5, 0, 0), Vector::new(1, 0, 0)),
+            (Point::new(-1.5, 0, 0), Vector::new(1, 0, 0)),
+            (Point::new(0, 0.5, 2), Vector::new(0, 1, 0)),
+            (Point::new(0, -0.5, -2), Vector::new(0, -1, 0)),
+        ];
+        for (point, normal) in cases {
+            assert_eq!(t.local_normal_at(point, &hit), normal);
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use crate::color::Color;
+use crate::ray::Ray;
+use crate::shape::{Intersection, Shape};
+use crate::tuple::{Point, Vector};
+
+/// Triangle with optional per-vertex normals, interpolated for smooth shading, and
+/// optional per-vertex colors, texture coordinates and tangents. The face normal follows
+/// the counter-clockwise winding of p1, p2, p3, like TriangleMesh::face_normal.
+#[derive(Debug, Clone, Copy)]
+pub struct Triangle {
+    pub p1: Point,
+    pub p2: Point,
+    pub p3: Point,
+    pub e1: Vector,
+    pub e2: Vector,
+    pub normal: Vector,
+    pub normals: Option<[Vector; 3]>,
+    pub colors: Option<[Color; 3]>,
+    pub uvs: Option<[(f64, f64); 3]>,
+    /// Tangents of the vertices with the handedness of their texture coordinates, as in
+    /// TriangleMesh.
+    pub tangents: Option<[(Vector, f64); 3]>,
+}
+
+impl Triangle {
+    /// Return a new flat shaded Triangle.
+    pub fn new(p1: Point, p2: Point, p3: Point) -> Self {
+        let e1 = p2 - p1;
+        let e2 = p3 - p1;
+        Triangle {
+            p1,
+            p2,
+            p3,
+            e1,
+            e2,
+            normal: e1.cross(&e2).normalize(),
+            normals: None,
+            colors: None,
+            uvs: None,
+            tangents: None,
+        }
+    }
+
+    /// Return a new Triangle which interpolates the normals of its vertices.
+    pub fn smooth(p1: Point, p2: Point, p3: Point, n1: Vector, n2: Vector, n3: Vector) -> Self {
+        Triangle {
+            normals: Some([n1, n2, n3]),
+            ..Triangle::new(p1, p2, p3)
+        }
+    }
+
+    /// Returns the vertex color interpolated at the barycentric coordinates u, v.
+    pub fn color_at(&self, u: f64, v: f64) -> Option<Color> {
+        let [c1, c2, c3] = self.colors?;
+        Some(c2 * u + c3 * v + c1 * (1.0 - u - v))
+    }
+}
+
+/// Returns the distance and barycentric coordinates u, v at which the ray crosses
+/// the triangle p1, p2, p3 (Möller–Trumbore).
+pub fn intersect_triangle(ray: &Ray, p1: Point, p2: Point, p3: Point) -> Option<(f64, f64, f64)> {
+    let e1 = p2 - p1;
+    let e2 = p3 - p1;
+    let dir_cross_e2 = ray.direction.cross(&e2);
+    let det = e1.dot(&dir_cross_e2);
+    if det.abs() < 1e-12 {
+        return None;
+    }
+
+    let f = 1.0 / det;
+    let p1_to_origin = ray.origin - p1;
+    let u = f * p1_to_origin.dot(&dir_cross_e2);
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let origin_cross_e1 = p1_to_origin.cross(&e1);
+    let v = f * ray.direction.dot(&origin_cross_e1);
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    Some((f * e2.dot(&origin_cross_e1), u, v))
+}
+
+impl Shape for Triangle {
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        match intersect_triangle(ray, self.p1, self.p2, self.p3) {
+            Some((t, u, v)) => vec![Intersection::with_uv(t, self, u, v)],
+            None => vec![],
+        }
+    }
+
+    fn local_normal_at(&self, _point: Point, hit: &Intersection) -> Vector {
+        match self.normals {
+            Some([n1, n2, n3]) => {
+                (n2 * hit.u + n3 * hit.v + n1 * (1.0 - hit.u - hit.v)).normalize()
+            }
+            None => self.normal,
+        }
+    }
+
+    fn local_color_at(&self, _point: Point, hit: &Intersection) -> Option<Color> {
+        self.color_at(hit.u, hit.v)
+    }
+
+    fn local_uv_at(&self, _point: Point, hit: &Intersection) -> Option<(f64, f64)> {
+        let [uv1, uv2, uv3] = self.uvs?;
+        let w = 1.0 - hit.u - hit.v;
+        Some((
+            uv2.0 * hit.u + uv3.0 * hit.v + uv1.0 * w,
+            uv2.1 * hit.u + uv3.1 * hit.v + uv1.1 * w,
+        ))
+    }
+
+    fn local_tangent_at(&self, _point: Point, hit: &Intersection) -> Option<(Vector, f64)> {
+        let [(t1, s1), (t2, s2), (t3, s3)] = self.tangents?;
+        let w = 1.0 - hit.u - hit.v;
+        let sign = s2 * hit.u + s3 * hit.v + s1 * w;
+        Some((
+            t2 * hit.u + t3 * hit.v + t1 * w,
+            if sign < 0.0 { -1.0 } else { 1.0 },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approximate_equation::ApproximateEq;
+
+    fn triangle() -> Triangle {
+        Triangle::new(
+            Point::new(0, 1, 0),
+            Point::new(-1, 0, 0),
+            Point::new(1, 0, 0),
+        )
+    }
+
+    /// Constructing a triangle.
+    #[test]
+    fn construct_triangle() {
+        let t = triangle();
+        assert_eq!(t.e1, Vector::new(-1, -1, 0));
+        assert_eq!(t.e2, Vector::new(1, -1, 0));
+        assert_eq!(t.normal, Vector::new(0, 0, 1));
+    }
+
+    /// Intersecting a ray parallel to the triangle.
+    #[test]
+    fn ray_parallel_triangle() {
+        let t = triangle();
+        let r = Ray::new(Point::new(0, -1, -2), Vector::new(0, 1, 0));
+        assert!(t.local_intersect(&r).is_empty());
+    }
+
+    /// A ray misses the edges of a triangle.
+    #[test]
+    fn ray_misses_edges() {
+        let t = triangle();
+        for origin in [
+            Point::new(1, 1, -2),
+            Point::new(-1, 1, -2),
+            Point::new(0, -1, -2),
+        ] {
+            let r = Ray::new(origin, Vector::new(0, 0, 1));
+            assert!(t.local_intersect(&r).is_empty());
+        }
+    }
+
+    /// A ray strikes a triangle.
+    #[test]
+    fn ray_strikes_triangle() {
+        let t = triangle();
+        let r = Ray::new(Point::new(0, 0.5, -2), Vector::new(0, 0, 1));
+        let xs = t.local_intersect(&r);
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].t, 2.0);
+    }
+
+    /// An intersection with a smooth triangle stores u/v.
+    #[test]
+    fn smooth_triangle_uv() {
+        let t = Triangle::smooth(
+            Point::new(0, 1, 0),
+            Point::new(-1, 0, 0),
+            Point::new(1, 0, 0),
+            Vector::new(0, 1, 0),
+            Vector::new(-1, 0, 0),
+            Vector::new(1, 0, 0),
+        );
+        let r = Ray::new(Point::new(-0.2, 0.3, -2), Vector::new(0, 0, 1));
+        let xs = t.local_intersect(&r);
+        assert!(xs[0].u.approx_eq(&0.45));
+        assert!(xs[0].v.approx_eq(&0.25));
+
+        // A smooth triangle uses u/v to interpolate the normal.
+        let hit = Intersection::with_uv(1.0, &t, 0.45, 0.25);
+        assert_eq!(
+            t.local_normal_at(Point::new(0, 0, 0), &hit),
+            Vector::new(-0.5547, 0.83205, 0)
+        );
+    }
+
+    /// Interpolating the vertex colors of a triangle.
+    #[test]
+    fn triangle_color_at() {
+        let mut t = triangle();
+        assert!(t.color_at(0.5, 0.5).is_none());
+        t.colors = Some([
+            Color::new(1, 0, 0),
+            Color::new(0, 1, 0),
+            Color::new(0, 0, 1),
+        ]);
+        assert_eq!(t.color_at(0.5, 0.25), Some(Color::new(0.25, 0.5, 0.25)));
+    }
+
+    /// Interpolating the texture coordinates and tangents of a triangle.
+    #[test]
+    fn triangle_uv_and_tangent() {
+        let mut t = triangle();
+        let hit = Intersection::with_uv(1.0, &t, 0.5, 0.25);
+        assert!(t.local_uv_at(Point::new(0, 0, 0), &hit).is_none());
+        assert!(t.local_tangent_at(Point::new(0, 0, 0), &hit).is_none());
+
+        t.uvs = Some([(0.5, 1.0), (0.0, 0.0), (1.0, 0.0)]);
+        t.tangents = Some([
+            (Vector::new(1, 0, 0), 1.0),
+            (Vector::new(0, 1, 0), -1.0),
+            (Vector::new(1, 0, 0), -1.0),
+        ]);
+        let hit = Intersection::with_uv(1.0, &t, 0.5, 0.25);
+        let (u, v) = t.local_uv_at(Point::new(0, 0, 0), &hit).unwrap();
+        assert!(u.approx_eq(&0.375) && v.approx_eq(&0.25));
+        assert_eq!(
+            t.local_tangent_at(Point::new(0, 0, 0), &hit),
+            Some((Vector::new(0.5, 0.5, 0), -1.0))
+        );
+    }
+}
@@ -195,6 +195,25 @@ impl Vector {
         let cross = vec3_cross([self.x(), self.y(), self.z()], [rhs.x(), rhs.y(), rhs.z()]);
         Vector::new(cross[0], cross[1], cross[2])
     }
+
+    /// Reflect Vector around normal.
+    pub fn reflect(&self, normal: &Self) -> Self {
+        *self - *normal * (2.0 * self.dot(normal))
+    }
+
+    /// Two unit vectors perpendicular to this unit Vector and to each other, forming a
+    /// right handed frame (tangent, bitangent, self).
+    pub fn orthonormal_basis(&self) -> (Self, Self) {
+        // Duff et al., "Building an Orthonormal Basis, Revisited".
+        let (x, y, z) = (self.x(), self.y(), self.z());
+        let sign = 1f64.copysign(z);
+        let a = -1.0 / (sign + z);
+        let b = x * y * a;
+        (
+            Vector::new(1.0 + sign * x * x * a, sign * b, -sign * x),
+            Vector::new(b, sign + y * y * a, -y),
+        )
+    }
 }
 
 #[cfg(test)]
@@ -386,4 +405,35 @@ mod tests {
         assert_eq!(a.cross(&b), Vector::new(-1, 2, -1));
         assert_eq!(b.cross(&a), Vector::new(1, -2, 1));
     }
+
+    /// Reflecting a vector approaching at 45° and off a slanted surface.
+    #[test]
+    fn reflect_vector() {
+        let v = Vector::new(1, -1, 0);
+        let n = Vector::new(0, 1, 0);
+        assert_eq!(v.reflect(&n), Vector::new(1, 1, 0));
+        let v = Vector::new(0, -1, 0);
+        let k = 2f64.sqrt() / 2.0;
+        let n = Vector::new(k, k, 0);
+        assert_eq!(v.reflect(&n), Vector::new(1, 0, 0));
+    }
+
+    /// Building an orthonormal basis around a vector.
+    #[test]
+    fn orthonormal_basis() {
+        for n in [
+            Vector::new(0, 0, 1),
+            Vector::new(0, 0, -1),
+            Vector::new(1, 2, 3).normalize(),
+            Vector::new(-1, 0.5, -0.2).normalize(),
+        ] {
+            let (t, b) = n.orthonormal_basis();
+            assert!(t.magnitude().approx_eq(&1.0));
+            assert!(b.magnitude().approx_eq(&1.0));
+            assert!(t.dot(&n).approx_eq(&0.0));
+            assert!(b.dot(&n).approx_eq(&0.0));
+            assert!(t.dot(&b).approx_eq(&0.0));
+            assert_eq!(t.cross(&b), n);
+        }
+    }
 }
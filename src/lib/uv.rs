@@ -0,0 +1,497 @@
+use crate::color::Color;
+use crate::pattern::Pattern;
+use crate::tuple::{Point, Vector};
+use std::f64::consts::PI;
+use std::fmt::Debug;
+use std::rc::Rc;
+
+/// Distance between the points at which texture coordinates are compared to find how
+/// fast they change, in object space.
+const EPSILON: f64 = 1e-4;
+
+/// Ways of wrapping a 2D texture around an object, from object space points to u, v
+/// texture coordinates between 0 and 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UvMapping {
+    /// Longitude and latitude on the unit sphere. u goes around the y axis, starting
+    /// and ending at -z, and v from the bottom pole to the top one.
+    Spherical,
+    /// The xz plane, repeating every unit.
+    Planar,
+    /// Around the y axis like Spherical, with v repeating every unit along it.
+    Cylindrical,
+    /// Each face of the cube from -1 to 1 as seen from inside, with v up on the sides.
+    Cube,
+}
+
+impl UvMapping {
+    /// Returns the texture coordinates of an object space point.
+    pub fn map(&self, point: Point) -> (f64, f64) {
+        match self {
+            UvMapping::Spherical => {
+                let radius = (point - Point::new(0, 0, 0)).magnitude();
+                let phi = (point.y() / radius).clamp(-1.0, 1.0).acos();
+                (around_y(point), 1.0 - phi / PI)
+            }
+            UvMapping::Planar => (point.x().rem_euclid(1.0), point.z().rem_euclid(1.0)),
+            UvMapping::Cylindrical => (around_y(point), point.y().rem_euclid(1.0)),
+            UvMapping::Cube => CubeFace::of(point).map(point),
+        }
+    }
+
+    /// Returns about how far the texture coordinates go over a footprint width object
+    /// space units wide around a point.
+    pub fn footprint(&self, point: Point, width: f64) -> f64 {
+        let axes = [
+            Vector::new(EPSILON, 0, 0),
+            Vector::new(0, EPSILON, 0),
+            Vector::new(0, 0, EPSILON),
+        ];
+        let (mut du, mut dv) = (0.0, 0.0);
+        for offset in axes {
+            let (a, b) = (self.map(point + offset), self.map(point - offset));
+            // Across seams, where coordinates wrap around, the change is off by a whole
+            // turn.
+            let slope = |change: f64| (change - change.round()) / (2.0 * EPSILON);
+            du += slope(a.0 - b.0).powi(2);
+            dv += slope(a.1 - b.1).powi(2);
+        }
+        f64::sqrt(du).max(f64::sqrt(dv)) * width
+    }
+}
+
+/// Returns the angle of point around the y axis, from 0 to 1 counterclockwise seen
+/// from above, starting at -z.
+fn around_y(point: Point) -> f64 {
+    let theta = point.x().atan2(point.z());
+    1.0 - (theta / (2.0 * PI) + 0.5)
+}
+
+/// The faces of a cube, named as seen from the front, looking toward +z.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CubeFace {
+    Left,
+    Right,
+    Front,
+    Back,
+    Up,
+    Down,
+}
+
+impl CubeFace {
+    /// Returns the face of the cube from -1 to 1 closest to an object space point.
+    pub fn of(point: Point) -> Self {
+        let (x, y, z) = (point.x(), point.y(), point.z());
+        let coordinate = x.abs().max(y.abs()).max(z.abs());
+        if coordinate == x {
+            CubeFace::Right
+        } else if coordinate == -x {
+            CubeFace::Left
+        } else if coordinate == y {
+            CubeFace::Up
+        } else if coordinate == -y {
+            CubeFace::Down
+        } else if coordinate == z {
+            CubeFace::Front
+        } else {
+            CubeFace::Back
+        }
+    }
+
+    /// Returns the texture coordinates of a point on this face of the cube.
+    pub fn map(&self, point: Point) -> (f64, f64) {
+        let (x, y, z) = (point.x(), point.y(), point.z());
+        let wrap = |a: f64| a.rem_euclid(2.0) / 2.0;
+        match self {
+            CubeFace::Front => (wrap(x + 1.0), wrap(y + 1.0)),
+            CubeFace::Back => (wrap(1.0 - x), wrap(y + 1.0)),
+            CubeFace::Left => (wrap(z + 1.0), wrap(y + 1.0)),
+            CubeFace::Right => (wrap(1.0 - z), wrap(y + 1.0)),
+            CubeFace::Up => (wrap(x + 1.0), wrap(1.0 - z)),
+            CubeFace::Down => (wrap(x + 1.0), wrap(z + 1.0)),
+        }
+    }
+}
+
+/// Color varying over the u, v coordinates of a 2D texture.
+pub trait UvPattern: Debug {
+    /// Returns the color at texture coordinates u, v.
+    fn uv_color_at(&self, u: f64, v: f64) -> Color;
+
+    /// Returns the color around texture coordinates u, v, blurred over a footprint about
+    /// width wide in texture coordinates. Textures which do not blur return the color at
+    /// u, v.
+    fn uv_color_over(&self, u: f64, v: f64, _width: f64) -> Color {
+        self.uv_color_at(u, v)
+    }
+}
+
+/// A 2D texture wrapped around objects by a mapping.
+#[derive(Debug, Clone)]
+pub struct TextureMap {
+    pub pattern: Rc<dyn UvPattern>,
+    pub mapping: UvMapping,
+}
+
+impl TextureMap {
+    /// Return a new TextureMap.
+    pub fn new(pattern: Rc<dyn UvPattern>, mapping: UvMapping) -> Self {
+        TextureMap { pattern, mapping }
+    }
+}
+
+impl Pattern for TextureMap {
+    fn color_at(&self, point: Point) -> Color {
+        let (u, v) = self.mapping.map(point);
+        self.pattern.uv_color_at(u, v)
+    }
+
+    fn color_over(&self, point: Point, width: f64) -> Color {
+        let (u, v) = self.mapping.map(point);
+        let width = self.mapping.footprint(point, width);
+        self.pattern.uv_color_over(u, v, width)
+    }
+}
+
+/// A different 2D texture on each face of a cube.
+#[derive(Debug, Clone)]
+pub struct CubeMap {
+    pub left: Rc<dyn UvPattern>,
+    pub front: Rc<dyn UvPattern>,
+    pub right: Rc<dyn UvPattern>,
+    pub back: Rc<dyn UvPattern>,
+    pub up: Rc<dyn UvPattern>,
+    pub down: Rc<dyn UvPattern>,
+}
+
+impl CubeMap {
+    /// Returns the texture of a face.
+    pub fn face(&self, face: CubeFace) -> &dyn UvPattern {
+        match face {
+            CubeFace::Left => self.left.as_ref(),
+            CubeFace::Front => self.front.as_ref(),
+            CubeFace::Right => self.right.as_ref(),
+            CubeFace::Back => self.back.as_ref(),
+            CubeFace::Up => self.up.as_ref(),
+            CubeFace::Down => self.down.as_ref(),
+        }
+    }
+}
+
+impl Pattern for CubeMap {
+    fn color_at(&self, point: Point) -> Color {
+        let face = CubeFace::of(point);
+        let (u, v) = face.map(point);
+        self.face(face).uv_color_at(u, v)
+    }
+
+    fn color_over(&self, point: Point, width: f64) -> Color {
+        let face = CubeFace::of(point);
+        let (u, v) = face.map(point);
+        let width = UvMapping::Cube.footprint(point, width);
+        self.face(face).uv_color_over(u, v, width)
+    }
+}
+
+/// Checkers of two colors, width squares across u and height squares across v.
+#[derive(Debug, Clone, Copy)]
+pub struct UvCheckers {
+    pub width: f64,
+    pub height: f64,
+    pub a: Color,
+    pub b: Color,
+}
+
+impl UvCheckers {
+    /// Return a new UvCheckers.
+    pub fn new(width: f64, height: f64, a: Color, b: Color) -> Self {
+        UvCheckers {
+            width,
+            height,
+            a,
+            b,
+        }
+    }
+}
+
+impl UvPattern for UvCheckers {
+    fn uv_color_at(&self, u: f64, v: f64) -> Color {
+        let (u2, v2) = ((u * self.width).floor(), (v * self.height).floor());
+        if (u2 + v2).rem_euclid(2.0) == 0.0 {
+            self.a
+        } else {
+            self.b
+        }
+    }
+}
+
+/// Test pattern with a different color in each corner, for checking that textures are
+/// neither flipped nor turned.
+#[derive(Debug, Clone, Copy)]
+pub struct UvAlignCheck {
+    pub main: Color,
+    pub upper_left: Color,
+    pub upper_right: Color,
+    pub bottom_left: Color,
+    pub bottom_right: Color,
+}
+
+impl UvAlignCheck {
+    /// Return a new UvAlignCheck.
+    pub fn new(
+        main: Color,
+        upper_left: Color,
+        upper_right: Color,
+        bottom_left: Color,
+        bottom_right: Color,
+    ) -> Self {
+        UvAlignCheck {
+            main,
+            upper_left,
+            upper_right,
+            bottom_left,
+            bottom_right,
+        }
+    }
+}
+
+impl UvPattern for UvAlignCheck {
+    fn uv_color_at(&self, u: f64, v: f64) -> Color {
+        if v > 0.8 {
+            if u < 0.2 {
+                return self.upper_left;
+            }
+            if u > 0.8 {
+                return self.upper_right;
+            }
+        } else if v < 0.2 {
+            if u < 0.2 {
+                return self.bottom_left;
+            }
+            if u > 0.8 {
+                return self.bottom_right;
+            }
+        }
+        self.main
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approximate_equation::ApproximateEq;
+
+    fn assert_uv(actual: (f64, f64), expected: (f64, f64)) {
+        assert!(
+            actual.0.approx_eq(&expected.0) && actual.1.approx_eq(&expected.1),
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    fn black() -> Color {
+        Color::new(0, 0, 0)
+    }
+
+    fn white() -> Color {
+        Color::new(1, 1, 1)
+    }
+
+    /// Checker pattern in 2D.
+    #[test]
+    fn uv_checkers() {
+        let checkers = UvCheckers::new(2.0, 2.0, black(), white());
+        for (u, v, expected) in [
+            (0.0, 0.0, black()),
+            (0.5, 0.0, white()),
+            (0.0, 0.5, white()),
+            (0.5, 0.5, black()),
+            (1.0, 1.0, black()),
+        ] {
+            assert_eq!(checkers.uv_color_at(u, v), expected);
+        }
+    }
+
+    /// Using a spherical mapping on a 3D point.
+    #[test]
+    fn spherical_mapping() {
+        let k = 2f64.sqrt() / 2.0;
+        for (point, uv) in [
+            (Point::new(0, 0, -1), (0.0, 0.5)),
+            (Point::new(1, 0, 0), (0.25, 0.5)),
+            (Point::new(0, 0, 1), (0.5, 0.5)),
+            (Point::new(-1, 0, 0), (0.75, 0.5)),
+            (Point::new(0, 1, 0), (0.5, 1.0)),
+            (Point::new(0, -1, 0), (0.5, 0.0)),
+            (Point::new(k, k, 0), (0.25, 0.75)),
+        ] {
+            assert_uv(UvMapping::Spherical.map(point), uv);
+        }
+    }
+
+    /// Using a texture map pattern with a spherical map.
+    #[test]
+    fn spherical_texture_map() {
+        let checkers = UvCheckers::new(16.0, 8.0, black(), white());
+        let pattern = TextureMap::new(Rc::new(checkers), UvMapping::Spherical);
+        for (point, expected) in [
+            (Point::new(0.4315, 0.4670, 0.7719), white()),
+            (Point::new(-0.9654, 0.2552, -0.0534), black()),
+            (Point::new(0.1039, 0.7090, 0.6975), white()),
+            (Point::new(-0.4986, -0.7856, -0.3663), black()),
+            (Point::new(-0.0317, -0.9395, 0.3411), black()),
+            (Point::new(0.4809, -0.7721, 0.4154), black()),
+            (Point::new(0.0285, -0.9612, -0.2745), black()),
+            (Point::new(-0.5734, -0.2162, -0.7903), white()),
+            (Point::new(0.7688, -0.1470, 0.6223), black()),
+            (Point::new(-0.7652, 0.2175, 0.6060), black()),
+        ] {
+            assert_eq!(pattern.color_at(point), expected);
+        }
+    }
+
+    /// Using a planar mapping on a 3D point.
+    #[test]
+    fn planar_mapping() {
+        for (point, uv) in [
+            (Point::new(0.25, 0, 0.5), (0.25, 0.5)),
+            (Point::new(0.25, 0, -0.25), (0.25, 0.75)),
+            (Point::new(0.25, 0.5, -0.25), (0.25, 0.75)),
+            (Point::new(1.25, 0, 0.5), (0.25, 0.5)),
+            (Point::new(0.25, 0, -1.75), (0.25, 0.25)),
+            (Point::new(1, 0, -1), (0.0, 0.0)),
+            (Point::new(0, 0, 0), (0.0, 0.0)),
+        ] {
+            assert_uv(UvMapping::Planar.map(point), uv);
+        }
+    }
+
+    /// Using a cylindrical mapping on a 3D point.
+    #[test]
+    fn cylindrical_mapping() {
+        let k = 2f64.sqrt() / 2.0;
+        for (point, uv) in [
+            (Point::new(0, 0, -1), (0.0, 0.0)),
+            (Point::new(0, 0.5, -1), (0.0, 0.5)),
+            (Point::new(0, 1, -1), (0.0, 0.0)),
+            (Point::new(k, 0.5, -k), (0.125, 0.5)),
+            (Point::new(1, 0.5, 0), (0.25, 0.5)),
+            (Point::new(k, 0.5, k), (0.375, 0.5)),
+            (Point::new(0, -0.25, 1), (0.5, 0.75)),
+            (Point::new(-k, 0.5, k), (0.625, 0.5)),
+            (Point::new(-1, 1.25, 0), (0.75, 0.25)),
+            (Point::new(-k, 0.5, -k), (0.875, 0.5)),
+        ] {
+            assert_uv(UvMapping::Cylindrical.map(point), uv);
+        }
+    }
+
+    /// Footprints grow with how fast the coordinates change, even across seams.
+    #[test]
+    fn mapping_footprint() {
+        let planar = UvMapping::Planar;
+        assert!((planar.footprint(Point::new(0.3, 0, 0.4), 0.5) - 0.5).abs() < 1e-6);
+        assert!((planar.footprint(Point::new(0, 0, 0.4), 0.5) - 0.5).abs() < 1e-6);
+        // v goes from pole to pole over half of the circumference, and u all around.
+        let spherical = UvMapping::Spherical.footprint(Point::new(2, 0, 0), 1.0);
+        assert!((spherical - 1.0 / (2.0 * PI)).abs() < 1e-6);
+    }
+
+    /// Layout of the "align check" pattern.
+    #[test]
+    fn align_check() {
+        let pattern = UvAlignCheck::new(
+            white(),
+            Color::new(1, 0, 0),
+            Color::new(1, 1, 0),
+            Color::new(0, 1, 0),
+            Color::new(0, 1, 1),
+        );
+        for (u, v, expected) in [
+            (0.5, 0.5, white()),
+            (0.1, 0.9, Color::new(1, 0, 0)),
+            (0.9, 0.9, Color::new(1, 1, 0)),
+            (0.1, 0.1, Color::new(0, 1, 0)),
+            (0.9, 0.1, Color::new(0, 1, 1)),
+        ] {
+            assert_eq!(pattern.uv_color_at(u, v), expected);
+        }
+    }
+
+    /// Identifying the face of a cube from a point, and the u, v of points on faces.
+    #[test]
+    fn cube_faces() {
+        for (point, face) in [
+            (Point::new(-1, 0.5, -0.25), CubeFace::Left),
+            (Point::new(1.1, -0.75, 0.8), CubeFace::Right),
+            (Point::new(0.1, 0.6, 0.9), CubeFace::Front),
+            (Point::new(-0.7, 0, -2), CubeFace::Back),
+            (Point::new(0.5, 1, 0.9), CubeFace::Up),
+            (Point::new(-0.2, -1.3, 1.1), CubeFace::Down),
+        ] {
+            assert_eq!(CubeFace::of(point), face);
+        }
+        for (face, point, uv) in [
+            (CubeFace::Front, Point::new(-0.5, 0.5, 1), (0.25, 0.75)),
+            (CubeFace::Front, Point::new(0.5, -0.5, 1), (0.75, 0.25)),
+            (CubeFace::Back, Point::new(0.5, 0.5, -1), (0.25, 0.75)),
+            (CubeFace::Back, Point::new(-0.5, -0.5, -1), (0.75, 0.25)),
+            (CubeFace::Left, Point::new(-1, 0.5, -0.5), (0.25, 0.75)),
+            (CubeFace::Left, Point::new(-1, -0.5, 0.5), (0.75, 0.25)),
+            (CubeFace::Right, Point::new(1, 0.5, 0.5), (0.25, 0.75)),
+            (CubeFace::Right, Point::new(1, -0.5, -0.5), (0.75, 0.25)),
+            (CubeFace::Up, Point::new(-0.5, 1, -0.5), (0.25, 0.75)),
+            (CubeFace::Up, Point::new(0.5, 1, 0.5), (0.75, 0.25)),
+            (CubeFace::Down, Point::new(-0.5, -1, 0.5), (0.25, 0.75)),
+            (CubeFace::Down, Point::new(0.5, -1, -0.5), (0.75, 0.25)),
+        ] {
+            assert_uv(face.map(point), uv);
+        }
+    }
+
+    /// Finding the colors on a mapped cube.
+    #[test]
+    fn cube_map() {
+        let (red, yellow, brown) = (
+            Color::new(1, 0, 0),
+            Color::new(1, 1, 0),
+            Color::new(1, 0.5, 0),
+        );
+        let (green, cyan, blue) = (
+            Color::new(0, 1, 0),
+            Color::new(0, 1, 1),
+            Color::new(0, 0, 1),
+        );
+        let (purple, white) = (Color::new(1, 0, 1), white());
+        let face = |main, ul, ur, bl, br| -> Rc<dyn UvPattern> {
+            Rc::new(UvAlignCheck::new(main, ul, ur, bl, br))
+        };
+        let cube = CubeMap {
+            left: face(yellow, cyan, red, blue, brown),
+            front: face(cyan, red, yellow, brown, green),
+            right: face(red, yellow, purple, green, white),
+            back: face(green, purple, cyan, white, blue),
+            up: face(brown, cyan, purple, red, yellow),
+            down: face(purple, brown, green, blue, white),
+        };
+        for (point, expected) in [
+            (Point::new(-1, 0, 0), yellow),
+            (Point::new(-1, 0.9, -0.9), cyan),
+            (Point::new(-1, 0.9, 0.9), red),
+            (Point::new(-1, -0.9, -0.9), blue),
+            (Point::new(-1, -0.9, 0.9), brown),
+            (Point::new(0, 0, 1), cyan),
+            (Point::new(-0.9, 0.9, 1), red),
+            (Point::new(0.9, -0.9, 1), green),
+            (Point::new(1, 0, 0), red),
+            (Point::new(1, 0.9, 0.9), yellow),
+            (Point::new(0, 0, -1), green),
+            (Point::new(0.9, 0.9, -1), purple),
+            (Point::new(0, 1, 0), brown),
+            (Point::new(0.9, 1, 0.9), yellow),
+            (Point::new(0, -1, 0), purple),
+            (Point::new(-0.9, -1, 0.9), brown),
+        ] {
+            assert_eq!(cube.color_at(point), expected);
+        }
+    }
+}
@@ -0,0 +1,657 @@
+use crate::color::Color;
+use crate::environment::EnvironmentMap;
+use crate::instance::Instance;
+use crate::light::{lighting, Light, LightSample, ShadingPoint};
+use crate::material::Material;
+use crate::matrix::Matrix;
+use crate::medium::{Medium, Volume};
+use crate::mesh::TriangleMesh;
+use crate::mesh_light::MeshLight;
+use crate::random::RandomSource;
+use crate::ray::Ray;
+use crate::shape::Intersection;
+use crate::skybox::Skybox;
+use crate::tuple::Point;
+use std::rc::Rc;
+
+/// Distance by which points are moved off surfaces before casting rays from them, so
+/// that rays do not hit the surface they start from.
+pub const SHADOW_BIAS: f64 = 1e-4;
+
+/// Objects and lights making up a scene, and what surrounds it.
+pub struct World {
+    pub objects: Vec<Instance>,
+    pub lights: Vec<Box<dyn Light>>,
+    /// Seen by rays escaping the scene and lighting it, when set. Rays escaping the
+    /// scene see black otherwise.
+    pub environment: Option<EnvironmentMap>,
+    /// Seen in place of the environment by rays escaping the scene, directly or through
+    /// mirrors, when set. It does not light the scene: set environment for that.
+    pub skybox: Option<Skybox>,
+    /// Medium filling the space between surfaces around the origin, when set.
+    pub fog: Option<Fog>,
+    /// Media bounded by shapes.
+    pub volumes: Vec<Volume>,
+}
+
+/// A medium filling a sphere around the origin of a world. Beyond it the scene stands
+/// in clear sky: rays escaping the scene, and the light of directional lights and of
+/// the environment, cross fog up to the radius.
+pub struct Fog {
+    pub medium: Medium,
+    pub radius: f64,
+}
+
+impl Fog {
+    /// Return a new Fog of medium, reaching radius from the origin. The radius should
+    /// take in the whole scene.
+    pub fn new(medium: Medium, radius: f64) -> Self {
+        Fog { medium, radius }
+    }
+}
+
+/// An intersection with one of the objects of a world.
+#[derive(Clone, Copy)]
+pub struct Hit<'a> {
+    pub instance: &'a Instance,
+    pub intersection: Intersection<'a>,
+}
+
+impl Hit<'_> {
+    /// Returns the shading geometry of the hit of ray. The point is moved off the surface
+    /// by SHADOW_BIAS on the side of the eye, and the normal is flipped to face the eye.
+    /// A bumpy material tilts the normal, but not the direction the point is moved in.
+    pub fn shading_point(&self, ray: &Ray) -> ShadingPoint {
+        let point = ray.position(self.intersection.t);
+        let eyev = -ray.direction.normalize();
+        let geometric = self.instance.normal_at(point, &self.intersection);
+        let side = if geometric.dot(&eyev) < 0.0 {
+            -1.0
+        } else {
+            1.0
+        };
+        ShadingPoint {
+            point: point + geometric * (side * SHADOW_BIAS),
+            eyev,
+            normalv: self.instance.shading_normal_at(point, &self.intersection) * side,
+        }
+    }
+}
+
+impl World {
+    /// Return a new World.
+    pub fn new(objects: Vec<Instance>, lights: Vec<Box<dyn Light>>) -> Self {
+        World {
+            objects,
+            lights,
+            environment: None,
+            skybox: None,
+            fog: None,
+            volumes: vec![],
+        }
+    }
+
+    /// Add an emissive mesh placed by transform, both as an object and as a light
+    /// sampling material.emitted() over its surface with samples points.
+    pub fn add_mesh_light(
+        &mut self,
+        mesh: &TriangleMesh,
+        transform: Matrix,
+        material: Material,
+        samples: usize,
+    ) {
+        let light = MeshLight::new(mesh, transform, material.emitted(), samples);
+        let mut instance = Instance::new(Rc::new(mesh.to_group()), transform, material);
+        instance.is_light = true;
+        self.objects.push(instance);
+        self.lights.push(Box::new(light));
+    }
+
+    /// Returns every intersection of the ray with the objects, sorted by increasing t.
+    pub fn intersect(&self, ray: &Ray) -> Vec<Hit<'_>> {
+        let mut hits: Vec<Hit> = self
+            .objects
+            .iter()
+            .flat_map(|instance| {
+                instance
+                    .intersect(ray)
+                    .into_iter()
+                    .map(move |intersection| Hit {
+                        instance,
+                        intersection,
+                    })
+            })
+            .collect();
+        hits.sort_by(|a, b| a.intersection.t.total_cmp(&b.intersection.t));
+        hits
+    }
+
+    /// Returns the visible hit of the ray: the one with the lowest non-negative t.
+    pub fn hit(&self, ray: &Ray) -> Option<Hit<'_>> {
+        self.intersect(ray)
+            .into_iter()
+            .find(|hit| hit.intersection.t >= 0.0)
+    }
+
+    /// Returns true if an object blocks the sample of a light seen from point.
+    pub fn is_shadowed(&self, point: Point, sample: &LightSample) -> bool {
+        let ray = Ray::new(point, sample.direction);
+        self.objects.iter().any(|instance| {
+            instance
+                .intersect(&ray)
+                .iter()
+                .any(|x| x.t > 0.0 && x.t < sample.distance)
+        })
+    }
+
+    /// Returns where the ray first collides with a medium before t_max, and the medium.
+    /// Rays pass through media without colliding as often as their light would.
+    pub fn medium_collision(
+        &self,
+        ray: &Ray,
+        t_max: f64,
+        rng: &mut dyn RandomSource,
+    ) -> Option<(f64, &Medium)> {
+        let mut collision: Option<(f64, &Medium)> = None;
+        let mut nearest = t_max;
+        if let Some((fog, (start, end))) = self.fog.as_ref().zip(self.fog_segment(ray, t_max)) {
+            let fog = &fog.medium;
+            if let Some(t) = fog.sample_collision(ray, start, end, Matrix::identity(), rng) {
+                collision = Some((t, fog));
+                nearest = t;
+            }
+        }
+        for volume in &self.volumes {
+            for (start, end) in volume.segments(ray) {
+                if start >= nearest {
+                    break;
+                }
+                let end = end.min(nearest);
+                if let Some(t) =
+                    volume
+                        .medium
+                        .sample_collision(ray, start, end, volume.inverse(), rng)
+                {
+                    collision = Some((t, &volume.medium));
+                    nearest = t;
+                    break;
+                }
+            }
+        }
+        collision
+    }
+
+    /// Returns the fraction of the light of a sample making it to point, through
+    /// objects blocking it entirely and media thinning it out.
+    pub fn transmittance(
+        &self,
+        point: Point,
+        sample: &LightSample,
+        rng: &mut dyn RandomSource,
+    ) -> f64 {
+        if self.is_shadowed(point, sample) {
+            return 0.0;
+        }
+        let ray = Ray::new(point, sample.direction);
+        let mut transmittance = 1.0;
+        let segment = self.fog_segment(&ray, sample.distance);
+        if let Some((fog, (start, end))) = self.fog.as_ref().zip(segment) {
+            transmittance *= fog
+                .medium
+                .transmittance(&ray, start, end, Matrix::identity(), rng);
+        }
+        for volume in &self.volumes {
+            for (start, end) in volume.segments(&ray) {
+                transmittance *= volume.medium.transmittance(
+                    &ray,
+                    start,
+                    end.min(sample.distance),
+                    volume.inverse(),
+                    rng,
+                );
+            }
+        }
+        transmittance
+    }
+
+    /// Returns the part of a ray from 0 to t_max inside the sphere filled by the fog, if
+    /// it crosses it.
+    fn fog_segment(&self, ray: &Ray, t_max: f64) -> Option<(f64, f64)> {
+        let radius = self.fog.as_ref()?.radius;
+        let origin = ray.origin - Point::new(0, 0, 0);
+        let a = ray.direction.dot(&ray.direction);
+        let b = 2.0 * ray.direction.dot(&origin);
+        let c = origin.dot(&origin) - radius * radius;
+        let discriminant = b * b - 4.0 * a * c;
+        if a == 0.0 || discriminant < 0.0 {
+            return None;
+        }
+        let root = discriminant.sqrt();
+        let start = ((-b - root) / (2.0 * a)).max(0.0);
+        let end = ((-b + root) / (2.0 * a)).min(t_max);
+        (start < end).then_some((start, end))
+    }
+
+    /// Returns the light seen by a ray escaping the scene.
+    pub fn background(&self, ray: &Ray) -> Color {
+        if let Some(skybox) = &self.skybox {
+            return skybox.radiance(ray.direction);
+        }
+        match &self.environment {
+            Some(environment) => environment.radiance(ray.direction),
+            None => Color::new(0, 0, 0),
+        }
+    }
+
+    /// Returns the light leaving a hit toward the ray which found it, from direct
+    /// lighting and mirror reflections followed remaining more times. Light beneath
+    /// the surface of subsurface materials is left to the path tracer.
+    pub fn shade_hit(
+        &self,
+        hit: &Hit,
+        ray: &Ray,
+        rng: &mut dyn RandomSource,
+        remaining: usize,
+    ) -> Color {
+        let shading = hit.shading_point(ray);
+        let width = ray.width_at(hit.intersection.t);
+        let material = &hit
+            .instance
+            .material_at(shading.point, &hit.intersection, width);
+        let mut color = material.emitted();
+        let environment = self.environment.as_ref().map(|e| e as &dyn Light);
+        for light in self.lights.iter().map(|l| l.as_ref()).chain(environment) {
+            color = color
+                + lighting(material, light, &shading, rng, &mut |sample| {
+                    self.is_shadowed(shading.point, sample)
+                });
+        }
+
+        if material.reflective > 0.0 && remaining > 0 {
+            let reflectv = ray.direction.normalize().reflect(&shading.normalv);
+            let reflected = Ray::new(shading.point, reflectv).with_cone(width, ray.spread);
+            color = color + self.color_at(&reflected, rng, remaining - 1) * material.reflective;
+        }
+        color
+    }
+
+    /// Returns the color seen by a ray with Whitted-style shading: direct light from
+    /// each light, plus mirror reflections followed up to remaining times.
+    pub fn color_at(&self, ray: &Ray, rng: &mut dyn RandomSource, remaining: usize) -> Color {
+        match self.hit(ray) {
+            Some(hit) => self.shade_hit(&hit, ray, rng, remaining),
+            None => self.background(ray),
+        }
+    }
+}
+
+impl Default for World {
+    fn default() -> Self {
+        World::new(vec![], vec![])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bump::BumpMap;
+    use crate::canvas::Canvas;
+    use crate::color::Color;
+    use crate::image_texture::ImageTexture;
+    use crate::light::{DirectionalLight, PointLight};
+    use crate::ply::parse_ply;
+    use crate::random::Sequence;
+    use crate::rectangle::Rectangle;
+    use crate::sphere::Sphere;
+    use crate::tuple::Vector;
+    use crate::uv::{TextureMap, UvMapping};
+
+    /// The default world of the book: two concentric spheres lit from the upper left.
+    fn default_world() -> World {
+        let outer = Material {
+            color: Color::new(0.8, 1.0, 0.6),
+            diffuse: 0.7,
+            specular: 0.2,
+            ..Material::default()
+        };
+        World::new(
+            vec![
+                Instance::new(Rc::new(Sphere::default()), Matrix::identity(), outer),
+                Instance::new(
+                    Rc::new(Sphere::default()),
+                    Matrix::scaling(0.5, 0.5, 0.5),
+                    Material::default(),
+                ),
+            ],
+            vec![Box::new(PointLight::new(
+                Point::new(-10, 10, -10),
+                Color::new(1, 1, 1),
+            ))],
+        )
+    }
+
+    fn rng() -> Sequence {
+        Sequence::new(vec![0.5])
+    }
+
+    /// Intersect a world with a ray.
+    #[test]
+    fn intersect_world() {
+        let w = default_world();
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        let ts: Vec<f64> = w.intersect(&r).iter().map(|h| h.intersection.t).collect();
+        assert_eq!(ts, vec![4.0, 4.5, 5.5, 6.0]);
+    }
+
+    /// The hit, when an intersection occurs on the inside.
+    #[test]
+    fn hit_inside() {
+        let w = default_world();
+        let r = Ray::new(Point::new(0, 0, 0), Vector::new(0, 0, 1));
+        let hit = w.hit(&r).unwrap();
+        assert_eq!(hit.intersection.t, 0.5);
+        let shading = hit.shading_point(&r);
+        assert_eq!(shading.eyev, Vector::new(0, 0, -1));
+        assert_eq!(shading.normalv, Vector::new(0, 0, -1));
+        assert!(shading.point.z() < 0.5);
+    }
+
+    /// A bump tilts the normal facing the eye, but the point is still moved off the
+    /// surface along its geometry.
+    #[test]
+    fn hit_inside_bump() {
+        let mut w = default_world();
+        w.objects[1].material.bump = Some(Rc::new(BumpMap::new(Rc::new(|p: Point| p.x()))));
+        let r = Ray::new(Point::new(0, 0, 0), Vector::new(0, 0, 1));
+        let shading = w.hit(&r).unwrap().shading_point(&r);
+        assert_eq!(shading.normalv, Vector::new(1, 0, -1).normalize());
+        assert_eq!(shading.point, Point::new(0, 0, 0.5 - SHADOW_BIAS));
+    }
+
+    /// The vertex colors of a PLY mesh replace the color of its material.
+    #[test]
+    fn shade_hit_vertex_colors() {
+        let ply = "\
+ply
+format ascii 1.0
+element vertex 3
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+0 1 0 255 51 0
+-1 -1 0 255 51 0
+1 -1 0 255 51 0
+3 0 1 2
+";
+        let mut mesh = parse_ply(ply.as_bytes()).unwrap();
+        let world = |mesh: &TriangleMesh, color| {
+            let material = Material {
+                color,
+                specular: 0.0,
+                ..Material::default()
+            };
+            World::new(
+                vec![Instance::new(
+                    Rc::new(mesh.to_group()),
+                    Matrix::identity(),
+                    material,
+                )],
+                vec![Box::new(PointLight::new(
+                    Point::new(0, 0, -10),
+                    Color::new(1, 1, 1),
+                ))],
+            )
+        };
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        let colored = world(&mesh, Color::new(1, 1, 1)).color_at(&r, &mut rng(), 5);
+        assert_eq!(colored, Color::new(1, 0.2, 0));
+        mesh.colors = None;
+        let plain = world(&mesh, Color::new(1, 0.2, 0)).color_at(&r, &mut rng(), 5);
+        assert_eq!(colored, plain);
+    }
+
+    /// Rays standing for wide beams see the mipmaps of image textures.
+    #[test]
+    fn shade_hit_texture_footprint() {
+        let mut image = Canvas::new(2, 2);
+        image.write_pixel(1, 0, Color::new(1, 0, 0));
+        image.write_pixel(0, 1, Color::new(0, 1, 0));
+        image.write_pixel(1, 1, Color::new(0, 0, 1));
+        let texture = ImageTexture::new(image).with_mipmaps();
+        let material = Material {
+            ambient: 0.0,
+            diffuse: 1.0,
+            specular: 0.0,
+            pattern: Some(Rc::new(TextureMap::new(
+                Rc::new(texture),
+                UvMapping::Planar,
+            ))),
+            ..Material::default()
+        };
+        let plane = Rectangle::new(
+            Point::new(0, 0, 0),
+            Vector::new(0, 0, 1),
+            Vector::new(1, 0, 0),
+        );
+        let w = World::new(
+            vec![Instance::new(Rc::new(plane), Matrix::identity(), material)],
+            vec![Box::new(PointLight::new(
+                Point::new(0.25, 10, 0.75),
+                Color::new(1, 1, 1),
+            ))],
+        );
+        let r = Ray::new(Point::new(0.25, 5, 0.75), Vector::new(0, -1, 0));
+        assert_eq!(w.color_at(&r, &mut rng(), 5), Color::new(0, 0, 0));
+        // A beam two units wide covers the whole image, and sees its average.
+        let wide = r.with_cone(0.0, 0.4);
+        assert_eq!(
+            w.color_at(&wide, &mut rng(), 5),
+            Color::new(0.25, 0.25, 0.25)
+        );
+    }
+
+    /// The color when a ray misses.
+    #[test]
+    fn color_ray_misses() {
+        let w = default_world();
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 1, 0));
+        assert_eq!(w.color_at(&r, &mut rng(), 5), Color::new(0, 0, 0));
+    }
+
+    /// The color when a ray hits.
+    #[test]
+    fn color_ray_hits() {
+        let w = default_world();
+        let r = Ray::new(Point::new(0, 0, -5), Vector::new(0, 0, 1));
+        assert_eq!(
+            w.color_at(&r, &mut rng(), 5),
+            Color::new(0.38066, 0.47583, 0.2855)
+        );
+    }
+
+    /// The color with an intersection behind the ray.
+    #[test]
+    fn color_intersection_behind_ray() {
+        let mut w = default_world();
+        w.objects[0].material.ambient = 1.0;
+        w.objects[1].material.ambient = 1.0;
+        let r = Ray::new(Point::new(0, 0, 0.75), Vector::new(0, 0, -1));
+        assert_eq!(w.color_at(&r, &mut rng(), 5), Color::new(1, 1, 1));
+    }
+
+    /// Rays escaping the scene see the skybox rather than black.
+    #[test]
+    fn color_ray_misses_skybox() {
+        let mut w = default_world();
+        let mut canvas = Canvas::new(2, 2);
+        canvas.set_all_pixel(Color::new(0.2, 0.4, 0.8));
+        let side = || {
+            let mut canvas = Canvas::new(2, 2);
+            canvas.set_all_pixel(Color::new(0.1, 0.1, 0.1));
+            canvas
+        };
+        w.skybox = Some(Skybox::from_images(
+            side(),
+            side(),
+            side(),
+            side(),
+            canvas,
+            side(),
+        ));
+        let up = Ray::new(Point::new(0, 0, -5), Vector::new(0, 1, 0));
+        assert_eq!(w.color_at(&up, &mut rng(), 5), Color::new(0.2, 0.4, 0.8));
+        let ahead = Ray::new(Point::new(0, 5, -5), Vector::new(0, 0, 1));
+        assert_eq!(w.color_at(&ahead, &mut rng(), 5), Color::new(0.1, 0.1, 0.1));
+    }
+
+    /// Shadows between points and the light.
+    #[test]
+    fn is_shadowed() {
+        let w = default_world();
+        let light = PointLight::new(Point::new(-10, 10, -10), Color::new(1, 1, 1));
+        for (point, shadowed) in [
+            (Point::new(0, 10, 0), false),
+            (Point::new(10, -10, 10), true),
+            (Point::new(-20, 20, -20), false),
+            (Point::new(-2, 2, -2), false),
+        ] {
+            let sample = light.samples(point, &mut rng())[0];
+            assert_eq!(w.is_shadowed(point, &sample), shadowed);
+        }
+    }
+
+    /// Fog and volumes thin out the light reaching a point.
+    #[test]
+    fn transmittance_through_media() {
+        let mut w = World::default();
+        let light = PointLight::new(Point::new(0, 0, 4), Color::new(1, 1, 1));
+        let sample = light.samples(Point::new(0, 0, 0), &mut rng())[0];
+        assert_eq!(
+            w.transmittance(Point::new(0, 0, 0), &sample, &mut rng()),
+            1.0
+        );
+        w.fog = Some(Fog::new(Medium::new(0.1, 0.1), 10.0));
+        w.volumes.push(Volume::new(
+            Rc::new(Sphere::default()),
+            Matrix::translation(0, 0, 2),
+            Medium::new(0.0, 0.5),
+        ));
+        let expected = (-0.2f64 * 4.0 - 0.5 * 2.0).exp();
+        let t = w.transmittance(Point::new(0, 0, 0), &sample, &mut rng());
+        assert!((t - expected).abs() < 1e-9);
+    }
+
+    /// Light from afar crosses the fog up to its radius, and none of it from outside.
+    #[test]
+    fn transmittance_fog_radius() {
+        let w = World {
+            fog: Some(Fog::new(Medium::new(0.1, 0.1), 3.0)),
+            ..World::default()
+        };
+        let sun = DirectionalLight::new(Vector::new(0, -1, 0), Color::new(1, 1, 1));
+        let sample = sun.samples(Point::new(0, 0, 0), &mut rng())[0];
+        let t = w.transmittance(Point::new(0, 0, 0), &sample, &mut rng());
+        assert!((t - (-0.2f64 * 3.0).exp()).abs() < 1e-9);
+        let sample = sun.samples(Point::new(5, 0, 0), &mut rng())[0];
+        assert_eq!(
+            w.transmittance(Point::new(5, 0, 0), &sample, &mut rng()),
+            1.0
+        );
+    }
+
+    /// shade_hit() is given an intersection in shadow.
+    #[test]
+    fn shade_hit_in_shadow() {
+        let w = World::new(
+            vec![
+                Instance::new(
+                    Rc::new(Sphere::default()),
+                    Matrix::identity(),
+                    Material::default(),
+                ),
+                Instance::new(
+                    Rc::new(Sphere::default()),
+                    Matrix::translation(0, 0, 10),
+                    Material::default(),
+                ),
+            ],
+            vec![Box::new(PointLight::new(
+                Point::new(0, 0, -10),
+                Color::new(1, 1, 1),
+            ))],
+        );
+        let r = Ray::new(Point::new(0, 0, 5), Vector::new(0, 0, 1));
+        let hit = w.hit(&r).unwrap();
+        assert_eq!(
+            w.shade_hit(&hit, &r, &mut rng(), 5),
+            Color::new(0.1, 0.1, 0.1)
+        );
+    }
+
+    /// The reflected color for a reflective material.
+    #[test]
+    fn shade_hit_reflective() {
+        let mut w = default_world();
+        let floor = Material {
+            reflective: 0.5,
+            ..Material::default()
+        };
+        let plane = Rectangle::new(
+            Point::new(-100, 0, -100),
+            Vector::new(0, 0, 200),
+            Vector::new(200, 0, 0),
+        );
+        w.objects.push(Instance::new(
+            Rc::new(plane),
+            Matrix::translation(0, -1, 0),
+            floor,
+        ));
+        let k = 2f64.sqrt() / 2.0;
+        let r = Ray::new(Point::new(0, 0, -3), Vector::new(0, -k, k));
+        assert_eq!(
+            w.color_at(&r, &mut rng(), 5),
+            Color::new(0.87677, 0.92436, 0.82918)
+        );
+        // Without remaining reflections only the direct light is left.
+        assert_eq!(
+            w.color_at(&r, &mut rng(), 0),
+            Color::new(0.68643, 0.68643, 0.68643)
+        );
+    }
+
+    /// Mutually reflective surfaces stop after the remaining reflections.
+    #[test]
+    fn mutually_reflective_surfaces() {
+        let mirror = Material {
+            reflective: 1.0,
+            ..Material::default()
+        };
+        let w = World::new(
+            vec![
+                Instance::new(
+                    Rc::new(Rectangle::default()),
+                    Matrix::scaling(10, 1, 10).then(Matrix::translation(0, -1, 0)),
+                    mirror.clone(),
+                ),
+                Instance::new(
+                    Rc::new(Rectangle::default()),
+                    Matrix::scaling(10, 1, 10).then(Matrix::translation(0, 1, 0)),
+                    mirror,
+                ),
+            ],
+            vec![Box::new(PointLight::new(
+                Point::new(0, 0, 0),
+                Color::new(1, 1, 1),
+            ))],
+        );
+        let r = Ray::new(Point::new(0, 0, 0), Vector::new(0, 1, 0));
+        let c = w.color_at(&r, &mut rng(), 5);
+        assert!(c.red() > 0.0);
+    }
+}